@@ -0,0 +1,62 @@
+use anchor_lang::prelude::Pubkey;
+
+use anchor_escrow::merkle::{hash_pair, leaf};
+
+use crate::state::AllowlistProof;
+
+// Builds allowlist roots and proofs for `make` and `take`. Each entry is a taker
+// and the most `mint_b` they may pay in, `u64::MAX` for no cap. An odd node out
+// is carried up a level unchanged, so it adds nothing to the proof.
+pub struct Allowlist {
+    entries: Vec<(Pubkey, u64)>,
+    layers: Vec<Vec<[u8; 32]>>,
+}
+
+impl Allowlist {
+    pub fn new(entries: Vec<(Pubkey, u64)>) -> Self {
+        let mut layers = vec![entries
+            .iter()
+            .map(|(taker, max_fill)| leaf(taker, *max_fill))
+            .collect::<Vec<_>>()];
+
+        while layers.last().is_some_and(|layer| layer.len() > 1) {
+            let next = layers
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| match pair {
+                    [a, b] => hash_pair(a, b),
+                    [a] => *a,
+                    _ => unreachable!(),
+                })
+                .collect();
+            layers.push(next);
+        }
+
+        Allowlist { entries, layers }
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        self.layers
+            .last()
+            .and_then(|layer| layer.first())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    // `None` when the taker is not on the list
+    pub fn proof(&self, taker: &Pubkey) -> Option<AllowlistProof> {
+        let mut index = self.entries.iter().position(|(entry, _)| entry == taker)?;
+        let max_fill = self.entries[index].1;
+        let mut proof = Vec::new();
+
+        for layer in &self.layers[..self.layers.len() - 1] {
+            if let Some(sibling) = layer.get(index ^ 1) {
+                proof.push(*sibling);
+            }
+            index /= 2;
+        }
+
+        Some(AllowlistProof { max_fill, proof })
+    }
+}
@@ -0,0 +1,1203 @@
+use anchor_lang::{
+    prelude::{AccountMeta, Pubkey},
+    solana_program::{instruction::Instruction, sysvar},
+    system_program, InstructionData, ToAccountMetas,
+};
+use anchor_spl::{
+    associated_token::{self, get_associated_token_address_with_program_id},
+    token_2022,
+};
+
+use crate::pda::{
+    allowlist_fill_address, basket_address, config_address, counter_address, escrow_address,
+    event_authority_address, maker_state_address, offer_delegate_address, offer_nonces_address,
+    program_data_address, receipt_mint_address, vault_address,
+};
+use crate::state::{
+    AllowlistProof, BasketLeg, Pricing, ReservationTerms, SignedOffer, TimeWindows, Vesting,
+};
+
+// Each builder mirrors one program instruction. Leave a mint as `None` for a native SOL leg.
+// Token-2022 transfer-hook extra accounts can be appended to the built instruction's accounts.
+
+// Only the program's upgrade authority can create the config, once
+pub struct InitializeConfig {
+    pub admin: Pubkey,
+    pub fee_bps: u16,
+    pub treasury: Pubkey,
+}
+
+impl InitializeConfig {
+    pub fn instruction(&self) -> Instruction {
+        Instruction {
+            program_id: anchor_escrow::ID,
+            accounts: anchor_escrow::accounts::InitializeConfig {
+                admin: self.admin,
+                program: anchor_escrow::ID,
+                program_data: program_data_address(),
+                config: config_address(),
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: anchor_escrow::instruction::InitializeConfig {
+                fee_bps: self.fee_bps,
+                treasury: self.treasury,
+            }
+            .data(),
+        }
+    }
+}
+
+// Signed by the config's `admin`
+pub struct UpdateConfig {
+    pub admin: Pubkey,
+}
+
+impl UpdateConfig {
+    pub fn update_config(&self, fee_bps: u16, treasury: Pubkey) -> Instruction {
+        self.instruction(anchor_escrow::instruction::UpdateConfig { fee_bps, treasury }.data())
+    }
+
+    pub fn set_paused(&self, paused: bool) -> Instruction {
+        self.instruction(anchor_escrow::instruction::SetPaused { paused }.data())
+    }
+
+    fn instruction(&self, data: Vec<u8>) -> Instruction {
+        Instruction {
+            program_id: anchor_escrow::ID,
+            accounts: anchor_escrow::accounts::UpdateConfig {
+                admin: self.admin,
+                config: config_address(),
+            }
+            .to_account_metas(None),
+            data,
+        }
+    }
+}
+
+// `seed` is not sent, the program assigns it, but it has to be the maker's current
+// `MakerState::next_seed` (0 for a first make) for the escrow address to line up.
+// Set `receipt` to mint the maker a transferable receipt for the escrow.
+pub struct Make {
+    pub maker: Pubkey,
+    pub seed: u64,
+    pub mint_a: Option<Pubkey>,
+    pub mint_b: Option<Pubkey>,
+    pub deposit: u64,
+    pub receive: u64,
+    pub windows: TimeWindows,
+    pub allowed_taker: Option<Pubkey>,
+    pub allowlist_root: Option<[u8; 32]>,
+    pub receive_net_of_fee: bool,
+    pub pricing: Pricing,
+    pub vesting: Option<Vesting>,
+    pub arbiter: Option<Pubkey>,
+    pub receipt: bool,
+    pub token_program: Pubkey,
+}
+
+impl Make {
+    pub fn instruction(&self) -> Instruction {
+        let escrow = escrow_address(&self.maker, self.seed);
+        let receipt_mint = Some(receipt_mint_address(&escrow)).filter(|_| self.receipt);
+
+        Instruction {
+            program_id: anchor_escrow::ID,
+            accounts: anchor_escrow::accounts::Make {
+                maker: self.maker,
+                mint_a: self.mint_a,
+                mint_b: self.mint_b,
+                maker_ata_a: self.mint_a.map(|mint| self.ata(&self.maker, &mint)),
+                config: config_address(),
+                maker_state: maker_state_address(&self.maker),
+                escrow,
+                vault: self.mint_a.map(|mint| vault_address(&escrow, &mint, &self.token_program)),
+                receipt_mint,
+                maker_receipt: receipt_mint.map(|mint| receipt_address(&self.maker, &mint)),
+                receipt_token_program: receipt_mint.map(|_| token_2022::ID),
+                associated_token_program: associated_token::ID,
+                token_program: self.token_program,
+                system_program: system_program::ID,
+                event_authority: event_authority_address(),
+                program: anchor_escrow::ID,
+            }
+            .to_account_metas(None),
+            data: anchor_escrow::instruction::Make {
+                deposit: self.deposit,
+                receive: self.receive,
+                windows: self.windows,
+                allowed_taker: self.allowed_taker,
+                allowlist_root: self.allowlist_root,
+                receive_net_of_fee: self.receive_net_of_fee,
+                pricing: self.pricing,
+                vesting: self.vesting,
+                arbiter: self.arbiter,
+            }
+            .data(),
+        }
+    }
+
+    fn ata(&self, owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(owner, mint, &self.token_program)
+    }
+}
+
+// `seed` works as for `Make`, baskets and escrows share the maker's `next_seed`.
+// `offered` pairs each mint with the amount to deposit. All legs use `token_program`.
+pub struct MakeBasket {
+    pub maker: Pubkey,
+    pub seed: u64,
+    pub offered: Vec<(Pubkey, u64)>,
+    pub requested: Vec<BasketLeg>,
+    pub token_program: Pubkey,
+}
+
+impl MakeBasket {
+    pub fn instruction(&self) -> Instruction {
+        let basket = basket_address(&self.maker, self.seed);
+
+        let mut accounts = anchor_escrow::accounts::MakeBasket {
+            maker: self.maker,
+            config: config_address(),
+            maker_state: maker_state_address(&self.maker),
+            basket,
+            associated_token_program: associated_token::ID,
+            token_program: self.token_program,
+            system_program: system_program::ID,
+            event_authority: event_authority_address(),
+            program: anchor_escrow::ID,
+        }
+        .to_account_metas(None);
+        for (mint, _) in &self.offered {
+            accounts.extend([
+                AccountMeta::new_readonly(*mint, false),
+                AccountMeta::new(self.ata(&self.maker, mint), false),
+                AccountMeta::new(self.ata(&basket, mint), false),
+            ]);
+        }
+
+        Instruction {
+            program_id: anchor_escrow::ID,
+            accounts,
+            data: anchor_escrow::instruction::MakeBasket {
+                deposits: self.offered.iter().map(|(_, deposit)| *deposit).collect(),
+                requested: self.requested.clone(),
+            }
+            .data(),
+        }
+    }
+
+    fn ata(&self, owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(owner, mint, &self.token_program)
+    }
+}
+
+// `offered` and `requested` are the basket's leg mints, in the order stored on it.
+// Missing ATAs on the receiving side are created, paid for by the taker.
+pub struct TakeBasket {
+    pub taker: Pubkey,
+    pub maker: Pubkey,
+    pub seed: u64,
+    pub offered: Vec<Pubkey>,
+    pub requested: Vec<Pubkey>,
+    pub treasury: Pubkey,
+    pub token_program: Pubkey,
+}
+
+impl TakeBasket {
+    pub fn instruction(&self) -> Instruction {
+        let basket = basket_address(&self.maker, self.seed);
+
+        let mut accounts = anchor_escrow::accounts::TakeBasket {
+            taker: self.taker,
+            maker: self.maker,
+            basket,
+            config: config_address(),
+            treasury: self.treasury,
+            associated_token_program: associated_token::ID,
+            token_program: self.token_program,
+            system_program: system_program::ID,
+            event_authority: event_authority_address(),
+            program: anchor_escrow::ID,
+        }
+        .to_account_metas(None);
+        for mint in &self.offered {
+            accounts.extend([
+                AccountMeta::new_readonly(*mint, false),
+                AccountMeta::new(self.ata(&basket, mint), false),
+                AccountMeta::new(self.ata(&self.taker, mint), false),
+            ]);
+        }
+        for mint in &self.requested {
+            accounts.extend([
+                AccountMeta::new_readonly(*mint, false),
+                AccountMeta::new(self.ata(&self.taker, mint), false),
+                AccountMeta::new(self.ata(&self.maker, mint), false),
+                AccountMeta::new(self.ata(&self.treasury, mint), false),
+            ]);
+        }
+
+        Instruction {
+            program_id: anchor_escrow::ID,
+            accounts,
+            data: anchor_escrow::instruction::TakeBasket {}.data(),
+        }
+    }
+
+    fn ata(&self, owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(owner, mint, &self.token_program)
+    }
+}
+
+// `offered` are the basket's leg mints, in the order stored on it
+pub struct RefundBasket {
+    pub maker: Pubkey,
+    pub seed: u64,
+    pub offered: Vec<Pubkey>,
+    pub token_program: Pubkey,
+}
+
+impl RefundBasket {
+    pub fn instruction(&self) -> Instruction {
+        let basket = basket_address(&self.maker, self.seed);
+
+        let mut accounts = anchor_escrow::accounts::RefundBasket {
+            maker: self.maker,
+            basket,
+            token_program: self.token_program,
+            system_program: system_program::ID,
+            event_authority: event_authority_address(),
+            program: anchor_escrow::ID,
+        }
+        .to_account_metas(None);
+        for mint in &self.offered {
+            accounts.extend([
+                AccountMeta::new_readonly(*mint, false),
+                AccountMeta::new(self.ata(&basket, mint), false),
+                AccountMeta::new(self.ata(&self.maker, mint), false),
+            ]);
+        }
+
+        Instruction {
+            program_id: anchor_escrow::ID,
+            accounts,
+            data: anchor_escrow::instruction::RefundBasket {}.data(),
+        }
+    }
+
+    fn ata(&self, owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(owner, mint, &self.token_program)
+    }
+}
+
+// `make` for a maker that signs through `invoke_signed`, mostly useful to build the accounts
+// for a CPI. `payer` funds the new accounts and the deposit comes from `maker_token_a`,
+// any `mint_a` account the maker owns. Both legs are SPL tokens.
+pub struct MakeFor {
+    pub payer: Pubkey,
+    pub maker: Pubkey,
+    pub seed: u64,
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    pub maker_token_a: Pubkey,
+    pub proceeds_to: Pubkey,
+    pub refund_to: Pubkey,
+    pub deposit: u64,
+    pub receive: u64,
+    pub windows: TimeWindows,
+    pub allowed_taker: Option<Pubkey>,
+    pub receive_net_of_fee: bool,
+    pub pricing: Pricing,
+    pub token_program: Pubkey,
+}
+
+impl MakeFor {
+    pub fn instruction(&self) -> Instruction {
+        let escrow = escrow_address(&self.maker, self.seed);
+
+        Instruction {
+            program_id: anchor_escrow::ID,
+            accounts: anchor_escrow::accounts::MakeFor {
+                payer: self.payer,
+                maker: self.maker,
+                mint_a: self.mint_a,
+                mint_b: self.mint_b,
+                maker_token_a: self.maker_token_a,
+                proceeds_to: self.proceeds_to,
+                refund_to: self.refund_to,
+                config: config_address(),
+                maker_state: maker_state_address(&self.maker),
+                escrow,
+                vault: vault_address(&escrow, &self.mint_a, &self.token_program),
+                associated_token_program: associated_token::ID,
+                token_program: self.token_program,
+                system_program: system_program::ID,
+                event_authority: event_authority_address(),
+                program: anchor_escrow::ID,
+            }
+            .to_account_metas(None),
+            data: anchor_escrow::instruction::MakeFor {
+                deposit: self.deposit,
+                receive: self.receive,
+                windows: self.windows,
+                allowed_taker: self.allowed_taker,
+                receive_net_of_fee: self.receive_net_of_fee,
+                pricing: self.pricing,
+            }
+            .data(),
+        }
+    }
+}
+
+// `treasury` has to match the config's treasury, it collects the protocol fee on the B leg.
+// `max_payment` caps what leaves the taker's account, protocol fee and any grossed-up transfer
+// fee included. It matters for auction and oracle-priced escrows, and `price_update` is the
+// Pyth price account the latter are quoted from.
+// Set `arbitrated` for escrows with an arbiter, which hold the payment in a second vault,
+// and `allowlist` for escrows with an allowlist root (see `crate::allowlist`).
+// For receipt escrows, `receipt_holder` is whoever holds the receipt and receives the proceeds.
+// Escrows made with `make_for` pay out to their `proceeds_to` account instead.
+pub struct Take {
+    pub taker: Pubkey,
+    pub maker: Pubkey,
+    pub seed: u64,
+    pub mint_a: Option<Pubkey>,
+    pub mint_b: Option<Pubkey>,
+    pub treasury: Pubkey,
+    pub expected_version: u32,
+    pub max_payment: u64,
+    pub allowlist: Option<AllowlistProof>,
+    pub price_update: Option<Pubkey>,
+    pub arbitrated: bool,
+    pub receipt_holder: Option<Pubkey>,
+    pub proceeds_to: Option<Pubkey>,
+    pub token_program: Pubkey,
+}
+
+impl Take {
+    pub fn instruction(&self) -> Instruction {
+        let escrow = escrow_address(&self.maker, self.seed);
+        let owner = self.receipt_holder.unwrap_or(self.maker);
+
+        Instruction {
+            program_id: anchor_escrow::ID,
+            accounts: anchor_escrow::accounts::Take {
+                taker: self.taker,
+                maker: owner,
+                mint_a: self.mint_a,
+                mint_b: self.mint_b,
+                taker_ata_a: self.mint_a.map(|mint| self.ata(&self.taker, &mint)),
+                taker_ata_b: self.mint_b.map(|mint| self.ata(&self.taker, &mint)),
+                maker_ata_b: self
+                    .mint_b
+                    .filter(|_| self.proceeds_to.is_none())
+                    .map(|mint| self.ata(&owner, &mint)),
+                proceeds_to: self.proceeds_to,
+                config: config_address(),
+                treasury: self.treasury,
+                treasury_ata_b: self.mint_b.map(|mint| self.ata(&self.treasury, &mint)),
+                escrow,
+                receipt: self
+                    .receipt_holder
+                    .map(|holder| receipt_address(&holder, &receipt_mint_address(&escrow))),
+                maker_state: maker_state_address(&self.maker),
+                vault: self.mint_a.map(|mint| vault_address(&escrow, &mint, &self.token_program)),
+                vault_b: self
+                    .mint_b
+                    .filter(|_| self.arbitrated)
+                    .map(|mint| vault_address(&escrow, &mint, &self.token_program)),
+                allowlist_fill: self
+                    .allowlist
+                    .as_ref()
+                    .map(|_| allowlist_fill_address(&escrow, &self.taker)),
+                price_update: self.price_update,
+                associated_token_program: associated_token::ID,
+                token_program: self.token_program,
+                system_program: system_program::ID,
+                event_authority: event_authority_address(),
+                program: anchor_escrow::ID,
+            }
+            .to_account_metas(None),
+            data: anchor_escrow::instruction::Take {
+                expected_version: self.expected_version,
+                max_payment: self.max_payment,
+                allowlist: self.allowlist.clone(),
+            }
+            .data(),
+        }
+    }
+
+    fn ata(&self, owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(owner, mint, &self.token_program)
+    }
+}
+
+// Buys `amount_b` worth of the deposit at the escrow's price, leaving the rest open.
+// The accounts follow `Take`, without the arbiter vault or a price update.
+pub struct TakePartial {
+    pub taker: Pubkey,
+    pub maker: Pubkey,
+    pub seed: u64,
+    pub mint_a: Option<Pubkey>,
+    pub mint_b: Option<Pubkey>,
+    pub treasury: Pubkey,
+    pub amount_b: u64,
+    pub expected_version: u32,
+    pub allowlist: Option<AllowlistProof>,
+    pub receipt_holder: Option<Pubkey>,
+    pub proceeds_to: Option<Pubkey>,
+    pub token_program: Pubkey,
+}
+
+impl TakePartial {
+    pub fn instruction(&self) -> Instruction {
+        let escrow = escrow_address(&self.maker, self.seed);
+        let owner = self.receipt_holder.unwrap_or(self.maker);
+
+        Instruction {
+            program_id: anchor_escrow::ID,
+            accounts: anchor_escrow::accounts::TakePartial {
+                taker: self.taker,
+                maker: owner,
+                mint_a: self.mint_a,
+                mint_b: self.mint_b,
+                taker_ata_a: self.mint_a.map(|mint| self.ata(&self.taker, &mint)),
+                taker_ata_b: self.mint_b.map(|mint| self.ata(&self.taker, &mint)),
+                maker_ata_b: self
+                    .mint_b
+                    .filter(|_| self.proceeds_to.is_none())
+                    .map(|mint| self.ata(&owner, &mint)),
+                proceeds_to: self.proceeds_to,
+                config: config_address(),
+                treasury: self.treasury,
+                treasury_ata_b: self.mint_b.map(|mint| self.ata(&self.treasury, &mint)),
+                escrow,
+                receipt: self
+                    .receipt_holder
+                    .map(|holder| receipt_address(&holder, &receipt_mint_address(&escrow))),
+                maker_state: maker_state_address(&self.maker),
+                vault: self.mint_a.map(|mint| vault_address(&escrow, &mint, &self.token_program)),
+                allowlist_fill: self
+                    .allowlist
+                    .as_ref()
+                    .map(|_| allowlist_fill_address(&escrow, &self.taker)),
+                associated_token_program: associated_token::ID,
+                token_program: self.token_program,
+                system_program: system_program::ID,
+                event_authority: event_authority_address(),
+                program: anchor_escrow::ID,
+            }
+            .to_account_metas(None),
+            data: anchor_escrow::instruction::TakePartial {
+                amount_b: self.amount_b,
+                expected_version: self.expected_version,
+                allowlist: self.allowlist.clone(),
+            }
+            .data(),
+        }
+    }
+
+    fn ata(&self, owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(owner, mint, &self.token_program)
+    }
+}
+
+// Sweeps several escrows for one token pair, given as `(maker, seed)`, cheapest first is up to
+// the caller. The total debited across all of them, fees included, is capped by
+// `max_total_payment`.
+pub struct TakeMany {
+    pub taker: Pubkey,
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    pub treasury: Pubkey,
+    pub escrows: Vec<(Pubkey, u64)>,
+    pub max_total_payment: u64,
+    pub token_program: Pubkey,
+}
+
+impl TakeMany {
+    pub fn instruction(&self) -> Instruction {
+        let mut accounts = anchor_escrow::accounts::TakeMany {
+            taker: self.taker,
+            mint_a: self.mint_a,
+            mint_b: self.mint_b,
+            taker_ata_a: self.ata(&self.taker, &self.mint_a),
+            taker_ata_b: self.ata(&self.taker, &self.mint_b),
+            config: config_address(),
+            treasury: self.treasury,
+            treasury_ata_b: Some(self.ata(&self.treasury, &self.mint_b)),
+            associated_token_program: associated_token::ID,
+            token_program: self.token_program,
+            system_program: system_program::ID,
+            event_authority: event_authority_address(),
+            program: anchor_escrow::ID,
+        }
+        .to_account_metas(None);
+
+        for (maker, seed) in &self.escrows {
+            let escrow = escrow_address(maker, *seed);
+            accounts.extend([
+                AccountMeta::new(escrow, false),
+                AccountMeta::new(vault_address(&escrow, &self.mint_a, &self.token_program), false),
+                AccountMeta::new(*maker, false),
+                AccountMeta::new(self.ata(maker, &self.mint_b), false),
+                AccountMeta::new(maker_state_address(maker), false),
+            ]);
+        }
+
+        Instruction {
+            program_id: anchor_escrow::ID,
+            accounts,
+            data: anchor_escrow::instruction::TakeMany {
+                max_total_payment: self.max_total_payment,
+            }
+            .data(),
+        }
+    }
+
+    fn ata(&self, owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(owner, mint, &self.token_program)
+    }
+}
+
+// Crosses the escrow `(maker_x, seed_x)` offering `mint_a` for `mint_b` with
+// `(maker_y, seed_y)` offering `mint_b` for `mint_a`. The matcher keeps the spread.
+pub struct MatchEscrows {
+    pub matcher: Pubkey,
+    pub maker_x: Pubkey,
+    pub seed_x: u64,
+    pub maker_y: Pubkey,
+    pub seed_y: u64,
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    pub treasury: Pubkey,
+    pub token_program: Pubkey,
+}
+
+impl MatchEscrows {
+    pub fn instruction(&self) -> Instruction {
+        let escrow_x = escrow_address(&self.maker_x, self.seed_x);
+        let escrow_y = escrow_address(&self.maker_y, self.seed_y);
+
+        Instruction {
+            program_id: anchor_escrow::ID,
+            accounts: anchor_escrow::accounts::MatchEscrows {
+                matcher: self.matcher,
+                maker_x: self.maker_x,
+                maker_y: self.maker_y,
+                mint_a: self.mint_a,
+                mint_b: self.mint_b,
+                maker_x_ata_b: self.ata(&self.maker_x, &self.mint_b),
+                maker_y_ata_a: self.ata(&self.maker_y, &self.mint_a),
+                matcher_ata_a: self.ata(&self.matcher, &self.mint_a),
+                matcher_ata_b: self.ata(&self.matcher, &self.mint_b),
+                config: config_address(),
+                treasury: self.treasury,
+                treasury_ata_a: Some(self.ata(&self.treasury, &self.mint_a)),
+                treasury_ata_b: Some(self.ata(&self.treasury, &self.mint_b)),
+                escrow_x,
+                escrow_y,
+                maker_state_x: maker_state_address(&self.maker_x),
+                maker_state_y: maker_state_address(&self.maker_y),
+                vault_x: vault_address(&escrow_x, &self.mint_a, &self.token_program),
+                vault_y: vault_address(&escrow_y, &self.mint_b, &self.token_program),
+                associated_token_program: associated_token::ID,
+                token_program: self.token_program,
+                system_program: system_program::ID,
+                event_authority: event_authority_address(),
+                program: anchor_escrow::ID,
+            }
+            .to_account_metas(None),
+            data: anchor_escrow::instruction::MatchEscrows {}.data(),
+        }
+    }
+
+    fn ata(&self, owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(owner, mint, &self.token_program)
+    }
+}
+
+// Fills an offer `maker` signed off-chain. Send it right after
+// `offer_signature_instruction` for the same offer, in one transaction.
+pub struct TakeOffer {
+    pub taker: Pubkey,
+    pub maker: Pubkey,
+    pub offer: SignedOffer,
+    pub treasury: Pubkey,
+    pub token_program: Pubkey,
+}
+
+impl TakeOffer {
+    pub fn instruction(&self) -> Instruction {
+        let offer = &self.offer;
+
+        Instruction {
+            program_id: anchor_escrow::ID,
+            accounts: anchor_escrow::accounts::TakeOffer {
+                taker: self.taker,
+                maker: self.maker,
+                delegate: offer_delegate_address(&self.maker),
+                mint_a: offer.mint_a,
+                mint_b: offer.mint_b,
+                maker_ata_a: self.ata(&self.maker, &offer.mint_a),
+                maker_ata_b: self.ata(&self.maker, &offer.mint_b),
+                taker_ata_a: self.ata(&self.taker, &offer.mint_a),
+                taker_ata_b: self.ata(&self.taker, &offer.mint_b),
+                config: config_address(),
+                treasury: self.treasury,
+                treasury_ata_b: Some(self.ata(&self.treasury, &offer.mint_b)),
+                offer_nonces: offer_nonces_address(&self.maker, offer.nonce),
+                instructions_sysvar: sysvar::instructions::ID,
+                associated_token_program: associated_token::ID,
+                token_program: self.token_program,
+                system_program: system_program::ID,
+                event_authority: event_authority_address(),
+                program: anchor_escrow::ID,
+            }
+            .to_account_metas(None),
+            data: anchor_escrow::instruction::TakeOffer {
+                offer: offer.clone(),
+            }
+            .data(),
+        }
+    }
+
+    fn ata(&self, owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(owner, mint, &self.token_program)
+    }
+}
+
+// Burns a signed offer's nonce so it can no longer be taken
+pub struct CancelOffer {
+    pub maker: Pubkey,
+    pub nonce: u64,
+}
+
+impl CancelOffer {
+    pub fn instruction(&self) -> Instruction {
+        Instruction {
+            program_id: anchor_escrow::ID,
+            accounts: anchor_escrow::accounts::CancelOffer {
+                maker: self.maker,
+                offer_nonces: offer_nonces_address(&self.maker, self.nonce),
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: anchor_escrow::instruction::CancelOffer { nonce: self.nonce }.data(),
+        }
+    }
+}
+
+// For receipt escrows, `receipt_holder` refunds instead of the maker and gets the deposit back.
+// Escrows made with `make_for` refund to their `refund_to` account instead.
+// Refunding under an active reservation needs the `reserver`, who is paid a penalty.
+pub struct Refund {
+    pub maker: Pubkey,
+    pub seed: u64,
+    pub mint_a: Option<Pubkey>,
+    pub receipt_holder: Option<Pubkey>,
+    pub refund_to: Option<Pubkey>,
+    pub reserver: Option<Pubkey>,
+    pub token_program: Pubkey,
+}
+
+impl Refund {
+    pub fn instruction(&self) -> Instruction {
+        let escrow = escrow_address(&self.maker, self.seed);
+        let owner = self.receipt_holder.unwrap_or(self.maker);
+
+        Instruction {
+            program_id: anchor_escrow::ID,
+            accounts: anchor_escrow::accounts::Refund {
+                maker: owner,
+                mint_a: self.mint_a,
+                maker_ata_a: self.mint_a.filter(|_| self.refund_to.is_none()).map(|mint| {
+                    get_associated_token_address_with_program_id(
+                        &owner,
+                        &mint,
+                        &self.token_program,
+                    )
+                }),
+                refund_to: self.refund_to,
+                escrow,
+                receipt: self
+                    .receipt_holder
+                    .map(|holder| receipt_address(&holder, &receipt_mint_address(&escrow))),
+                reserver: self.reserver,
+                maker_state: maker_state_address(&self.maker),
+                vault: self.mint_a.map(|mint| vault_address(&escrow, &mint, &self.token_program)),
+                token_program: self.token_program,
+                system_program: system_program::ID,
+                event_authority: event_authority_address(),
+                program: anchor_escrow::ID,
+            }
+            .to_account_metas(None),
+            data: anchor_escrow::instruction::Refund {}.data(),
+        }
+    }
+}
+
+// Permissionless once the escrow has expired, signed by the transaction's fee payer only.
+// The deposit goes back the same way as with `Refund`.
+pub struct Expire {
+    pub maker: Pubkey,
+    pub seed: u64,
+    pub mint_a: Option<Pubkey>,
+    pub receipt_holder: Option<Pubkey>,
+    pub refund_to: Option<Pubkey>,
+    pub token_program: Pubkey,
+}
+
+impl Expire {
+    pub fn instruction(&self) -> Instruction {
+        let escrow = escrow_address(&self.maker, self.seed);
+        let owner = self.receipt_holder.unwrap_or(self.maker);
+
+        Instruction {
+            program_id: anchor_escrow::ID,
+            accounts: anchor_escrow::accounts::Expire {
+                maker: owner,
+                mint_a: self.mint_a,
+                maker_ata_a: self.mint_a.filter(|_| self.refund_to.is_none()).map(|mint| {
+                    get_associated_token_address_with_program_id(
+                        &owner,
+                        &mint,
+                        &self.token_program,
+                    )
+                }),
+                refund_to: self.refund_to,
+                escrow,
+                receipt: self
+                    .receipt_holder
+                    .map(|holder| receipt_address(&holder, &receipt_mint_address(&escrow))),
+                maker_state: maker_state_address(&self.maker),
+                vault: self.mint_a.map(|mint| vault_address(&escrow, &mint, &self.token_program)),
+                token_program: self.token_program,
+                system_program: system_program::ID,
+                event_authority: event_authority_address(),
+                program: anchor_escrow::ID,
+            }
+            .to_account_metas(None),
+            data: anchor_escrow::instruction::Expire {}.data(),
+        }
+    }
+}
+
+// `None` stops new reservations of the escrow
+pub struct SetReservationTerms {
+    pub maker: Pubkey,
+    pub seed: u64,
+    pub terms: Option<ReservationTerms>,
+}
+
+impl SetReservationTerms {
+    pub fn instruction(&self) -> Instruction {
+        Instruction {
+            program_id: anchor_escrow::ID,
+            accounts: anchor_escrow::accounts::SetReservationTerms {
+                maker: self.maker,
+                escrow: escrow_address(&self.maker, self.seed),
+                event_authority: event_authority_address(),
+                program: anchor_escrow::ID,
+            }
+            .to_account_metas(None),
+            data: anchor_escrow::instruction::SetReservationTerms { terms: self.terms }.data(),
+        }
+    }
+}
+
+// The taker posts the bond from the escrow's `ReservationTerms` for `duration` seconds
+pub struct Reserve {
+    pub taker: Pubkey,
+    pub maker: Pubkey,
+    pub seed: u64,
+    pub duration: i64,
+}
+
+impl Reserve {
+    pub fn instruction(&self) -> Instruction {
+        Instruction {
+            program_id: anchor_escrow::ID,
+            accounts: anchor_escrow::accounts::Reserve {
+                taker: self.taker,
+                maker: self.maker,
+                config: config_address(),
+                escrow: escrow_address(&self.maker, self.seed),
+                system_program: system_program::ID,
+                event_authority: event_authority_address(),
+                program: anchor_escrow::ID,
+            }
+            .to_account_metas(None),
+            data: anchor_escrow::instruction::Reserve {
+                duration: self.duration,
+            }
+            .data(),
+        }
+    }
+}
+
+// A `funded` counter moves `amount_b` into the counter up front, otherwise the taker's
+// `mint_b` account delegates it to the counter. Native SOL counters must be funded.
+// An account has one delegate, so an unfunded counter can no longer be accepted once the
+// taker revokes it or posts another unfunded counter from the same account.
+pub struct PostCounter {
+    pub taker: Pubkey,
+    pub maker: Pubkey,
+    pub seed: u64,
+    pub mint_b: Option<Pubkey>,
+    pub amount_a: u64,
+    pub amount_b: u64,
+    pub funded: bool,
+    pub token_program: Pubkey,
+}
+
+impl PostCounter {
+    pub fn instruction(&self) -> Instruction {
+        let escrow = escrow_address(&self.maker, self.seed);
+        let counter = counter_address(&escrow, &self.taker);
+
+        Instruction {
+            program_id: anchor_escrow::ID,
+            accounts: anchor_escrow::accounts::PostCounter {
+                taker: self.taker,
+                mint_b: self.mint_b,
+                taker_ata_b: self.mint_b.map(|mint| self.ata(&self.taker, &mint)),
+                escrow,
+                counter,
+                counter_vault: self
+                    .mint_b
+                    .filter(|_| self.funded)
+                    .map(|mint| self.ata(&counter, &mint)),
+                associated_token_program: associated_token::ID,
+                token_program: self.token_program,
+                system_program: system_program::ID,
+                event_authority: event_authority_address(),
+                program: anchor_escrow::ID,
+            }
+            .to_account_metas(None),
+            data: anchor_escrow::instruction::PostCounter {
+                amount_a: self.amount_a,
+                amount_b: self.amount_b,
+                funded: self.funded,
+            }
+            .data(),
+        }
+    }
+
+    fn ata(&self, owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(owner, mint, &self.token_program)
+    }
+}
+
+// `treasury` has to match the config's treasury. `funded` must match how the counter
+// was posted. For receipt escrows, `receipt_holder` accepts instead of the maker.
+pub struct AcceptCounter {
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    pub seed: u64,
+    pub mint_a: Option<Pubkey>,
+    pub mint_b: Option<Pubkey>,
+    pub treasury: Pubkey,
+    pub funded: bool,
+    pub receipt_holder: Option<Pubkey>,
+    pub token_program: Pubkey,
+}
+
+impl AcceptCounter {
+    pub fn instruction(&self) -> Instruction {
+        let escrow = escrow_address(&self.maker, self.seed);
+        let counter = counter_address(&escrow, &self.taker);
+        let owner = self.receipt_holder.unwrap_or(self.maker);
+
+        Instruction {
+            program_id: anchor_escrow::ID,
+            accounts: anchor_escrow::accounts::AcceptCounter {
+                maker: owner,
+                taker: self.taker,
+                mint_a: self.mint_a,
+                mint_b: self.mint_b,
+                taker_ata_a: self.mint_a.map(|mint| self.ata(&self.taker, &mint)),
+                taker_ata_b: self
+                    .mint_b
+                    .filter(|_| !self.funded)
+                    .map(|mint| self.ata(&self.taker, &mint)),
+                maker_ata_a: self.mint_a.map(|mint| self.ata(&owner, &mint)),
+                maker_ata_b: self.mint_b.map(|mint| self.ata(&owner, &mint)),
+                config: config_address(),
+                treasury: self.treasury,
+                treasury_ata_b: self.mint_b.map(|mint| self.ata(&self.treasury, &mint)),
+                escrow,
+                receipt: self
+                    .receipt_holder
+                    .map(|holder| receipt_address(&holder, &receipt_mint_address(&escrow))),
+                maker_state: maker_state_address(&self.maker),
+                counter,
+                vault: self.mint_a.map(|mint| vault_address(&escrow, &mint, &self.token_program)),
+                counter_vault: self
+                    .mint_b
+                    .filter(|_| self.funded)
+                    .map(|mint| self.ata(&counter, &mint)),
+                associated_token_program: associated_token::ID,
+                token_program: self.token_program,
+                system_program: system_program::ID,
+                event_authority: event_authority_address(),
+                program: anchor_escrow::ID,
+            }
+            .to_account_metas(None),
+            data: anchor_escrow::instruction::AcceptCounter {}.data(),
+        }
+    }
+
+    fn ata(&self, owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(owner, mint, &self.token_program)
+    }
+}
+
+// `authority` is the maker (or `receipt_holder`) rejecting, or the taker withdrawing.
+// Set `escrow_closed` once the escrow is gone, only the taker can withdraw then.
+pub struct RejectCounter {
+    pub authority: Pubkey,
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    pub seed: u64,
+    pub mint_b: Option<Pubkey>,
+    pub funded: bool,
+    pub receipt_holder: Option<Pubkey>,
+    pub escrow_closed: bool,
+    pub token_program: Pubkey,
+}
+
+impl RejectCounter {
+    pub fn instruction(&self) -> Instruction {
+        let escrow = escrow_address(&self.maker, self.seed);
+        let counter = counter_address(&escrow, &self.taker);
+        let funded_mint_b = self.mint_b.filter(|_| self.funded);
+
+        Instruction {
+            program_id: anchor_escrow::ID,
+            accounts: anchor_escrow::accounts::RejectCounter {
+                authority: self.authority,
+                taker: self.taker,
+                mint_b: self.mint_b,
+                taker_ata_b: funded_mint_b.map(|mint| self.ata(&self.taker, &mint)),
+                escrow: Some(escrow).filter(|_| !self.escrow_closed),
+                receipt: self
+                    .receipt_holder
+                    .map(|holder| receipt_address(&holder, &receipt_mint_address(&escrow))),
+                counter,
+                counter_vault: funded_mint_b.map(|mint| self.ata(&counter, &mint)),
+                token_program: self.token_program,
+                system_program: system_program::ID,
+                event_authority: event_authority_address(),
+                program: anchor_escrow::ID,
+            }
+            .to_account_metas(None),
+            data: anchor_escrow::instruction::RejectCounter {}.data(),
+        }
+    }
+
+    fn ata(&self, owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(owner, mint, &self.token_program)
+    }
+}
+
+// Only the taker of a vesting escrow, recorded as its beneficiary, can claim
+pub struct Claim {
+    pub beneficiary: Pubkey,
+    pub maker: Pubkey,
+    pub seed: u64,
+    pub mint_a: Option<Pubkey>,
+    pub token_program: Pubkey,
+}
+
+impl Claim {
+    pub fn instruction(&self) -> Instruction {
+        let escrow = escrow_address(&self.maker, self.seed);
+
+        Instruction {
+            program_id: anchor_escrow::ID,
+            accounts: anchor_escrow::accounts::Claim {
+                beneficiary: self.beneficiary,
+                maker: self.maker,
+                mint_a: self.mint_a,
+                beneficiary_ata_a: self.mint_a.map(|mint| {
+                    get_associated_token_address_with_program_id(
+                        &self.beneficiary,
+                        &mint,
+                        &self.token_program,
+                    )
+                }),
+                escrow,
+                vault: self.mint_a.map(|mint| vault_address(&escrow, &mint, &self.token_program)),
+                token_program: self.token_program,
+                system_program: system_program::ID,
+                event_authority: event_authority_address(),
+                program: anchor_escrow::ID,
+            }
+            .to_account_metas(None),
+            data: anchor_escrow::instruction::Claim {}.data(),
+        }
+    }
+}
+
+// Either the maker or the taker of a taken arbiter escrow can dispute it
+pub struct Dispute {
+    pub party: Pubkey,
+    pub maker: Pubkey,
+    pub seed: u64,
+}
+
+impl Dispute {
+    pub fn instruction(&self) -> Instruction {
+        Instruction {
+            program_id: anchor_escrow::ID,
+            accounts: anchor_escrow::accounts::Dispute {
+                party: self.party,
+                escrow: escrow_address(&self.maker, self.seed),
+                event_authority: event_authority_address(),
+                program: anchor_escrow::ID,
+            }
+            .to_account_metas(None),
+            data: anchor_escrow::instruction::Dispute {}.data(),
+        }
+    }
+}
+
+// Settles a taken arbiter escrow. `authority` is the approving maker or taker,
+// or the arbiter when resolving a dispute.
+pub struct Settle {
+    pub authority: Pubkey,
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    pub seed: u64,
+    pub mint_a: Option<Pubkey>,
+    pub mint_b: Option<Pubkey>,
+    pub token_program: Pubkey,
+}
+
+impl Settle {
+    pub fn approve(&self) -> Instruction {
+        self.instruction(anchor_escrow::instruction::Approve {}.data())
+    }
+
+    pub fn resolve(&self, taker_share_bps: u16) -> Instruction {
+        self.instruction(anchor_escrow::instruction::Resolve { taker_share_bps }.data())
+    }
+
+    fn instruction(&self, data: Vec<u8>) -> Instruction {
+        let escrow = escrow_address(&self.maker, self.seed);
+
+        Instruction {
+            program_id: anchor_escrow::ID,
+            accounts: anchor_escrow::accounts::Settle {
+                authority: self.authority,
+                maker: self.maker,
+                taker: self.taker,
+                mint_a: self.mint_a,
+                mint_b: self.mint_b,
+                maker_ata_a: self.mint_a.map(|mint| self.ata(&self.maker, &mint)),
+                maker_ata_b: self.mint_b.map(|mint| self.ata(&self.maker, &mint)),
+                taker_ata_a: self.mint_a.map(|mint| self.ata(&self.taker, &mint)),
+                taker_ata_b: self.mint_b.map(|mint| self.ata(&self.taker, &mint)),
+                escrow,
+                vault: self.mint_a.map(|mint| vault_address(&escrow, &mint, &self.token_program)),
+                vault_b: self.mint_b.map(|mint| vault_address(&escrow, &mint, &self.token_program)),
+                token_program: self.token_program,
+                system_program: system_program::ID,
+                event_authority: event_authority_address(),
+                program: anchor_escrow::ID,
+            }
+            .to_account_metas(None),
+            data,
+        }
+    }
+
+    fn ata(&self, owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(owner, mint, &self.token_program)
+    }
+}
+
+// Reprices the outstanding receive amount. For receipt escrows, `receipt_holder` signs
+// instead of the maker.
+pub struct Amend {
+    pub maker: Pubkey,
+    pub seed: u64,
+    pub receive: u64,
+    pub receipt_holder: Option<Pubkey>,
+}
+
+impl Amend {
+    pub fn instruction(&self) -> Instruction {
+        let escrow = escrow_address(&self.maker, self.seed);
+
+        Instruction {
+            program_id: anchor_escrow::ID,
+            accounts: anchor_escrow::accounts::Amend {
+                maker: self.receipt_holder.unwrap_or(self.maker),
+                escrow,
+                receipt: self
+                    .receipt_holder
+                    .map(|holder| receipt_address(&holder, &receipt_mint_address(&escrow))),
+                event_authority: event_authority_address(),
+                program: anchor_escrow::ID,
+            }
+            .to_account_metas(None),
+            data: anchor_escrow::instruction::Amend {
+                receive: self.receive,
+            }
+            .data(),
+        }
+    }
+}
+
+// Adds `amount` to the deposit, out of the signer's `mint_a` account. For receipt escrows,
+// `receipt_holder` signs and pays instead of the maker.
+pub struct TopUp {
+    pub maker: Pubkey,
+    pub seed: u64,
+    pub mint_a: Option<Pubkey>,
+    pub amount: u64,
+    pub receipt_holder: Option<Pubkey>,
+    pub token_program: Pubkey,
+}
+
+impl TopUp {
+    pub fn instruction(&self) -> Instruction {
+        let escrow = escrow_address(&self.maker, self.seed);
+        let owner = self.receipt_holder.unwrap_or(self.maker);
+
+        Instruction {
+            program_id: anchor_escrow::ID,
+            accounts: anchor_escrow::accounts::TopUp {
+                maker: owner,
+                mint_a: self.mint_a,
+                maker_ata_a: self.mint_a.map(|mint| {
+                    get_associated_token_address_with_program_id(
+                        &owner,
+                        &mint,
+                        &self.token_program,
+                    )
+                }),
+                escrow,
+                receipt: self
+                    .receipt_holder
+                    .map(|holder| receipt_address(&holder, &receipt_mint_address(&escrow))),
+                vault: self.mint_a.map(|mint| vault_address(&escrow, &mint, &self.token_program)),
+                token_program: self.token_program,
+                system_program: system_program::ID,
+                event_authority: event_authority_address(),
+                program: anchor_escrow::ID,
+            }
+            .to_account_metas(None),
+            data: anchor_escrow::instruction::TopUp {
+                amount: self.amount,
+            }
+            .data(),
+        }
+    }
+}
+
+// Receipts are Token-2022 mints, whatever program the escrow's legs use
+fn receipt_address(owner: &Pubkey, receipt_mint: &Pubkey) -> Pubkey {
+    get_associated_token_address_with_program_id(owner, receipt_mint, &token_2022::ID)
+}
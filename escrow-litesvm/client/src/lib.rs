@@ -0,0 +1,17 @@
+// Client-side helpers for anchor-escrow: PDA derivation, typed instruction builders
+// and account decoding, so consumers stop hand-assembling account lists
+pub mod allowlist;
+pub mod instructions;
+pub mod pda;
+pub mod signed_offer;
+pub mod state;
+#[cfg(test)]
+mod tests;
+
+pub use anchor_escrow::events;
+pub use anchor_escrow::ID as PROGRAM_ID;
+pub use allowlist::*;
+pub use instructions::*;
+pub use pda::*;
+pub use signed_offer::*;
+pub use state::*;
@@ -0,0 +1,78 @@
+use anchor_lang::{prelude::Pubkey, solana_program::bpf_loader_upgradeable};
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+
+use crate::state::OfferNonces;
+
+pub fn escrow_address(maker: &Pubkey, seed: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"escrow", maker.as_ref(), &seed.to_le_bytes()],
+        &anchor_escrow::ID,
+    )
+    .0
+}
+
+// Baskets draw their seeds from the same `MakerState::next_seed` as escrows
+pub fn basket_address(maker: &Pubkey, seed: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"basket", maker.as_ref(), &seed.to_le_bytes()],
+        &anchor_escrow::ID,
+    )
+    .0
+}
+
+// The vault is the escrow's ATA, so it depends on which token program owns `mint_a`
+pub fn vault_address(escrow: &Pubkey, mint_a: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    get_associated_token_address_with_program_id(escrow, mint_a, token_program)
+}
+
+// Mint of the escrow's 1-of-1 Token-2022 receipt, when it has one
+pub fn receipt_mint_address(escrow: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"receipt", escrow.as_ref()], &anchor_escrow::ID).0
+}
+
+// One counter-offer per taker and escrow
+pub fn counter_address(escrow: &Pubkey, taker: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"counter", escrow.as_ref(), taker.as_ref()], &anchor_escrow::ID)
+        .0
+}
+
+// Running total of what an allowlisted taker has filled on one escrow
+pub fn allowlist_fill_address(escrow: &Pubkey, taker: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"fill", escrow.as_ref(), taker.as_ref()],
+        &anchor_escrow::ID,
+    )
+    .0
+}
+
+// PDA a maker approves as delegate of their `mint_a` ATA to back signed offers
+pub fn offer_delegate_address(maker: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"delegate", maker.as_ref()], &anchor_escrow::ID).0
+}
+
+// Page of the maker's nonce bitmap that records `nonce`
+pub fn offer_nonces_address(maker: &Pubkey, nonce: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"nonces", maker.as_ref(), &OfferNonces::page(nonce).to_le_bytes()],
+        &anchor_escrow::ID,
+    )
+    .0
+}
+
+pub fn maker_state_address(maker: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"maker", maker.as_ref()], &anchor_escrow::ID).0
+}
+
+pub fn config_address() -> Pubkey {
+    Pubkey::find_program_address(&[b"config"], &anchor_escrow::ID).0
+}
+
+// The program's upgradeable-loader data account, which names the upgrade authority
+pub fn program_data_address() -> Pubkey {
+    Pubkey::find_program_address(&[anchor_escrow::ID.as_ref()], &bpf_loader_upgradeable::ID).0
+}
+
+// Signer of the self-CPI that carries emitted events
+pub fn event_authority_address() -> Pubkey {
+    Pubkey::find_program_address(&[b"__event_authority"], &anchor_escrow::ID).0
+}
@@ -0,0 +1,46 @@
+use anchor_lang::{
+    prelude::Pubkey,
+    solana_program::{ed25519_program, instruction::Instruction},
+};
+
+use crate::state::SignedOffer;
+
+// Offsets of the single signature's parts in the precompile instruction data, laid out as
+// the 16-byte header, then the public key, the signature and the message
+const PUBLIC_KEY_OFFSET: u16 = 16;
+const SIGNATURE_OFFSET: u16 = PUBLIC_KEY_OFFSET + 32;
+const MESSAGE_OFFSET: u16 = SIGNATURE_OFFSET + 64;
+
+// Ed25519 precompile instruction checking the maker's `signature` over `offer.message()`.
+// It has to go in the transaction right before the `TakeOffer` instruction.
+pub fn offer_signature_instruction(
+    maker: &Pubkey,
+    signature: &[u8; 64],
+    offer: &SignedOffer,
+) -> Instruction {
+    let message = offer
+        .message()
+        .expect("serializing into a Vec cannot fail");
+
+    let mut data = vec![1, 0];
+    for offset in [
+        SIGNATURE_OFFSET,
+        u16::MAX,
+        PUBLIC_KEY_OFFSET,
+        u16::MAX,
+        MESSAGE_OFFSET,
+        message.len() as u16,
+        u16::MAX,
+    ] {
+        data.extend_from_slice(&offset.to_le_bytes());
+    }
+    data.extend_from_slice(maker.as_ref());
+    data.extend_from_slice(signature);
+    data.extend_from_slice(&message);
+
+    Instruction {
+        program_id: ed25519_program::ID,
+        accounts: vec![],
+        data,
+    }
+}
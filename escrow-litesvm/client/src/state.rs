@@ -0,0 +1,45 @@
+use anchor_lang::{prelude::Pubkey, AccountDeserialize, Result};
+
+pub use anchor_escrow::merkle::AllowlistProof;
+pub use anchor_escrow::signed_offer::SignedOffer;
+pub use anchor_escrow::state::{
+    BasketLeg, DutchAuction, Escrow, MakerState, OfferNonces, OraclePricing, Pricing, Reservation,
+    ReservationTerms, TimeWindows, Vesting, NATIVE_SOL, NONCES_PER_PAGE,
+};
+
+use crate::pda::escrow_address;
+
+// Checks the account discriminator before deserializing
+pub fn decode_escrow(mut data: &[u8]) -> Result<Escrow> {
+    Escrow::try_deserialize(&mut data)
+}
+
+pub fn decode_maker_state(mut data: &[u8]) -> Result<MakerState> {
+    MakerState::try_deserialize(&mut data)
+}
+
+// Walks the maker's seed range and returns every escrow still open, oldest first.
+// `fetch` returns an account's data, or `None` once it is closed (an RPC or LiteSVM lookup).
+// Taken vesting and arbiter escrows stay alive with a beneficiary but no longer count as open
+// offers, so they are skipped.
+pub fn live_escrows(
+    maker_state: &MakerState,
+    mut fetch: impl FnMut(&Pubkey) -> Option<Vec<u8>>,
+) -> Vec<(Pubkey, Escrow)> {
+    let mut escrows = Vec::new();
+
+    for seed in 0..maker_state.next_seed {
+        if escrows.len() == maker_state.open_offers as usize {
+            break;
+        }
+
+        let address = escrow_address(&maker_state.maker, seed);
+        if let Some(escrow) = fetch(&address).and_then(|data| decode_escrow(&data).ok()) {
+            if escrow.beneficiary.is_none() {
+                escrows.push((address, escrow));
+            }
+        }
+    }
+
+    escrows
+}
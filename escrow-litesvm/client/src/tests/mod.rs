@@ -0,0 +1,465 @@
+use {
+    crate::{
+        basket_address, config_address, decode_escrow, escrow_address, event_authority_address,
+        events, live_escrows, maker_state_address, offer_nonces_address,
+        offer_signature_instruction, vault_address, Allowlist, BasketLeg, Dispute, Escrow, Make,
+        MakeBasket, MakerState, Pricing, Refund, ReservationTerms, SignedOffer, Take,
+        TimeWindows, NATIVE_SOL, NONCES_PER_PAGE, PROGRAM_ID,
+    },
+    anchor_escrow::merkle,
+    anchor_lang::{
+        event::EVENT_IX_TAG_LE,
+        prelude::Pubkey,
+        solana_program::message::{
+            compiled_instruction::CompiledInstruction, inner_instruction::InnerInstruction,
+        },
+        AccountSerialize, Event,
+    },
+    anchor_spl::{associated_token, token, token_2022},
+    std::collections::HashMap,
+};
+
+fn escrow(maker: Pubkey, seed: u64) -> Escrow {
+    Escrow {
+        seed,
+        maker,
+        mint_a: NATIVE_SOL,
+        mint_b: Pubkey::new_unique(),
+        deposit: 100,
+        receive: 200,
+        receive_net_of_fee: false,
+        creation_time: 1,
+        take_open_time: 0,
+        expiry_time: i64::MAX,
+        refund_lock_time: 0,
+        allowed_taker: None,
+        allowlist_root: None,
+        pricing: Pricing::Fixed,
+        version: 3,
+        vesting: None,
+        beneficiary: None,
+        vesting_start: 0,
+        claimed: 0,
+        arbiter: None,
+        held_b: 0,
+        maker_approved: false,
+        taker_approved: false,
+        disputed: false,
+        receipt_mint: None,
+        proceeds_to: None,
+        refund_to: None,
+        reservation_terms: None,
+        reservation: None,
+        bump: 255,
+    }
+}
+
+fn windows() -> TimeWindows {
+    TimeWindows {
+        take_open_time: 0,
+        expiry_time: i64::MAX,
+        refund_lock_time: 0,
+    }
+}
+
+#[test]
+fn test_pda_derivation() {
+    let maker = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+
+    let escrow = escrow_address(&maker, 42);
+    let (expected, _) = Pubkey::find_program_address(
+        &[b"escrow", maker.as_ref(), &42u64.to_le_bytes()],
+        &PROGRAM_ID,
+    );
+    assert_eq!(escrow, expected);
+    assert_ne!(escrow, escrow_address(&maker, 43));
+
+    // The vault lives at a different ATA depending on the token program
+    assert_eq!(
+        vault_address(&escrow, &mint, &token::ID),
+        associated_token::get_associated_token_address(&escrow, &mint)
+    );
+    assert_ne!(
+        vault_address(&escrow, &mint, &token::ID),
+        vault_address(&escrow, &mint, &token_2022::ID)
+    );
+}
+
+#[test]
+fn test_make_and_take_accounts() {
+    let maker = Pubkey::new_unique();
+    let taker = Pubkey::new_unique();
+    let treasury = Pubkey::new_unique();
+    let mint_a = Pubkey::new_unique();
+    let mint_b = Pubkey::new_unique();
+    let escrow = escrow_address(&maker, 7);
+    let vault = vault_address(&escrow, &mint_a, &token_2022::ID);
+
+    let make_ix = Make {
+        maker,
+        seed: 7,
+        mint_a: Some(mint_a),
+        mint_b: Some(mint_b),
+        deposit: 10,
+        receive: 20,
+        windows: windows(),
+        allowed_taker: None,
+        allowlist_root: None,
+        receive_net_of_fee: false,
+        pricing: Pricing::Fixed,
+        vesting: None,
+        arbiter: None,
+        receipt: false,
+        token_program: token_2022::ID,
+    }
+    .instruction();
+    assert_eq!(make_ix.program_id, PROGRAM_ID);
+    assert!(make_ix.accounts[0].is_signer && make_ix.accounts[0].is_writable);
+    assert!(make_ix.accounts.iter().any(|meta| meta.pubkey == vault && meta.is_writable));
+    assert!(make_ix.accounts.iter().any(|meta| meta.pubkey == config_address()));
+
+    let take_ix = Take {
+        taker,
+        maker,
+        seed: 7,
+        mint_a: Some(mint_a),
+        mint_b: Some(mint_b),
+        treasury,
+        expected_version: 0,
+        max_payment: 20,
+        allowlist: None,
+        price_update: None,
+        arbitrated: false,
+        receipt_holder: None,
+        proceeds_to: None,
+        token_program: token_2022::ID,
+    }
+    .instruction();
+    assert!(take_ix.accounts[0].pubkey == taker && take_ix.accounts[0].is_signer);
+    assert!(take_ix.accounts.iter().any(|meta| meta.pubkey == escrow && meta.is_writable));
+    assert!(take_ix.accounts.iter().any(|meta| meta.pubkey == vault));
+}
+
+#[test]
+fn test_make_basket_accounts() {
+    let maker = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let basket = basket_address(&maker, 3);
+
+    // A basket shares the maker's seed counter with escrows but not their address space
+    assert_ne!(basket, escrow_address(&maker, 3));
+
+    let ix = MakeBasket {
+        maker,
+        seed: 3,
+        offered: vec![(mint, 10)],
+        requested: vec![BasketLeg {
+            mint: Pubkey::new_unique(),
+            amount: 20,
+        }],
+        token_program: token::ID,
+    }
+    .instruction();
+    assert!(ix.accounts.iter().any(|meta| meta.pubkey == maker_state_address(&maker)));
+    assert!(ix.accounts.iter().any(|meta| meta.pubkey == basket && meta.is_writable));
+
+    // One `[mint, maker_ata, vault]` triple per offered leg follows the named accounts
+    let legs: Vec<Pubkey> = ix.accounts[ix.accounts.len() - 3..]
+        .iter()
+        .map(|meta| meta.pubkey)
+        .collect();
+    assert_eq!(
+        legs,
+        vec![
+            mint,
+            associated_token::get_associated_token_address(&maker, &mint),
+            associated_token::get_associated_token_address(&basket, &mint),
+        ]
+    );
+}
+
+#[test]
+fn test_native_leg_leaves_token_accounts_out() {
+    let maker = Pubkey::new_unique();
+    let refund_ix = Refund {
+        maker,
+        seed: 1,
+        mint_a: None,
+        receipt_holder: None,
+        refund_to: None,
+        reserver: None,
+        token_program: token::ID,
+    }
+    .instruction();
+
+    // Anchor encodes an absent optional account as the program id
+    let placeholders = refund_ix
+        .accounts
+        .iter()
+        .filter(|meta| meta.pubkey == PROGRAM_ID && !meta.is_writable)
+        .count();
+    assert!(placeholders >= 3);
+}
+
+#[test]
+fn test_decode_escrow() {
+    let escrow = escrow(Pubkey::new_unique(), 5);
+    let mut data = Vec::new();
+    escrow.try_serialize(&mut data).unwrap();
+
+    let decoded = decode_escrow(&data).unwrap();
+    assert_eq!(decoded.seed, 5);
+    assert_eq!(decoded.maker, escrow.maker);
+    assert!(decoded.is_native_a());
+    assert_eq!(decoded.version, 3);
+
+    assert!(decode_escrow(&data[8..]).is_err());
+}
+
+#[test]
+fn test_live_escrows() {
+    let maker = Pubkey::new_unique();
+    assert_ne!(maker_state_address(&maker), maker_state_address(&Pubkey::new_unique()));
+
+    // Seeds 0..4 were handed out, 1 and 3 have since been taken or refunded
+    let mut accounts = HashMap::new();
+    for seed in [0, 2] {
+        let mut data = Vec::new();
+        escrow(maker, seed).try_serialize(&mut data).unwrap();
+        accounts.insert(escrow_address(&maker, seed), data);
+    }
+    let maker_state = MakerState {
+        maker,
+        next_seed: 4,
+        open_offers: 2,
+        bump: 255,
+    };
+
+    let mut lookups = 0;
+    let escrows = live_escrows(&maker_state, |address| {
+        lookups += 1;
+        accounts.get(address).cloned()
+    });
+    assert_eq!(escrows.len(), 2);
+    assert_eq!(escrows[0].0, escrow_address(&maker, 0));
+    assert_eq!(escrows[1].1.seed, 2);
+    // Stops once every open offer is found
+    assert_eq!(lookups, 3);
+
+    // A taken vesting escrow is still alive but is not an open offer
+    let mut taken = escrow(maker, 0);
+    taken.beneficiary = Some(Pubkey::new_unique());
+    let mut data = Vec::new();
+    taken.try_serialize(&mut data).unwrap();
+    accounts.insert(escrow_address(&maker, 0), data);
+    let maker_state = MakerState {
+        open_offers: 1,
+        ..maker_state
+    };
+
+    let escrows = live_escrows(&maker_state, |address| accounts.get(address).cloned());
+    assert_eq!(escrows.len(), 1);
+    assert_eq!(escrows[0].0, escrow_address(&maker, 2));
+}
+
+#[test]
+fn test_allowlist_proofs() {
+    let takers: Vec<Pubkey> = (0..5).map(|_| Pubkey::new_unique()).collect();
+    let allowlist = Allowlist::new(
+        takers
+            .iter()
+            .enumerate()
+            .map(|(i, taker)| (*taker, 100 * i as u64))
+            .collect(),
+    );
+    let root = allowlist.root();
+
+    for (i, taker) in takers.iter().enumerate() {
+        let proof = allowlist.proof(taker).unwrap();
+        assert_eq!(proof.max_fill, 100 * i as u64);
+        assert!(merkle::verify(&proof.proof, root, merkle::leaf(taker, proof.max_fill)));
+        // A taker cannot claim a bigger cap than the one committed to
+        assert!(!merkle::verify(&proof.proof, root, merkle::leaf(taker, u64::MAX)));
+    }
+    assert!(allowlist.proof(&Pubkey::new_unique()).is_none());
+}
+
+#[test]
+fn test_offer_signature_instruction() {
+    let maker = Pubkey::new_unique();
+    let offer = SignedOffer {
+        mint_a: Pubkey::new_unique(),
+        mint_b: Pubkey::new_unique(),
+        amount_a: 10,
+        amount_b: 20,
+        expiry_time: i64::MAX,
+        nonce: 7,
+    };
+    let ix = offer_signature_instruction(&maker, &[9; 64], &offer);
+
+    // Read the parts back the way the precompile does, through the offsets header
+    let offset = |i: usize| u16::from_le_bytes([ix.data[2 + 2 * i], ix.data[3 + 2 * i]]) as usize;
+    assert_eq!(&ix.data[..2], &[1, 0]);
+    assert_eq!(&ix.data[offset(0)..offset(0) + 64], &[9; 64]);
+    assert_eq!(&ix.data[offset(2)..offset(2) + 32], maker.as_ref());
+    assert_eq!(&ix.data[offset(4)..offset(4) + offset(5)], offer.message().unwrap());
+
+    // Nonces share a bitmap page until they cross a page boundary
+    assert_eq!(offer_nonces_address(&maker, 0), offer_nonces_address(&maker, 7));
+    assert_ne!(
+        offer_nonces_address(&maker, 7),
+        offer_nonces_address(&maker, NONCES_PER_PAGE)
+    );
+}
+
+#[test]
+fn test_decode_events() {
+    use events::EscrowEvent;
+
+    let escrow = Pubkey::new_unique();
+    let maker = Pubkey::new_unique();
+    let taker = Pubkey::new_unique();
+    let legs = vec![BasketLeg {
+        mint: Pubkey::new_unique(),
+        amount: 10,
+    }];
+    let emitted = vec![
+        EscrowEvent::Claimed(events::EscrowClaimed {
+            escrow,
+            maker,
+            beneficiary: taker,
+            seed: 1,
+            mint_a: NATIVE_SOL,
+            amount: 25,
+            claimed: 50,
+            deposit: 100,
+            timestamp: 7,
+        }),
+        EscrowEvent::Disputed(events::EscrowDisputed {
+            escrow,
+            maker,
+            party: taker,
+            seed: 1,
+            timestamp: 7,
+        }),
+        EscrowEvent::Approved(events::EscrowApproved {
+            escrow,
+            maker,
+            party: maker,
+            seed: 1,
+            settled: true,
+            timestamp: 7,
+        }),
+        EscrowEvent::Resolved(events::EscrowResolved {
+            escrow,
+            maker,
+            taker,
+            arbiter: Pubkey::new_unique(),
+            seed: 1,
+            taker_share_bps: 2_500,
+            taker_amount_a: 25,
+            taker_amount_b: 50,
+            timestamp: 7,
+        }),
+        EscrowEvent::ReservationTermsSet(events::ReservationTermsSet {
+            escrow,
+            maker,
+            seed: 1,
+            terms: Some(ReservationTerms {
+                bond: 1_000,
+                max_duration: 60,
+            }),
+            timestamp: 7,
+        }),
+        EscrowEvent::Reserved(events::EscrowReserved {
+            escrow,
+            maker,
+            taker,
+            seed: 1,
+            bond: 1_000,
+            expires_at: 67,
+            timestamp: 7,
+        }),
+        EscrowEvent::CounterPosted(events::CounterPosted {
+            counter: Pubkey::new_unique(),
+            escrow,
+            taker,
+            amount_a: 100,
+            amount_b: 150,
+            funded: false,
+            timestamp: 7,
+        }),
+        EscrowEvent::CounterRejected(events::CounterRejected {
+            counter: Pubkey::new_unique(),
+            escrow,
+            taker,
+            withdrawn: true,
+            timestamp: 7,
+        }),
+        EscrowEvent::BasketMade(events::BasketMade {
+            basket: escrow,
+            maker,
+            seed: 2,
+            offered: legs.clone(),
+            requested: legs.clone(),
+            timestamp: 7,
+        }),
+        EscrowEvent::BasketTaken(events::BasketTaken {
+            basket: escrow,
+            maker,
+            taker,
+            seed: 2,
+            offered: legs.clone(),
+            requested: legs.clone(),
+            timestamp: 7,
+        }),
+        EscrowEvent::BasketRefunded(events::BasketRefunded {
+            basket: escrow,
+            maker,
+            seed: 2,
+            offered: legs,
+            timestamp: 7,
+        }),
+    ];
+
+    // Encode each one the way `emit_cpi!` does, as a self-invocation signed by the event authority
+    let data = |event: &EscrowEvent| match event {
+        EscrowEvent::Claimed(e) => e.data(),
+        EscrowEvent::Disputed(e) => e.data(),
+        EscrowEvent::Approved(e) => e.data(),
+        EscrowEvent::Resolved(e) => e.data(),
+        EscrowEvent::ReservationTermsSet(e) => e.data(),
+        EscrowEvent::Reserved(e) => e.data(),
+        EscrowEvent::CounterPosted(e) => e.data(),
+        EscrowEvent::CounterRejected(e) => e.data(),
+        EscrowEvent::BasketMade(e) => e.data(),
+        EscrowEvent::BasketTaken(e) => e.data(),
+        EscrowEvent::BasketRefunded(e) => e.data(),
+        _ => unreachable!(),
+    };
+    let inner: Vec<InnerInstruction> = emitted
+        .iter()
+        .map(|event| InnerInstruction {
+            instruction: CompiledInstruction {
+                program_id_index: 0,
+                accounts: vec![1],
+                data: [EVENT_IX_TAG_LE, &data(event)].concat(),
+            },
+            stack_height: 2,
+        })
+        .collect();
+    let account_keys = [PROGRAM_ID, event_authority_address()];
+
+    assert_eq!(events::decode_events(&account_keys, &[inner]), emitted);
+
+    // Emitting instructions end with the event authority and the program itself
+    let ix = Dispute {
+        party: taker,
+        maker,
+        seed: 1,
+    }
+    .instruction();
+    let tail: Vec<Pubkey> = ix.accounts.iter().rev().take(2).map(|meta| meta.pubkey).collect();
+    assert_eq!(tail, vec![PROGRAM_ID, event_authority_address()]);
+}
@@ -4,4 +4,124 @@ use anchor_lang::prelude::*;
 pub enum ErrorCode {
     #[msg("Too early to take from the escrow")]
     Locked,
-}
\ No newline at end of file
+    #[msg("Fill amount must be non-zero and no more than the remaining receive amount")]
+    InvalidFillAmount,
+    #[msg("Fill amount is too small to release any of the deposit")]
+    FillTooSmall,
+    #[msg("Arithmetic overflow")]
+    MathOverflow,
+    #[msg("The escrow has expired")]
+    Expired,
+    #[msg("Too early for the maker to refund the escrow")]
+    RefundLocked,
+    #[msg("Invalid time window: take must open before expiry, and expiry must be in the future")]
+    InvalidTimeWindow,
+    #[msg("The escrow has not expired yet")]
+    NotExpired,
+    #[msg("This escrow can only be taken by its designated taker")]
+    UnauthorizedTaker,
+    #[msg("At most one leg of an escrow can be native SOL")]
+    BothLegsNative,
+    #[msg("Mint and token accounts are required for an SPL token leg")]
+    MissingTokenAccounts,
+    #[msg("Mint does not match the escrow")]
+    MintMismatch,
+    #[msg("A basket must have between 1 and 5 legs on each side")]
+    InvalidBasketSize,
+    #[msg("A basket cannot list the same mint twice")]
+    DuplicateBasketMint,
+    #[msg("Remaining accounts do not match the basket legs")]
+    BasketAccountsMismatch,
+    #[msg("Amount must be greater than zero")]
+    InvalidAmount,
+    #[msg("Escrow was amended after the taker read it")]
+    VersionMismatch,
+    #[msg("Fee cannot exceed 1000 bps")]
+    InvalidFee,
+    #[msg("The escrow is paused")]
+    Paused,
+    #[msg("Auction floor must not exceed the start price and its decay window must not be empty")]
+    InvalidAuction,
+    #[msg("Price exceeds the taker's maximum payment")]
+    SlippageExceeded,
+    #[msg("Only fixed-price escrows without vesting or an arbiter can be partially filled")]
+    FullFillOnly,
+    #[msg("Oracle premium or price checks are out of range")]
+    InvalidOraclePricing,
+    #[msg("Price update account is missing or not a verified feed for this escrow")]
+    InvalidPriceUpdate,
+    #[msg("Oracle price is older than the escrow allows")]
+    StalePrice,
+    #[msg("Oracle confidence interval is wider than the escrow allows")]
+    PriceConfidenceTooWide,
+    #[msg("Vesting duration must be positive and the cliff must fall within it")]
+    InvalidVesting,
+    #[msg("The escrow has already been taken")]
+    AlreadyTaken,
+    #[msg("Signer is not the beneficiary of this escrow")]
+    NotBeneficiary,
+    #[msg("Nothing has vested since the last claim")]
+    NothingToClaim,
+    #[msg("An arbiter must not be the maker, needs two mints, and rules out vesting or a receipt")]
+    InvalidArbiter,
+    #[msg("The escrow has no arbiter or has not been taken yet")]
+    NotArbitrated,
+    #[msg("Signer is neither the maker nor the taker of this escrow")]
+    NotEscrowParty,
+    #[msg("The escrow is under dispute")]
+    Disputed,
+    #[msg("Only a disputed escrow can be resolved")]
+    NotDisputed,
+    #[msg("Signer is not the arbiter of this escrow")]
+    NotArbiter,
+    #[msg("Share cannot exceed 10000 bps")]
+    InvalidShare,
+    #[msg("Taker is missing from the escrow's allowlist")]
+    NotAllowlisted,
+    #[msg("Fill exceeds the taker's allowlisted maximum")]
+    AllowlistFillExceeded,
+    #[msg("Partial fills of an allowlisted escrow need the taker's fill account")]
+    MissingAllowlistFill,
+    #[msg("A batch must take between 1 and 4 escrows")]
+    InvalidBatchSize,
+    #[msg("Remaining accounts do not match the batch")]
+    BatchAccountsMismatch,
+    #[msg("Only plain fixed-price or auction escrows can be batched")]
+    BatchUnsupported,
+    #[msg("Account is neither the maker nor the holder of the escrow's receipt")]
+    NotEscrowOwner,
+    #[msg("Receipt accounts must be passed together")]
+    MissingReceiptAccounts,
+    #[msg("Counter-offer amounts must be non-zero and within the escrow's deposit")]
+    InvalidCounterOffer,
+    #[msg("Allowlisted, vesting, arbiter, payout or reservable escrows cannot take counters")]
+    CounterUnsupported,
+    #[msg("Counter-offers paying native SOL must be funded up front")]
+    UnfundedNativeCounter,
+    #[msg("The escrows' prices do not cross")]
+    EscrowsDoNotCross,
+    #[msg("Only plain fixed-price or auction escrows can be matched")]
+    MatchUnsupported,
+    #[msg("Account is not the payout account the escrow was made with")]
+    PayoutAccountMismatch,
+    #[msg("Missing an Ed25519 check of the maker's signature over this offer")]
+    InvalidOfferSignature,
+    #[msg("Offer nonce was already taken or cancelled")]
+    NonceAlreadyUsed,
+    #[msg("Only escrows without an allowlist, vesting, arbiter or receipt can be reserved")]
+    NotReservable,
+    #[msg("Reservation terms or duration are out of range")]
+    InvalidReservation,
+    #[msg("The escrow is reserved by another taker")]
+    Reserved,
+    #[msg("Account is not the escrow's reserver")]
+    ReserverMismatch,
+    #[msg("Basket legs cannot use transfer-hook or transfer-fee mints")]
+    UnsupportedBasketMint,
+    #[msg("Only the program's upgrade authority can initialize the config")]
+    NotUpgradeAuthority,
+    #[msg("The arbiter of an escrow cannot take it")]
+    ArbiterCannotTake,
+    #[msg("The taker's token account no longer delegates the counter's amount to it")]
+    CounterNotDelegated,
+}
@@ -0,0 +1,325 @@
+use anchor_lang::prelude::*;
+use anchor_lang::event::EVENT_IX_TAG_LE;
+use anchor_lang::solana_program::message::compiled_instruction::CompiledInstruction;
+use anchor_lang::solana_program::message::inner_instruction::InnerInstruction;
+use anchor_lang::Discriminator;
+
+use crate::state::{BasketLeg, ReservationTerms};
+
+#[event]
+#[derive(Clone, Debug, PartialEq)]
+pub struct EscrowMade {
+    pub escrow: Pubkey,
+    pub maker: Pubkey,
+    pub seed: u64,
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    pub deposit: u64,
+    pub receive: u64,
+    pub take_open_time: i64,
+    pub expiry_time: i64,
+    pub refund_lock_time: i64,
+    pub allowed_taker: Option<Pubkey>,
+    pub timestamp: i64,
+}
+
+// Emitted by `take` and every `take_partial` fill; `fee` is the protocol fee out of `amount_b`
+#[event]
+#[derive(Clone, Debug, PartialEq)]
+pub struct EscrowTaken {
+    pub escrow: Pubkey,
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    pub seed: u64,
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    pub amount_a: u64,
+    pub amount_b: u64,
+    pub fee: u64,
+    pub remaining_deposit: u64,
+    pub remaining_receive: u64,
+    pub timestamp: i64,
+}
+
+// Emitted by `refund`, and by `expire` with `expired` set
+#[event]
+#[derive(Clone, Debug, PartialEq)]
+pub struct EscrowRefunded {
+    pub escrow: Pubkey,
+    pub maker: Pubkey,
+    pub seed: u64,
+    pub mint_a: Pubkey,
+    pub amount: u64,
+    pub expired: bool,
+    pub timestamp: i64,
+}
+
+// Emitted by `amend` and `top_up` with the terms as they stand afterwards
+#[event]
+#[derive(Clone, Debug, PartialEq)]
+pub struct EscrowAmended {
+    pub escrow: Pubkey,
+    pub maker: Pubkey,
+    pub seed: u64,
+    pub deposit: u64,
+    pub receive: u64,
+    pub version: u32,
+    pub timestamp: i64,
+}
+
+// Emitted by `take_offer`; there is no escrow account, the maker's `nonce` identifies the offer
+#[event]
+#[derive(Clone, Debug, PartialEq)]
+pub struct OfferTaken {
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    pub nonce: u64,
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    pub amount_a: u64,
+    pub amount_b: u64,
+    pub fee: u64,
+    pub timestamp: i64,
+}
+
+// Emitted by every `claim`; `claimed` is the running total released to the beneficiary
+#[event]
+#[derive(Clone, Debug, PartialEq)]
+pub struct EscrowClaimed {
+    pub escrow: Pubkey,
+    pub maker: Pubkey,
+    pub beneficiary: Pubkey,
+    pub seed: u64,
+    pub mint_a: Pubkey,
+    pub amount: u64,
+    pub claimed: u64,
+    pub deposit: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Clone, Debug, PartialEq)]
+pub struct EscrowDisputed {
+    pub escrow: Pubkey,
+    pub maker: Pubkey,
+    pub party: Pubkey,
+    pub seed: u64,
+    pub timestamp: i64,
+}
+
+// Emitted by each `approve`; `settled` once both sides have approved and the swap went through
+#[event]
+#[derive(Clone, Debug, PartialEq)]
+pub struct EscrowApproved {
+    pub escrow: Pubkey,
+    pub maker: Pubkey,
+    pub party: Pubkey,
+    pub seed: u64,
+    pub settled: bool,
+    pub timestamp: i64,
+}
+
+// Emitted by `resolve`; the maker got whatever of either leg did not go to the taker
+#[event]
+#[derive(Clone, Debug, PartialEq)]
+pub struct EscrowResolved {
+    pub escrow: Pubkey,
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    pub arbiter: Pubkey,
+    pub seed: u64,
+    pub taker_share_bps: u16,
+    pub taker_amount_a: u64,
+    pub taker_amount_b: u64,
+    pub timestamp: i64,
+}
+
+// Emitted by `set_reservation_terms`, `None` when reservations were turned off
+#[event]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReservationTermsSet {
+    pub escrow: Pubkey,
+    pub maker: Pubkey,
+    pub seed: u64,
+    pub terms: Option<ReservationTerms>,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Clone, Debug, PartialEq)]
+pub struct EscrowReserved {
+    pub escrow: Pubkey,
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    pub seed: u64,
+    pub bond: u64,
+    pub expires_at: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Clone, Debug, PartialEq)]
+pub struct CounterPosted {
+    pub counter: Pubkey,
+    pub escrow: Pubkey,
+    pub taker: Pubkey,
+    pub amount_a: u64,
+    pub amount_b: u64,
+    pub funded: bool,
+    pub timestamp: i64,
+}
+
+// Emitted by `reject_counter`, with `withdrawn` set when the taker pulled the counter itself
+#[event]
+#[derive(Clone, Debug, PartialEq)]
+pub struct CounterRejected {
+    pub counter: Pubkey,
+    pub escrow: Pubkey,
+    pub taker: Pubkey,
+    pub withdrawn: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Clone, Debug, PartialEq)]
+pub struct BasketMade {
+    pub basket: Pubkey,
+    pub maker: Pubkey,
+    pub seed: u64,
+    pub offered: Vec<BasketLeg>,
+    pub requested: Vec<BasketLeg>,
+    pub timestamp: i64,
+}
+
+// The requested legs are paid in full, the protocol fee coming out of the maker's side
+#[event]
+#[derive(Clone, Debug, PartialEq)]
+pub struct BasketTaken {
+    pub basket: Pubkey,
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    pub seed: u64,
+    pub offered: Vec<BasketLeg>,
+    pub requested: Vec<BasketLeg>,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Clone, Debug, PartialEq)]
+pub struct BasketRefunded {
+    pub basket: Pubkey,
+    pub maker: Pubkey,
+    pub seed: u64,
+    pub offered: Vec<BasketLeg>,
+    pub timestamp: i64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum EscrowEvent {
+    Made(EscrowMade),
+    Taken(EscrowTaken),
+    Refunded(EscrowRefunded),
+    Amended(EscrowAmended),
+    OfferTaken(OfferTaken),
+    Claimed(EscrowClaimed),
+    Disputed(EscrowDisputed),
+    Approved(EscrowApproved),
+    Resolved(EscrowResolved),
+    ReservationTermsSet(ReservationTermsSet),
+    Reserved(EscrowReserved),
+    CounterPosted(CounterPosted),
+    CounterRejected(CounterRejected),
+    BasketMade(BasketMade),
+    BasketTaken(BasketTaken),
+    BasketRefunded(BasketRefunded),
+}
+
+impl EscrowEvent {
+    // Decodes an `emit_cpi!` self-invocation, `None` for any other instruction. `account_keys`
+    // are the transaction's keys, used to check the instruction really targets this program and
+    // is signed by its event authority, so another program cannot spoof escrow events.
+    pub fn decode(account_keys: &[Pubkey], instruction: &CompiledInstruction) -> Option<Self> {
+        let key = |index: u8| account_keys.get(index as usize);
+        if key(instruction.program_id_index) != Some(&crate::ID) {
+            return None;
+        }
+        let event_authority = Pubkey::find_program_address(&[b"__event_authority"], &crate::ID).0;
+        if key(*instruction.accounts.first()?) != Some(&event_authority) {
+            return None;
+        }
+
+        let data = instruction.data.strip_prefix(EVENT_IX_TAG_LE)?;
+        if data.len() < 8 {
+            return None;
+        }
+        let (discriminator, mut event_data) = data.split_at(8);
+
+        match discriminator {
+            d if d == EscrowMade::DISCRIMINATOR => {
+                EscrowMade::deserialize(&mut event_data).ok().map(Self::Made)
+            }
+            d if d == EscrowTaken::DISCRIMINATOR => {
+                EscrowTaken::deserialize(&mut event_data).ok().map(Self::Taken)
+            }
+            d if d == EscrowRefunded::DISCRIMINATOR => {
+                EscrowRefunded::deserialize(&mut event_data).ok().map(Self::Refunded)
+            }
+            d if d == EscrowAmended::DISCRIMINATOR => {
+                EscrowAmended::deserialize(&mut event_data).ok().map(Self::Amended)
+            }
+            d if d == OfferTaken::DISCRIMINATOR => {
+                OfferTaken::deserialize(&mut event_data).ok().map(Self::OfferTaken)
+            }
+            d if d == EscrowClaimed::DISCRIMINATOR => {
+                EscrowClaimed::deserialize(&mut event_data).ok().map(Self::Claimed)
+            }
+            d if d == EscrowDisputed::DISCRIMINATOR => {
+                EscrowDisputed::deserialize(&mut event_data).ok().map(Self::Disputed)
+            }
+            d if d == EscrowApproved::DISCRIMINATOR => {
+                EscrowApproved::deserialize(&mut event_data).ok().map(Self::Approved)
+            }
+            d if d == EscrowResolved::DISCRIMINATOR => {
+                EscrowResolved::deserialize(&mut event_data).ok().map(Self::Resolved)
+            }
+            d if d == ReservationTermsSet::DISCRIMINATOR => {
+                ReservationTermsSet::deserialize(&mut event_data)
+                    .ok()
+                    .map(Self::ReservationTermsSet)
+            }
+            d if d == EscrowReserved::DISCRIMINATOR => {
+                EscrowReserved::deserialize(&mut event_data).ok().map(Self::Reserved)
+            }
+            d if d == CounterPosted::DISCRIMINATOR => {
+                CounterPosted::deserialize(&mut event_data).ok().map(Self::CounterPosted)
+            }
+            d if d == CounterRejected::DISCRIMINATOR => {
+                CounterRejected::deserialize(&mut event_data).ok().map(Self::CounterRejected)
+            }
+            d if d == BasketMade::DISCRIMINATOR => {
+                BasketMade::deserialize(&mut event_data).ok().map(Self::BasketMade)
+            }
+            d if d == BasketTaken::DISCRIMINATOR => {
+                BasketTaken::deserialize(&mut event_data).ok().map(Self::BasketTaken)
+            }
+            d if d == BasketRefunded::DISCRIMINATOR => {
+                BasketRefunded::deserialize(&mut event_data).ok().map(Self::BasketRefunded)
+            }
+            _ => None,
+        }
+    }
+}
+
+// Pulls every escrow event out of a transaction's inner instructions, in emission order.
+// Takes the message's account keys and the `inner_instructions` of LiteSVM's
+// `TransactionMetadata` (or an RPC transaction).
+pub fn decode_events(
+    account_keys: &[Pubkey],
+    inner_instructions: &[Vec<InnerInstruction>],
+) -> Vec<EscrowEvent> {
+    inner_instructions
+        .iter()
+        .flatten()
+        .filter_map(|inner| EscrowEvent::decode(account_keys, &inner.instruction))
+        .collect()
+}
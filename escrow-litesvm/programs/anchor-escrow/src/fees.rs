@@ -0,0 +1,33 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token_2022::spl_token_2022::{
+        self,
+        extension::{
+            transfer_fee::TransferFeeConfig, BaseStateWithExtensions, StateWithExtensions,
+        },
+    },
+    token_interface::Mint,
+};
+
+use crate::error::ErrorCode;
+
+// Amount that has to be sent so that `net_amount` arrives after the mint's Token-2022 transfer fee.
+// Legacy SPL mints and Token-2022 mints without `TransferFeeConfig` charge nothing.
+pub fn gross_up_for_fee(mint: &InterfaceAccount<Mint>, net_amount: u64) -> Result<u64> {
+    let mint_info = mint.to_account_info();
+    if *mint_info.owner != spl_token_2022::ID {
+        return Ok(net_amount);
+    }
+
+    let mint_data = mint_info.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint_data)?;
+    let Ok(fee_config) = mint_state.get_extension::<TransferFeeConfig>() else {
+        return Ok(net_amount);
+    };
+
+    let fee = fee_config
+        .calculate_inverse_epoch_fee(Clock::get()?.epoch, net_amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    Ok(net_amount.checked_add(fee).ok_or(ErrorCode::MathOverflow)?)
+}
@@ -0,0 +1,325 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program_option::COption;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{
+        CloseAccount, Mint, TokenAccount, TokenInterface, TransferChecked,
+    },
+};
+
+use crate::error::ErrorCode;
+use crate::events::EscrowTaken;
+use crate::state::{leg_mint, Config, CounterOffer, Escrow, MakerState};
+use crate::transfer::{close_account, transfer_checked};
+
+// Settles an escrow on the terms of a taker's counter-offer. `maker` is the maker,
+// or the holder of the escrow's receipt, and pays for any token accounts created.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct AcceptCounter<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+    #[account(mut)]
+    pub taker: SystemAccount<'info>,
+    #[account(mut)]
+    pub mint_a: Option<InterfaceAccount<'info, Mint>>,
+    #[account(mut)]
+    pub mint_b: Option<InterfaceAccount<'info, Mint>>,
+    #[account(
+        init_if_needed,
+        payer = maker,
+        associated_token::mint = mint_a,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_ata_a: Option<InterfaceAccount<'info, TokenAccount>>,
+    // Pays unfunded counters, through the delegation made when posting
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_ata_b: Option<InterfaceAccount<'info, TokenAccount>>,
+    // Gets back whatever part of the deposit the counter did not ask for
+    #[account(
+        init_if_needed,
+        payer = maker,
+        associated_token::mint = mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program,
+    )]
+    pub maker_ata_a: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = maker,
+        associated_token::mint = mint_b,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program,
+    )]
+    pub maker_ata_b: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, Config>,
+    #[account(mut, address = config.treasury)]
+    pub treasury: SystemAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = maker,
+        associated_token::mint = mint_b,
+        associated_token::authority = treasury,
+        associated_token::token_program = token_program,
+    )]
+    pub treasury_ata_b: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = leg_mint(&mint_a) == escrow.mint_a @ ErrorCode::MintMismatch,
+        constraint = leg_mint(&mint_b) == escrow.mint_b @ ErrorCode::MintMismatch,
+        seeds = [b"escrow", escrow.maker.as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, Escrow>,
+    // The `maker`'s receipt token account, for escrows that minted one
+    pub receipt: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [b"maker", escrow.maker.as_ref()],
+        bump = maker_state.bump,
+    )]
+    pub maker_state: Account<'info, MakerState>,
+    #[account(
+        mut,
+        close = taker,
+        seeds = [b"counter", escrow.key().as_ref(), taker.key().as_ref()],
+        bump = counter.bump,
+    )]
+    pub counter: Account<'info, CounterOffer>,
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = escrow,
+        associated_token::token_program = token_program,
+    )]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = counter,
+        associated_token::token_program = token_program,
+    )]
+    pub counter_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+//Pay the counter's `amount_b` to the maker, less the protocol fee sent to treasury,
+//release its `amount_a` to the taker and the rest of the deposit back to the maker,
+//then close the vaults, the counter and the escrow
+impl<'info> AcceptCounter<'info> {
+    pub fn accept(&mut self, remaining_accounts: &[AccountInfo<'info>]) -> Result<(u64, u64)> {
+        self.escrow.check_takeable(Clock::get()?.unix_timestamp)?;
+        self.escrow.check_owner(self.maker.key, &self.receipt)?;
+        self.config.check_not_paused()?;
+
+        let (amount_a, amount_b) = (self.counter.amount_a, self.counter.amount_b);
+        // The maker may have amended the escrow since the counter was posted
+        self.escrow.check_counterable(self.taker.key, amount_a, amount_b)?;
+        self.maker_state.close_offer();
+
+        self.pay_maker(amount_b, remaining_accounts)?;
+        self.release_deposit(amount_a, remaining_accounts)?;
+
+        Ok((amount_a, amount_b))
+    }
+
+    pub fn taken_event(&self, amount_a: u64, amount_b: u64) -> Result<EscrowTaken> {
+        Ok(EscrowTaken {
+            escrow: self.escrow.key(),
+            maker: self.escrow.maker,
+            taker: self.taker.key(),
+            seed: self.escrow.seed,
+            mint_a: self.escrow.mint_a,
+            mint_b: self.escrow.mint_b,
+            amount_a,
+            amount_b,
+            fee: self.config.fee(amount_b)?,
+            remaining_deposit: 0,
+            remaining_receive: 0,
+            timestamp: Clock::get()?.unix_timestamp,
+        })
+    }
+
+    fn pay_maker(&self, amount_b: u64, remaining_accounts: &[AccountInfo<'info>]) -> Result<()> {
+        let fee = self.config.fee(amount_b)?;
+
+        let (Some(mint_b), Some(maker_ata_b)) = (&self.mint_b, &self.maker_ata_b) else {
+            require!(self.escrow.is_native_b(), ErrorCode::MissingTokenAccounts);
+
+            // Native counters are always funded, with `amount_b` on top of the counter's rent
+            self.counter.sub_lamports(amount_b)?;
+            self.maker.add_lamports(amount_b - fee)?;
+            self.treasury.add_lamports(fee)?;
+            return Ok(());
+        };
+
+        // A funded counter pays out whatever reached its vault, which is less than
+        // `amount_b` for mints with a transfer fee
+        let (from, paid) = match (self.counter.funded, &self.counter_vault, &self.taker_ata_b) {
+            (true, Some(counter_vault), _) => (counter_vault, counter_vault.amount),
+            (false, _, Some(taker_ata_b)) => {
+                // An account has a single delegate, so a revoke, or another unfunded counter
+                // posted from the same account since, leaves this one unpayable
+                require!(
+                    taker_ata_b.delegate == COption::Some(self.counter.key())
+                        && taker_ata_b.delegated_amount >= amount_b,
+                    ErrorCode::CounterNotDelegated
+                );
+                (taker_ata_b, amount_b)
+            }
+            _ => return err!(ErrorCode::MissingTokenAccounts),
+        };
+
+        let maker_amount = paid.checked_sub(fee).ok_or(ErrorCode::MathOverflow)?;
+        self.pay_tokens(mint_b, from, maker_ata_b, maker_amount, remaining_accounts)?;
+
+        if fee > 0 {
+            let treasury_ata_b = self
+                .treasury_ata_b
+                .as_ref()
+                .ok_or(ErrorCode::MissingTokenAccounts)?;
+
+            self.pay_tokens(mint_b, from, treasury_ata_b, fee, remaining_accounts)?;
+        }
+
+        if let Some(counter_vault) = self.counter_vault.as_ref().filter(|_| self.counter.funded) {
+            let cpi_program = self.token_program.to_account_info();
+
+            let cpi_accounts = CloseAccount {
+                account: counter_vault.to_account_info(),
+                destination: self.taker.to_account_info(),
+                authority: self.counter.to_account_info(),
+            };
+
+            let escrow_key = self.escrow.key();
+            let signer_seeds: [&[&[u8]]; 1] = [&[
+                b"counter",
+                escrow_key.as_ref(),
+                self.taker.key.as_ref(),
+                &[self.counter.bump],
+            ]];
+
+            let cpi_context =
+                CpiContext::new_with_signer(cpi_program, cpi_accounts, &signer_seeds);
+
+            close_account(cpi_context, mint_b.to_account_info())?;
+        }
+
+        Ok(())
+    }
+
+    // Signed by the counter, which owns its vault and is the delegate on the taker's account
+    fn pay_tokens(
+        &self,
+        mint_b: &InterfaceAccount<'info, Mint>,
+        from: &InterfaceAccount<'info, TokenAccount>,
+        to: &InterfaceAccount<'info, TokenAccount>,
+        amount: u64,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> Result<()> {
+        let escrow_key = self.escrow.key();
+        let signer_seeds: [&[&[u8]]; 1] = [&[
+            b"counter",
+            escrow_key.as_ref(),
+            self.taker.key.as_ref(),
+            &[self.counter.bump],
+        ]];
+
+        let cpi_program = self.token_program.to_account_info();
+
+        let cpi_accounts = TransferChecked {
+            from: from.to_account_info(),
+            to: to.to_account_info(),
+            authority: self.counter.to_account_info(),
+            mint: mint_b.to_account_info(),
+        };
+
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, &signer_seeds)
+            .with_remaining_accounts(remaining_accounts.to_vec());
+
+        transfer_checked(cpi_ctx, amount, mint_b.decimals)
+    }
+
+    fn release_deposit(
+        &mut self,
+        amount_a: u64,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> Result<()> {
+        let (Some(mint_a), Some(taker_ata_a), Some(vault)) =
+            (&self.mint_a, &self.taker_ata_a, &self.vault)
+        else {
+            require!(self.escrow.is_native_a(), ErrorCode::MissingTokenAccounts);
+
+            // The rest of the deposit leaves with the escrow's rent when it is closed
+            self.escrow.sub_lamports(amount_a)?;
+            self.taker.add_lamports(amount_a)?;
+
+            return self.escrow.close(self.maker.to_account_info());
+        };
+
+        let signer_seeds: [&[&[u8]]; 1] = [&[
+            b"escrow",
+            self.escrow.maker.as_ref(),
+            &self.escrow.seed.to_le_bytes()[..],
+            &[self.escrow.bump],
+        ]];
+
+        let transfer = |to: &InterfaceAccount<'info, TokenAccount>, amount: u64| {
+            let cpi_program = self.token_program.to_account_info();
+
+            let cpi_accounts = TransferChecked {
+                from: vault.to_account_info(),
+                to: to.to_account_info(),
+                authority: self.escrow.to_account_info(),
+                mint: mint_a.to_account_info(),
+            };
+
+            let cpi_context =
+                CpiContext::new_with_signer(cpi_program, cpi_accounts, &signer_seeds)
+                    .with_remaining_accounts(remaining_accounts.to_vec());
+
+            transfer_checked(cpi_context, amount, mint_a.decimals)
+        };
+
+        transfer(taker_ata_a, amount_a)?;
+
+        let rest = vault
+            .amount
+            .checked_sub(amount_a)
+            .ok_or(ErrorCode::InvalidCounterOffer)?;
+        if rest > 0 {
+            let maker_ata_a = self
+                .maker_ata_a
+                .as_ref()
+                .ok_or(ErrorCode::MissingTokenAccounts)?;
+
+            transfer(maker_ata_a, rest)?;
+        }
+
+        let cpi_program = self.token_program.to_account_info();
+
+        let cpi_accounts = CloseAccount {
+            account: vault.to_account_info(),
+            destination: self.maker.to_account_info(),
+            authority: self.escrow.to_account_info(),
+        };
+
+        let cpi_context = CpiContext::new_with_signer(cpi_program, cpi_accounts, &signer_seeds);
+
+        close_account(cpi_context, mint_a.to_account_info())?;
+
+        self.escrow.close(self.maker.to_account_info())
+    }
+}
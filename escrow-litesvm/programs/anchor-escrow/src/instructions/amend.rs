@@ -0,0 +1,52 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::TokenAccount;
+
+use crate::error::ErrorCode;
+use crate::events::EscrowAmended;
+use crate::state::Escrow;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct Amend<'info> {
+    maker: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"escrow", escrow.maker.as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, Escrow>,
+    // The `maker`'s receipt token account, for escrows that minted one
+    receipt: Option<InterfaceAccount<'info, TokenAccount>>,
+}
+
+//Reprice the outstanding receive amount
+impl<'info> Amend<'info> {
+    pub fn amend(&mut self, receive: u64) -> Result<()> {
+        self.escrow.check_open()?;
+        self.escrow.check_owner(self.maker.key, &self.receipt)?;
+        // A reserver is promised the terms they reserved at
+        require!(
+            self.escrow
+                .active_reservation(Clock::get()?.unix_timestamp)
+                .is_none(),
+            ErrorCode::Reserved
+        );
+        require!(receive > 0, ErrorCode::InvalidAmount);
+        self.escrow.pricing.check(receive)?;
+
+        self.escrow.receive = receive;
+        self.escrow.bump_version()
+    }
+
+    pub fn amended_event(&self) -> Result<EscrowAmended> {
+        Ok(EscrowAmended {
+            escrow: self.escrow.key(),
+            maker: self.escrow.maker,
+            seed: self.escrow.seed,
+            deposit: self.escrow.deposit,
+            receive: self.escrow.receive,
+            version: self.escrow.version,
+            timestamp: Clock::get()?.unix_timestamp,
+        })
+    }
+}
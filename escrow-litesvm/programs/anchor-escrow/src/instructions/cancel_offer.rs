@@ -0,0 +1,32 @@
+use anchor_lang::prelude::*;
+
+use crate::state::OfferNonces;
+
+// Burns a nonce so the signed offer carrying it can no longer be taken.
+// Revoking the delegate approval instead cancels every outstanding offer at once.
+#[derive(Accounts)]
+#[instruction(nonce: u64)]
+pub struct CancelOffer<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+    #[account(
+        init_if_needed,
+        payer = maker,
+        space = 8 + OfferNonces::INIT_SPACE,
+        seeds = [
+            b"nonces",
+            maker.key().as_ref(),
+            OfferNonces::page(nonce).to_le_bytes().as_ref(),
+        ],
+        bump,
+    )]
+    pub offer_nonces: Account<'info, OfferNonces>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> CancelOffer<'info> {
+    pub fn cancel_offer(&mut self, nonce: u64, bumps: &CancelOfferBumps) -> Result<()> {
+        self.offer_nonces
+            .consume(self.maker.key(), nonce, bumps.offer_nonces)
+    }
+}
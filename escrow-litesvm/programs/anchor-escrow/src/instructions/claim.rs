@@ -0,0 +1,129 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{
+    CloseAccount, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+
+use crate::error::ErrorCode;
+use crate::events::EscrowClaimed;
+use crate::state::{leg_mint, Escrow};
+use crate::transfer::{close_account, transfer_checked};
+
+// Releases the vested part of a taken vesting escrow to its beneficiary.
+// The vault and escrow are closed to the maker once everything is claimed.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct Claim<'info> {
+    #[account(mut)]
+    pub beneficiary: Signer<'info>,
+    #[account(mut)]
+    pub maker: SystemAccount<'info>,
+    #[account(mut)]
+    pub mint_a: Option<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = beneficiary,
+        associated_token::token_program = token_program,
+    )]
+    pub beneficiary_ata_a: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        has_one = maker,
+        constraint = leg_mint(&mint_a) == escrow.mint_a @ ErrorCode::MintMismatch,
+        constraint = escrow.beneficiary == Some(beneficiary.key()) @ ErrorCode::NotBeneficiary,
+        seeds = [b"escrow", maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, Escrow>,
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = escrow,
+        associated_token::token_program = token_program,
+    )]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> Claim<'info> {
+    pub fn claim(&mut self, remaining_accounts: &[AccountInfo<'info>]) -> Result<u64> {
+        let vested = self.escrow.vested(Clock::get()?.unix_timestamp)?;
+        let amount = vested.saturating_sub(self.escrow.claimed);
+        require!(amount > 0, ErrorCode::NothingToClaim);
+
+        self.escrow.claimed = vested;
+        let fully_claimed = vested == self.escrow.deposit;
+
+        let (Some(mint_a), Some(beneficiary_ata_a), Some(vault)) =
+            (&self.mint_a, &self.beneficiary_ata_a, &self.vault)
+        else {
+            require!(self.escrow.is_native_a(), ErrorCode::MissingTokenAccounts);
+
+            self.escrow.sub_lamports(amount)?;
+            self.beneficiary.add_lamports(amount)?;
+
+            if fully_claimed {
+                self.escrow.close(self.maker.to_account_info())?;
+            }
+            return Ok(amount);
+        };
+
+        let signer_seeds: [&[&[u8]]; 1] = [&[
+            b"escrow",
+            self.maker.key.as_ref(),
+            &self.escrow.seed.to_le_bytes()[..],
+            &[self.escrow.bump],
+        ]];
+
+        // The final claim sweeps the vault so rounding never strands anything in it
+        let amount = if fully_claimed { vault.amount } else { amount };
+
+        let cpi_program = self.token_program.to_account_info();
+
+        let cpi_accounts = TransferChecked {
+            from: vault.to_account_info(),
+            to: beneficiary_ata_a.to_account_info(),
+            authority: self.escrow.to_account_info(),
+            mint: mint_a.to_account_info(),
+        };
+
+        let cpi_context = CpiContext::new_with_signer(cpi_program, cpi_accounts, &signer_seeds)
+            .with_remaining_accounts(remaining_accounts.to_vec());
+
+        transfer_checked(cpi_context, amount, mint_a.decimals)?;
+
+        if !fully_claimed {
+            return Ok(amount);
+        }
+
+        let cpi_program = self.token_program.to_account_info();
+
+        let cpi_accounts = CloseAccount {
+            account: vault.to_account_info(),
+            destination: self.maker.to_account_info(),
+            authority: self.escrow.to_account_info(),
+        };
+
+        let cpi_context = CpiContext::new_with_signer(cpi_program, cpi_accounts, &signer_seeds);
+
+        close_account(cpi_context, mint_a.to_account_info())?;
+
+        self.escrow.close(self.maker.to_account_info())?;
+        Ok(amount)
+    }
+
+    pub fn claimed_event(&self, amount: u64) -> Result<EscrowClaimed> {
+        Ok(EscrowClaimed {
+            escrow: self.escrow.key(),
+            maker: self.escrow.maker,
+            beneficiary: self.beneficiary.key(),
+            seed: self.escrow.seed,
+            mint_a: self.escrow.mint_a,
+            amount,
+            claimed: self.escrow.claimed,
+            deposit: self.escrow.deposit,
+            timestamp: Clock::get()?.unix_timestamp,
+        })
+    }
+}
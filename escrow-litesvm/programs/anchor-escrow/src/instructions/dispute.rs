@@ -0,0 +1,36 @@
+use anchor_lang::prelude::*;
+
+use crate::events::EscrowDisputed;
+use crate::state::Escrow;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct Dispute<'info> {
+    party: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"escrow", escrow.maker.as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+//Either side of a taken arbiter escrow can freeze it until the arbiter resolves it
+impl<'info> Dispute<'info> {
+    pub fn dispute(&mut self) -> Result<()> {
+        self.escrow.check_party(self.party.key)?;
+        self.escrow.disputed = true;
+
+        Ok(())
+    }
+
+    pub fn disputed_event(&self) -> Result<EscrowDisputed> {
+        Ok(EscrowDisputed {
+            escrow: self.escrow.key(),
+            maker: self.escrow.maker,
+            party: self.party.key(),
+            seed: self.escrow.seed,
+            timestamp: Clock::get()?.unix_timestamp,
+        })
+    }
+}
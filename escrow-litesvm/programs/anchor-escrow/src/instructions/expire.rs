@@ -0,0 +1,80 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use crate::error::ErrorCode;
+use crate::instructions::refund::return_vault_and_close;
+use crate::state::{leg_mint, payout_account, Escrow, MakerState};
+
+// Permissionless: anyone can crank an expired escrow back to its maker,
+// or to the holder of its receipt
+#[event_cpi]
+#[derive(Accounts)]
+pub struct Expire<'info> {
+    #[account(mut)]
+    maker: SystemAccount<'info>,
+    #[account(mut)]
+    mint_a: Option<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program,
+    )]
+    maker_ata_a: Option<InterfaceAccount<'info, TokenAccount>>,
+    // Refunded instead of `maker_ata_a` when the escrow chose a refund account on `make_for`
+    #[account(
+        mut,
+        constraint = escrow.refund_to == Some(refund_to.key()) @ ErrorCode::PayoutAccountMismatch,
+    )]
+    refund_to: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        close = maker,
+        constraint = leg_mint(&mint_a) == escrow.mint_a @ ErrorCode::MintMismatch,
+        seeds = [b"escrow", escrow.maker.as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, Escrow>,
+    // The `maker`'s receipt token account, for escrows that minted one
+    receipt: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [b"maker", escrow.maker.as_ref()],
+        bump = maker_state.bump,
+    )]
+    maker_state: Account<'info, MakerState>,
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = escrow,
+        associated_token::token_program = token_program,
+    )]
+    vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    token_program: Interface<'info, TokenInterface>,
+    system_program: Program<'info, System>,
+}
+
+impl<'info> Expire<'info> {
+    pub fn expire_and_close_vault(
+        &mut self,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> Result<()> {
+        self.escrow.check_open()?;
+        self.escrow.check_owner(self.maker.key, &self.receipt)?;
+        require!(
+            Clock::get()?.unix_timestamp >= self.escrow.expiry_time,
+            ErrorCode::NotExpired
+        );
+        self.maker_state.close_offer();
+
+        return_vault_and_close(
+            &self.escrow,
+            &self.vault,
+            payout_account(self.escrow.refund_to, &self.refund_to, &self.maker_ata_a)?,
+            &self.mint_a,
+            &self.maker.to_account_info(),
+            &self.token_program,
+            remaining_accounts,
+        )
+    }
+}
@@ -0,0 +1,49 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+use crate::program::AnchorEscrow;
+use crate::state::{Config, MAX_FEE_BPS};
+
+// Only the program's upgrade authority can create the config, so nobody can front-run the deployer
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(mut)]
+    admin: Signer<'info>,
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    program: Program<'info, AnchorEscrow>,
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(admin.key())
+            @ ErrorCode::NotUpgradeAuthority,
+    )]
+    program_data: Account<'info, ProgramData>,
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"config"],
+        bump,
+        space = 8 + Config::INIT_SPACE,
+    )]
+    pub config: Account<'info, Config>,
+    system_program: Program<'info, System>,
+}
+
+impl<'info> InitializeConfig<'info> {
+    pub fn initialize_config(
+        &mut self,
+        fee_bps: u16,
+        treasury: Pubkey,
+        bumps: &InitializeConfigBumps,
+    ) -> Result<()> {
+        require!(fee_bps <= MAX_FEE_BPS, ErrorCode::InvalidFee);
+
+        self.config.set_inner(Config {
+            admin: self.admin.key(),
+            treasury,
+            fee_bps,
+            paused: false,
+            bump: bumps.config,
+        });
+
+        Ok(())
+    }
+}
@@ -1,28 +1,54 @@
 use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+    token_2022::{spl_token_2022::instruction::AuthorityType, Token2022},
+    token_interface::{
+        mint_to, set_authority, Mint, MintTo, SetAuthority, TokenAccount, TokenInterface,
+        TransferChecked,
+    },
 };
 
-use crate::state::Escrow;
+use crate::error::ErrorCode;
+use crate::events::EscrowMade;
+use crate::state::{leg_mint, Config, Escrow, MakerState, Pricing, TimeWindows, Vesting};
+use crate::transfer::transfer_checked;
 
+// Leave `mint_a`, `maker_ata_a` and `vault` out to offer lamports held by the escrow PDA,
+// or leave `mint_b` out to ask for lamports in return.
+// The escrow seed is the maker's next sequential ID, taken from `maker_state`.
+// Pass the receipt accounts to mint the maker a transferable receipt for the escrow.
+#[event_cpi]
 #[derive(Accounts)]
-#[instruction(seed: u64)]
 pub struct Make<'info> {
     #[account(mut)]
     pub maker: Signer<'info>,
-    pub mint_a: InterfaceAccount<'info, Mint>,
-    pub mint_b: InterfaceAccount<'info, Mint>,
+    pub mint_a: Option<InterfaceAccount<'info, Mint>>,
+    pub mint_b: Option<InterfaceAccount<'info, Mint>>,
     #[account(
         mut,
         associated_token::mint = mint_a,
         associated_token::authority = maker,
+        associated_token::token_program = token_program,
     )]
-    pub maker_ata_a: InterfaceAccount<'info, TokenAccount>,
+    pub maker_ata_a: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        init_if_needed,
+        payer = maker,
+        seeds = [b"maker", maker.key().as_ref()],
+        bump,
+        space = 8 + MakerState::INIT_SPACE,
+    )]
+    pub maker_state: Account<'info, MakerState>,
     #[account(
         init,
         payer = maker,
-        seeds = [b"escrow", maker.key().as_ref(), seed.to_le_bytes().as_ref()],
+        seeds = [b"escrow", maker.key().as_ref(), maker_state.next_seed.to_le_bytes().as_ref()],
         bump,
         space = 8 + Escrow::INIT_SPACE,
     )]
@@ -32,41 +58,225 @@ pub struct Make<'info> {
         payer = maker,
         associated_token::mint = mint_a,
         associated_token::authority = escrow,
+        associated_token::token_program = token_program,
     )]
-    pub vault: InterfaceAccount<'info, TokenAccount>,
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init,
+        payer = maker,
+        seeds = [b"receipt", escrow.key().as_ref()],
+        bump,
+        mint::decimals = 0,
+        mint::authority = escrow,
+        mint::token_program = receipt_token_program,
+    )]
+    pub receipt_mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(
+        init,
+        payer = maker,
+        associated_token::mint = receipt_mint,
+        associated_token::authority = maker,
+        associated_token::token_program = receipt_token_program,
+    )]
+    pub maker_receipt: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub receipt_token_program: Option<Program<'info, Token2022>>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
 }
 
 impl<'info> Make<'info> {
-    pub fn init_escrow(&mut self, seed: u64, receive: u64, bumps: &MakeBumps) -> Result<()> {
+    #[allow(clippy::too_many_arguments)]
+    pub fn init_escrow(
+        &mut self,
+        receive: u64,
+        windows: TimeWindows,
+        allowed_taker: Option<Pubkey>,
+        allowlist_root: Option<[u8; 32]>,
+        receive_net_of_fee: bool,
+        pricing: Pricing,
+        vesting: Option<Vesting>,
+        arbiter: Option<Pubkey>,
+        bumps: &MakeBumps,
+    ) -> Result<()> {
+        self.config.check_not_paused()?;
+
+        let creation_time = Clock::get()?.unix_timestamp;
+        windows.check(creation_time)?;
+        require!(
+            self.mint_a.is_some() || self.mint_b.is_some(),
+            ErrorCode::BothLegsNative
+        );
+
+        pricing.check(receive)?;
+        if let Some(vesting) = vesting {
+            vesting.check()?;
+        }
+        // Arbiter escrows hold both legs in the escrow's ATAs, which need two distinct mints
+        if let Some(arbiter) = arbiter {
+            require!(
+                arbiter != self.maker.key()
+                    && leg_mint(&self.mint_a) != leg_mint(&self.mint_b)
+                    && vesting.is_none()
+                    && self.receipt_mint.is_none(),
+                ErrorCode::InvalidArbiter
+            );
+        }
+
+        if self.maker_state.maker == Pubkey::default() {
+            self.maker_state.maker = self.maker.key();
+            self.maker_state.bump = bumps.maker_state;
+        }
+        let seed = self.maker_state.open_offer()?;
+
         self.escrow.set_inner(Escrow {
             seed,
             maker: self.maker.key(),
-            mint_a: self.mint_a.key(),
-            mint_b: self.mint_b.key(),
+            mint_a: leg_mint(&self.mint_a),
+            mint_b: leg_mint(&self.mint_b),
+            deposit: 0,
             receive,
-            creation_time: Clock::get()?.unix_timestamp,
+            receive_net_of_fee,
+            creation_time,
+            take_open_time: windows.take_open_time,
+            expiry_time: windows.expiry_time,
+            refund_lock_time: windows.refund_lock_time,
+            allowed_taker,
+            allowlist_root,
+            pricing,
+            version: 0,
+            vesting,
+            beneficiary: None,
+            vesting_start: 0,
+            claimed: 0,
+            arbiter,
+            held_b: 0,
+            maker_approved: false,
+            taker_approved: false,
+            disputed: false,
+            receipt_mint: self.receipt_mint.as_ref().map(|mint| mint.key()),
+            proceeds_to: None,
+            refund_to: None,
+            reservation_terms: None,
+            reservation: None,
             bump: bumps.escrow,
         });
 
         Ok(())
     }
 
-    pub fn deposit(&mut self, deposit: u64) -> Result<()> {
+    // Mints the single receipt token to the maker and revokes the mint authority
+    pub fn mint_receipt(&mut self) -> Result<()> {
+        let (Some(receipt_mint), Some(maker_receipt), Some(receipt_token_program)) =
+            (&self.receipt_mint, &self.maker_receipt, &self.receipt_token_program)
+        else {
+            require!(
+                self.receipt_mint.is_none()
+                    && self.maker_receipt.is_none()
+                    && self.receipt_token_program.is_none(),
+                ErrorCode::MissingReceiptAccounts
+            );
+            return Ok(());
+        };
+
+        let signer_seeds: [&[&[u8]]; 1] = [&[
+            b"escrow",
+            self.maker.key.as_ref(),
+            &self.escrow.seed.to_le_bytes()[..],
+            &[self.escrow.bump],
+        ]];
+
+        let cpi_program = receipt_token_program.to_account_info();
+
+        let cpi_accounts = MintTo {
+            mint: receipt_mint.to_account_info(),
+            to: maker_receipt.to_account_info(),
+            authority: self.escrow.to_account_info(),
+        };
+
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, &signer_seeds);
+
+        mint_to(cpi_ctx, 1)?;
+
+        let cpi_program = receipt_token_program.to_account_info();
+
+        let cpi_accounts = SetAuthority {
+            current_authority: self.escrow.to_account_info(),
+            account_or_mint: receipt_mint.to_account_info(),
+        };
+
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, &signer_seeds);
+
+        set_authority(cpi_ctx, AuthorityType::MintTokens, None)
+    }
+
+    pub fn deposit(
+        &mut self,
+        deposit: u64,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> Result<()> {
+        let (Some(mint_a), Some(maker_ata_a), Some(vault)) =
+            (&self.mint_a, &self.maker_ata_a, &mut self.vault)
+        else {
+            require!(
+                self.mint_a.is_none() && self.maker_ata_a.is_none() && self.vault.is_none(),
+                ErrorCode::MissingTokenAccounts
+            );
+            return self.deposit_lamports(deposit);
+        };
+
         let cpi_program = self.token_program.to_account_info();
 
         let cpi_accounts = TransferChecked {
-            from: self.maker_ata_a.to_account_info(),
-            to: self.vault.to_account_info(),
+            from: maker_ata_a.to_account_info(),
+            to: vault.to_account_info(),
             authority: self.maker.to_account_info(),
-            mint: self.mint_a.to_account_info(),
+            mint: mint_a.to_account_info(),
+        };
+
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts)
+            .with_remaining_accounts(remaining_accounts.to_vec());
+
+        transfer_checked(cpi_ctx, deposit, mint_a.decimals)?;
+
+        // Record what actually reached the vault, which is less than `deposit` for fee mints
+        vault.reload()?;
+        self.escrow.deposit = vault.amount;
+
+        Ok(())
+    }
+
+    pub fn made_event(&self) -> Result<EscrowMade> {
+        Ok(EscrowMade {
+            escrow: self.escrow.key(),
+            maker: self.maker.key(),
+            seed: self.escrow.seed,
+            mint_a: self.escrow.mint_a,
+            mint_b: self.escrow.mint_b,
+            deposit: self.escrow.deposit,
+            receive: self.escrow.receive,
+            take_open_time: self.escrow.take_open_time,
+            expiry_time: self.escrow.expiry_time,
+            refund_lock_time: self.escrow.refund_lock_time,
+            allowed_taker: self.escrow.allowed_taker,
+            timestamp: Clock::get()?.unix_timestamp,
+        })
+    }
+
+    // Native SOL offers keep the lamports on the escrow PDA itself, on top of its rent
+    fn deposit_lamports(&mut self, deposit: u64) -> Result<()> {
+        let cpi_program = self.system_program.to_account_info();
+
+        let cpi_accounts = Transfer {
+            from: self.maker.to_account_info(),
+            to: self.escrow.to_account_info(),
         };
 
         let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
 
-        transfer_checked(cpi_ctx, deposit, self.mint_a.decimals)?;
+        transfer(cpi_ctx, deposit)?;
+
+        self.escrow.deposit = deposit;
 
         Ok(())
     }
@@ -0,0 +1,153 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::{
+        create, get_associated_token_address_with_program_id, AssociatedToken, Create,
+    },
+    token_interface::{TokenInterface, TransferChecked},
+};
+
+use crate::error::ErrorCode;
+use crate::events::BasketMade;
+use crate::instructions::refund_basket::{check_basket_mint, read_mint, read_token_account};
+use crate::state::{BasketEscrow, BasketLeg, Config, MakerState};
+use crate::transfer::transfer_checked;
+
+// Offered legs come in as remaining accounts, one `[mint, maker_ata, vault]` triple per
+// entry of `deposits`. Each vault is created here as the basket PDA's ATA for that mint.
+// The basket seed is taken from `maker_state`, like an escrow's.
+// Baskets are simpler than single escrows: every leg goes through the one `token_program`,
+// transfer-hook and transfer-fee mints are rejected, and there are no time windows or expiry
+// (only the maker can close one, through `refund_basket`).
+#[event_cpi]
+#[derive(Accounts)]
+pub struct MakeBasket<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        init_if_needed,
+        payer = maker,
+        seeds = [b"maker", maker.key().as_ref()],
+        bump,
+        space = 8 + MakerState::INIT_SPACE,
+    )]
+    pub maker_state: Account<'info, MakerState>,
+    #[account(
+        init,
+        payer = maker,
+        seeds = [b"basket", maker.key().as_ref(), maker_state.next_seed.to_le_bytes().as_ref()],
+        bump,
+        space = 8 + BasketEscrow::INIT_SPACE,
+    )]
+    pub basket: Account<'info, BasketEscrow>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> MakeBasket<'info> {
+    pub fn init_basket(
+        &mut self,
+        requested: Vec<BasketLeg>,
+        bumps: &MakeBasketBumps,
+    ) -> Result<()> {
+        self.config.check_not_paused()?;
+        BasketEscrow::check_legs(&requested)?;
+
+        if self.maker_state.maker == Pubkey::default() {
+            self.maker_state.maker = self.maker.key();
+            self.maker_state.bump = bumps.maker_state;
+        }
+        let seed = self.maker_state.take_seed()?;
+
+        self.basket.set_inner(BasketEscrow {
+            seed,
+            maker: self.maker.key(),
+            offered: Vec::new(),
+            requested,
+            creation_time: Clock::get()?.unix_timestamp,
+            bump: bumps.basket,
+        });
+
+        Ok(())
+    }
+
+    pub fn deposit(
+        &mut self,
+        deposits: Vec<u64>,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> Result<()> {
+        require!(
+            remaining_accounts.len() == deposits.len() * 3,
+            ErrorCode::BasketAccountsMismatch
+        );
+
+        for (deposit, accounts) in deposits.into_iter().zip(remaining_accounts.chunks(3)) {
+            let [mint, maker_ata, vault] = accounts else {
+                return err!(ErrorCode::BasketAccountsMismatch);
+            };
+            let mint_account = read_mint(mint)?;
+            check_basket_mint(mint)?;
+            require_keys_eq!(
+                vault.key(),
+                get_associated_token_address_with_program_id(
+                    &self.basket.key(),
+                    mint.key,
+                    self.token_program.key
+                ),
+                ErrorCode::BasketAccountsMismatch
+            );
+
+            // Fails if the vault already exists, which also rules out the same mint twice
+            let cpi_program = self.associated_token_program.to_account_info();
+
+            let cpi_accounts = Create {
+                payer: self.maker.to_account_info(),
+                associated_token: vault.clone(),
+                authority: self.basket.to_account_info(),
+                mint: mint.clone(),
+                system_program: self.system_program.to_account_info(),
+                token_program: self.token_program.to_account_info(),
+            };
+
+            create(CpiContext::new(cpi_program, cpi_accounts))?;
+
+            let cpi_program = self.token_program.to_account_info();
+
+            let cpi_accounts = TransferChecked {
+                from: maker_ata.clone(),
+                to: vault.clone(),
+                authority: self.maker.to_account_info(),
+                mint: mint.clone(),
+            };
+
+            let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+
+            transfer_checked(cpi_ctx, deposit, mint_account.decimals)?;
+
+            // Record what actually reached the vault, as `make` does for fee mints
+            let vault_account = read_token_account(vault)?;
+            self.basket.offered.push(BasketLeg {
+                mint: mint.key(),
+                amount: vault_account.amount,
+            });
+        }
+
+        BasketEscrow::check_legs(&self.basket.offered)
+    }
+
+    pub fn made_event(&self) -> Result<BasketMade> {
+        Ok(BasketMade {
+            basket: self.basket.key(),
+            maker: self.maker.key(),
+            seed: self.basket.seed,
+            offered: self.basket.offered.clone(),
+            requested: self.basket.requested.clone(),
+            timestamp: Clock::get()?.unix_timestamp,
+        })
+    }
+}
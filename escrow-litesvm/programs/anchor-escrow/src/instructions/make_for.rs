@@ -0,0 +1,175 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+
+use crate::events::EscrowMade;
+use crate::state::{Config, Escrow, MakerState, Pricing, TimeWindows};
+use crate::transfer::transfer_checked;
+
+// `make` for makers that are PDAs of another program, signing through `invoke_signed`.
+// `payer` funds the new accounts, so the maker does not need to hold lamports, and the
+// deposit comes from any `mint_a` account the maker owns rather than its ATA.
+// Take proceeds go to `proceeds_to` and refunds to `refund_to`, both fixed here.
+// Rent still goes back to the maker when the escrow closes. Both legs are SPL tokens.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct MakeFor<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub maker: Signer<'info>,
+    pub mint_a: InterfaceAccount<'info, Mint>,
+    pub mint_b: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        token::mint = mint_a,
+        token::authority = maker,
+        token::token_program = token_program,
+    )]
+    pub maker_token_a: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        token::mint = mint_b,
+        token::token_program = token_program,
+    )]
+    pub proceeds_to: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        token::mint = mint_a,
+        token::token_program = token_program,
+    )]
+    pub refund_to: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        seeds = [b"maker", maker.key().as_ref()],
+        bump,
+        space = 8 + MakerState::INIT_SPACE,
+    )]
+    pub maker_state: Account<'info, MakerState>,
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"escrow", maker.key().as_ref(), maker_state.next_seed.to_le_bytes().as_ref()],
+        bump,
+        space = 8 + Escrow::INIT_SPACE,
+    )]
+    pub escrow: Account<'info, Escrow>,
+    #[account(
+        init,
+        payer = payer,
+        associated_token::mint = mint_a,
+        associated_token::authority = escrow,
+        associated_token::token_program = token_program,
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> MakeFor<'info> {
+    pub fn init_escrow(
+        &mut self,
+        receive: u64,
+        windows: TimeWindows,
+        allowed_taker: Option<Pubkey>,
+        receive_net_of_fee: bool,
+        pricing: Pricing,
+        bumps: &MakeForBumps,
+    ) -> Result<()> {
+        self.config.check_not_paused()?;
+
+        let creation_time = Clock::get()?.unix_timestamp;
+        windows.check(creation_time)?;
+        pricing.check(receive)?;
+
+        if self.maker_state.maker == Pubkey::default() {
+            self.maker_state.maker = self.maker.key();
+            self.maker_state.bump = bumps.maker_state;
+        }
+        let seed = self.maker_state.open_offer()?;
+
+        self.escrow.set_inner(Escrow {
+            seed,
+            maker: self.maker.key(),
+            mint_a: self.mint_a.key(),
+            mint_b: self.mint_b.key(),
+            deposit: 0,
+            receive,
+            receive_net_of_fee,
+            creation_time,
+            take_open_time: windows.take_open_time,
+            expiry_time: windows.expiry_time,
+            refund_lock_time: windows.refund_lock_time,
+            allowed_taker,
+            allowlist_root: None,
+            pricing,
+            version: 0,
+            vesting: None,
+            beneficiary: None,
+            vesting_start: 0,
+            claimed: 0,
+            arbiter: None,
+            held_b: 0,
+            maker_approved: false,
+            taker_approved: false,
+            disputed: false,
+            receipt_mint: None,
+            proceeds_to: Some(self.proceeds_to.key()),
+            refund_to: Some(self.refund_to.key()),
+            reservation_terms: None,
+            reservation: None,
+            bump: bumps.escrow,
+        });
+
+        Ok(())
+    }
+
+    pub fn deposit(
+        &mut self,
+        deposit: u64,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> Result<()> {
+        let cpi_program = self.token_program.to_account_info();
+
+        let cpi_accounts = TransferChecked {
+            from: self.maker_token_a.to_account_info(),
+            to: self.vault.to_account_info(),
+            authority: self.maker.to_account_info(),
+            mint: self.mint_a.to_account_info(),
+        };
+
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts)
+            .with_remaining_accounts(remaining_accounts.to_vec());
+
+        transfer_checked(cpi_ctx, deposit, self.mint_a.decimals)?;
+
+        // Record what actually reached the vault, which is less than `deposit` for fee mints
+        self.vault.reload()?;
+        self.escrow.deposit = self.vault.amount;
+
+        Ok(())
+    }
+
+    pub fn made_event(&self) -> Result<EscrowMade> {
+        Ok(EscrowMade {
+            escrow: self.escrow.key(),
+            maker: self.maker.key(),
+            seed: self.escrow.seed,
+            mint_a: self.escrow.mint_a,
+            mint_b: self.escrow.mint_b,
+            deposit: self.escrow.deposit,
+            receive: self.escrow.receive,
+            take_open_time: self.escrow.take_open_time,
+            expiry_time: self.escrow.expiry_time,
+            refund_lock_time: self.escrow.refund_lock_time,
+            allowed_taker: self.escrow.allowed_taker,
+            timestamp: Clock::get()?.unix_timestamp,
+        })
+    }
+}
@@ -0,0 +1,331 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{
+        CloseAccount, Mint, TokenAccount, TokenInterface, TransferChecked,
+    },
+};
+
+use crate::error::ErrorCode;
+use crate::events::EscrowTaken;
+use crate::fees::gross_up_for_fee;
+use crate::state::{Config, Escrow, MakerState, Pricing};
+use crate::transfer::{close_account, transfer_checked};
+
+// Settles two escrows that cross: `escrow_x` offers `mint_a` for `mint_b` and `escrow_y`
+// offers `mint_b` for `mint_a`. Anyone can match them and keeps the spread.
+// Both legs have to be SPL tokens, and the escrows are boxed to keep this many accounts
+// within the stack frame.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct MatchEscrows<'info> {
+    #[account(mut)]
+    pub matcher: Signer<'info>,
+    #[account(mut)]
+    pub maker_x: SystemAccount<'info>,
+    #[account(mut)]
+    pub maker_y: SystemAccount<'info>,
+    #[account(mut)]
+    pub mint_a: InterfaceAccount<'info, Mint>,
+    #[account(mut)]
+    pub mint_b: InterfaceAccount<'info, Mint>,
+    #[account(
+        init_if_needed,
+        payer = matcher,
+        associated_token::mint = mint_b,
+        associated_token::authority = maker_x,
+        associated_token::token_program = token_program,
+    )]
+    pub maker_x_ata_b: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = matcher,
+        associated_token::mint = mint_a,
+        associated_token::authority = maker_y,
+        associated_token::token_program = token_program,
+    )]
+    pub maker_y_ata_a: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = matcher,
+        associated_token::mint = mint_a,
+        associated_token::authority = matcher,
+        associated_token::token_program = token_program,
+    )]
+    pub matcher_ata_a: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = matcher,
+        associated_token::mint = mint_b,
+        associated_token::authority = matcher,
+        associated_token::token_program = token_program,
+    )]
+    pub matcher_ata_b: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, Config>,
+    #[account(address = config.treasury)]
+    pub treasury: SystemAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = matcher,
+        associated_token::mint = mint_a,
+        associated_token::authority = treasury,
+        associated_token::token_program = token_program,
+    )]
+    pub treasury_ata_a: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = matcher,
+        associated_token::mint = mint_b,
+        associated_token::authority = treasury,
+        associated_token::token_program = token_program,
+    )]
+    pub treasury_ata_b: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        close = maker_x,
+        constraint = escrow_x.maker == maker_x.key() @ ErrorCode::NotEscrowOwner,
+        constraint = escrow_x.mint_a == mint_a.key() @ ErrorCode::MintMismatch,
+        constraint = escrow_x.mint_b == mint_b.key() @ ErrorCode::MintMismatch,
+        seeds = [b"escrow", escrow_x.maker.as_ref(), escrow_x.seed.to_le_bytes().as_ref()],
+        bump = escrow_x.bump,
+    )]
+    pub escrow_x: Box<Account<'info, Escrow>>,
+    #[account(
+        mut,
+        close = maker_y,
+        constraint = escrow_y.maker == maker_y.key() @ ErrorCode::NotEscrowOwner,
+        constraint = escrow_y.mint_a == mint_b.key() @ ErrorCode::MintMismatch,
+        constraint = escrow_y.mint_b == mint_a.key() @ ErrorCode::MintMismatch,
+        seeds = [b"escrow", escrow_y.maker.as_ref(), escrow_y.seed.to_le_bytes().as_ref()],
+        bump = escrow_y.bump,
+    )]
+    pub escrow_y: Box<Account<'info, Escrow>>,
+    #[account(
+        mut,
+        seeds = [b"maker", escrow_x.maker.as_ref()],
+        bump = maker_state_x.bump,
+    )]
+    pub maker_state_x: Account<'info, MakerState>,
+    #[account(
+        mut,
+        seeds = [b"maker", escrow_y.maker.as_ref()],
+        bump = maker_state_y.bump,
+    )]
+    pub maker_state_y: Account<'info, MakerState>,
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = escrow_x,
+        associated_token::token_program = token_program,
+    )]
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = escrow_y,
+        associated_token::token_program = token_program,
+    )]
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+//Pay each maker its current price out of the other escrow's vault, less the protocol fee
+//sent to treasury, and sweep what is left in both vaults to the matcher.
+//Both vaults and escrows are closed.
+impl<'info> MatchEscrows<'info> {
+    pub fn match_escrows(
+        &mut self,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> Result<[EscrowTaken; 2]> {
+        self.config.check_not_paused()?;
+        // Also rules out passing the same escrow as both sides
+        require_keys_neq!(self.mint_a.key(), self.mint_b.key(), ErrorCode::MintMismatch);
+        let now = Clock::get()?.unix_timestamp;
+
+        let pairs = [(&self.escrow_x, &self.escrow_y), (&self.escrow_y, &self.escrow_x)];
+        for (escrow, counterparty) in pairs {
+            escrow.check_takeable(now)?;
+            escrow.check_taker(&counterparty.maker)?;
+            require!(
+                !matches!(escrow.pricing, Pricing::Oracle(_))
+                    && escrow.allowlist_root.is_none()
+                    && escrow.vesting.is_none()
+                    && escrow.arbiter.is_none()
+                    && escrow.receipt_mint.is_none()
+                    && escrow.proceeds_to.is_none()
+                    && escrow.reservation_terms.is_none(),
+                ErrorCode::MatchUnsupported
+            );
+        }
+
+        // `escrow_x` is paid in `mint_b` out of `vault_y`, `escrow_y` in `mint_a` out of `vault_x`
+        let price_x = self.escrow_x.current_receive(now)?;
+        let price_y = self.escrow_y.current_receive(now)?;
+        let (amount_x, fee_x) =
+            self.maker_amount(&self.mint_b, price_x, self.escrow_x.receive_net_of_fee)?;
+        let (amount_y, fee_y) =
+            self.maker_amount(&self.mint_a, price_y, self.escrow_y.receive_net_of_fee)?;
+
+        // Whatever each vault holds beyond the other maker's price is the matcher's spread
+        let spread_b = self
+            .vault_y
+            .amount
+            .checked_sub(amount_x.checked_add(fee_x).ok_or(ErrorCode::MathOverflow)?)
+            .ok_or(ErrorCode::EscrowsDoNotCross)?;
+        let spread_a = self
+            .vault_x
+            .amount
+            .checked_sub(amount_y.checked_add(fee_y).ok_or(ErrorCode::MathOverflow)?)
+            .ok_or(ErrorCode::EscrowsDoNotCross)?;
+
+        let events = [
+            self.taken_event(&self.escrow_x, self.vault_x.amount, price_x, fee_x, now),
+            self.taken_event(&self.escrow_y, self.vault_y.amount, price_y, fee_y, now),
+        ];
+
+        let (escrow_x, vault_x, mint_a) = (&self.escrow_x, &self.vault_x, &self.mint_a);
+        let (escrow_y, vault_y, mint_b) = (&self.escrow_y, &self.vault_y, &self.mint_b);
+        let rem = remaining_accounts;
+
+        self.release(escrow_y, vault_y, mint_b, &self.maker_x_ata_b, amount_x, rem)?;
+        self.release(escrow_x, vault_x, mint_a, &self.maker_y_ata_a, amount_y, rem)?;
+
+        if fee_x > 0 {
+            let treasury_ata_b = self
+                .treasury_ata_b
+                .as_ref()
+                .ok_or(ErrorCode::MissingTokenAccounts)?;
+
+            self.release(escrow_y, vault_y, mint_b, treasury_ata_b, fee_x, rem)?;
+        }
+        if fee_y > 0 {
+            let treasury_ata_a = self
+                .treasury_ata_a
+                .as_ref()
+                .ok_or(ErrorCode::MissingTokenAccounts)?;
+
+            self.release(escrow_x, vault_x, mint_a, treasury_ata_a, fee_y, rem)?;
+        }
+
+        self.release(escrow_y, vault_y, mint_b, &self.matcher_ata_b, spread_b, rem)?;
+        self.release(escrow_x, vault_x, mint_a, &self.matcher_ata_a, spread_a, rem)?;
+
+        self.close_vault(escrow_x, vault_x, mint_a, &self.maker_x)?;
+        self.close_vault(escrow_y, vault_y, mint_b, &self.maker_y)?;
+
+        self.maker_state_x.close_offer();
+        self.maker_state_y.close_offer();
+
+        Ok(events)
+    }
+
+    // What a maker asking `price` is sent, grossed up for `mint`'s transfer fee if it asked
+    // to receive net of it, and the protocol fee kept out of `price`
+    fn maker_amount(
+        &self,
+        mint: &InterfaceAccount<'info, Mint>,
+        price: u64,
+        receive_net_of_fee: bool,
+    ) -> Result<(u64, u64)> {
+        let fee = self.config.fee(price)?;
+        let maker_amount = price - fee;
+        let amount = if receive_net_of_fee {
+            gross_up_for_fee(mint, maker_amount)?
+        } else {
+            maker_amount
+        };
+        Ok((amount, fee))
+    }
+
+    fn taken_event(
+        &self,
+        escrow: &Account<'info, Escrow>,
+        amount_a: u64,
+        amount_b: u64,
+        fee: u64,
+        now: i64,
+    ) -> EscrowTaken {
+        EscrowTaken {
+            escrow: escrow.key(),
+            maker: escrow.maker,
+            taker: self.matcher.key(),
+            seed: escrow.seed,
+            mint_a: escrow.mint_a,
+            mint_b: escrow.mint_b,
+            amount_a,
+            amount_b,
+            fee,
+            remaining_deposit: 0,
+            remaining_receive: 0,
+            timestamp: now,
+        }
+    }
+
+    fn release(
+        &self,
+        escrow: &Account<'info, Escrow>,
+        vault: &InterfaceAccount<'info, TokenAccount>,
+        mint: &InterfaceAccount<'info, Mint>,
+        to: &InterfaceAccount<'info, TokenAccount>,
+        amount: u64,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+
+        let signer_seeds: [&[&[u8]]; 1] = [&[
+            b"escrow",
+            escrow.maker.as_ref(),
+            &escrow.seed.to_le_bytes()[..],
+            &[escrow.bump],
+        ]];
+
+        let cpi_program = self.token_program.to_account_info();
+
+        let cpi_accounts = TransferChecked {
+            from: vault.to_account_info(),
+            to: to.to_account_info(),
+            authority: escrow.to_account_info(),
+            mint: mint.to_account_info(),
+        };
+
+        let cpi_context = CpiContext::new_with_signer(cpi_program, cpi_accounts, &signer_seeds)
+            .with_remaining_accounts(remaining_accounts.to_vec());
+
+        transfer_checked(cpi_context, amount, mint.decimals)
+    }
+
+    fn close_vault(
+        &self,
+        escrow: &Account<'info, Escrow>,
+        vault: &InterfaceAccount<'info, TokenAccount>,
+        mint: &InterfaceAccount<'info, Mint>,
+        maker: &SystemAccount<'info>,
+    ) -> Result<()> {
+        let signer_seeds: [&[&[u8]]; 1] = [&[
+            b"escrow",
+            escrow.maker.as_ref(),
+            &escrow.seed.to_le_bytes()[..],
+            &[escrow.bump],
+        ]];
+
+        let cpi_program = self.token_program.to_account_info();
+
+        let cpi_accounts = CloseAccount {
+            account: vault.to_account_info(),
+            destination: maker.to_account_info(),
+            authority: escrow.to_account_info(),
+        };
+
+        let cpi_context = CpiContext::new_with_signer(cpi_program, cpi_accounts, &signer_seeds);
+
+        close_account(cpi_context, mint.to_account_info())
+    }
+}
@@ -1,7 +1,51 @@
+pub mod accept_counter;
+pub mod amend;
+pub mod cancel_offer;
+pub mod claim;
+pub mod dispute;
+pub mod expire;
+pub mod initialize_config;
 pub mod make;
+pub mod make_basket;
+pub mod make_for;
+pub mod match_escrows;
+pub mod post_counter;
 pub mod refund;
+pub mod refund_basket;
+pub mod reject_counter;
+pub mod reserve;
+pub mod set_reservation_terms;
+pub mod settle;
 pub mod take;
+pub mod take_basket;
+pub mod take_many;
+pub mod take_offer;
+pub mod take_partial;
+pub mod top_up;
+pub mod update_config;
 
+pub use accept_counter::*;
+pub use amend::*;
+pub use cancel_offer::*;
+pub use claim::*;
+pub use dispute::*;
+pub use expire::*;
+pub use initialize_config::*;
 pub use make::*;
+pub use make_basket::*;
+pub use make_for::*;
+pub use match_escrows::*;
+pub use post_counter::*;
 pub use refund::*;
+pub use refund_basket::*;
+pub use reject_counter::*;
+pub use reserve::*;
+pub use set_reservation_terms::*;
+pub use settle::*;
 pub use take::*;
+pub use take_basket::*;
+pub use take_many::*;
+pub use take_offer::*;
+pub use take_partial::*;
+pub use top_up::*;
+pub use update_config::*;
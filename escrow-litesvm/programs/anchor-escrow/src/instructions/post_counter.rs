@@ -0,0 +1,141 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{approve, Approve, Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+
+use crate::error::ErrorCode;
+use crate::events::CounterPosted;
+use crate::state::{leg_mint, CounterOffer, Escrow};
+use crate::transfer::transfer_checked;
+
+// One open counter-offer per taker and escrow. The B-side accounts are left out when
+// the maker asked for native SOL, and `counter_vault` for counters that are not funded.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct PostCounter<'info> {
+    #[account(mut)]
+    pub taker: Signer<'info>,
+    pub mint_b: Option<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_ata_b: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        constraint = leg_mint(&mint_b) == escrow.mint_b @ ErrorCode::MintMismatch,
+        seeds = [b"escrow", escrow.maker.as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, Escrow>,
+    #[account(
+        init,
+        payer = taker,
+        space = 8 + CounterOffer::INIT_SPACE,
+        seeds = [b"counter", escrow.key().as_ref(), taker.key().as_ref()],
+        bump,
+    )]
+    pub counter: Account<'info, CounterOffer>,
+    #[account(
+        init,
+        payer = taker,
+        associated_token::mint = mint_b,
+        associated_token::authority = counter,
+        associated_token::token_program = token_program,
+    )]
+    pub counter_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+//Record the taker's terms, then either move `amount_b` into the counter
+//or let the counter spend it from the taker's account once accepted
+impl<'info> PostCounter<'info> {
+    pub fn post(
+        &mut self,
+        amount_a: u64,
+        amount_b: u64,
+        funded: bool,
+        bumps: &PostCounterBumps,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> Result<()> {
+        self.escrow.check_takeable(Clock::get()?.unix_timestamp)?;
+        self.escrow.check_counterable(self.taker.key, amount_a, amount_b)?;
+
+        self.counter.set_inner(CounterOffer {
+            escrow: self.escrow.key(),
+            taker: self.taker.key(),
+            mint_b: self.escrow.mint_b,
+            amount_a,
+            amount_b,
+            funded,
+            bump: bumps.counter,
+        });
+
+        let (Some(mint_b), Some(taker_ata_b)) = (&self.mint_b, &self.taker_ata_b) else {
+            require!(self.escrow.is_native_b(), ErrorCode::MissingTokenAccounts);
+            require!(funded, ErrorCode::UnfundedNativeCounter);
+
+            let cpi_program = self.system_program.to_account_info();
+
+            let cpi_accounts = Transfer {
+                from: self.taker.to_account_info(),
+                to: self.counter.to_account_info(),
+            };
+
+            let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+
+            return transfer(cpi_ctx, amount_b);
+        };
+
+        // This replaces any delegation the account had, including one for another counter
+        if !funded {
+            let cpi_program = self.token_program.to_account_info();
+
+            let cpi_accounts = Approve {
+                to: taker_ata_b.to_account_info(),
+                delegate: self.counter.to_account_info(),
+                authority: self.taker.to_account_info(),
+            };
+
+            let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+
+            return approve(cpi_ctx, amount_b);
+        }
+
+        let counter_vault = self
+            .counter_vault
+            .as_ref()
+            .ok_or(ErrorCode::MissingTokenAccounts)?;
+
+        let cpi_program = self.token_program.to_account_info();
+
+        let cpi_accounts = TransferChecked {
+            from: taker_ata_b.to_account_info(),
+            to: counter_vault.to_account_info(),
+            authority: self.taker.to_account_info(),
+            mint: mint_b.to_account_info(),
+        };
+
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts)
+            .with_remaining_accounts(remaining_accounts.to_vec());
+
+        transfer_checked(cpi_ctx, amount_b, mint_b.decimals)
+    }
+
+    pub fn posted_event(&self) -> Result<CounterPosted> {
+        Ok(CounterPosted {
+            counter: self.counter.key(),
+            escrow: self.escrow.key(),
+            taker: self.taker.key(),
+            amount_a: self.counter.amount_a,
+            amount_b: self.counter.amount_b,
+            funded: self.counter.funded,
+            timestamp: Clock::get()?.unix_timestamp,
+        })
+    }
+}
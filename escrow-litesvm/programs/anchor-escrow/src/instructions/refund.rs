@@ -1,75 +1,180 @@
 use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
 use anchor_spl::token_interface::{
-    close_account, transfer_checked, CloseAccount, Mint, TokenAccount, TokenInterface,
-    TransferChecked,
+    CloseAccount, Mint, TokenAccount, TokenInterface, TransferChecked,
 };
 
-use crate::state::Escrow;
+use crate::error::ErrorCode;
+use crate::events::EscrowRefunded;
+use crate::state::{leg_mint, payout_account, release_bond, Escrow, MakerState};
+use crate::transfer::{close_account, transfer_checked};
 
+#[event_cpi]
 #[derive(Accounts)]
 pub struct Refund<'info> {
     #[account(mut)]
     maker: Signer<'info>,
-    mint_a: InterfaceAccount<'info, Mint>,
+    #[account(mut)]
+    mint_a: Option<InterfaceAccount<'info, Mint>>,
     #[account(
         mut,
         associated_token::mint = mint_a,
         associated_token::authority = maker,
+        associated_token::token_program = token_program,
+    )]
+    maker_ata_a: Option<InterfaceAccount<'info, TokenAccount>>,
+    // Refunded instead of `maker_ata_a` when the escrow chose a refund account on `make_for`
+    #[account(
+        mut,
+        constraint = escrow.refund_to == Some(refund_to.key()) @ ErrorCode::PayoutAccountMismatch,
     )]
-    maker_ata_a: InterfaceAccount<'info, TokenAccount>,
+    refund_to: Option<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
         close = maker,
-        has_one = mint_a,
-        has_one = maker,
-        seeds = [b"escrow", maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        constraint = leg_mint(&mint_a) == escrow.mint_a @ ErrorCode::MintMismatch,
+        seeds = [b"escrow", escrow.maker.as_ref(), escrow.seed.to_le_bytes().as_ref()],
         bump = escrow.bump,
     )]
     pub escrow: Account<'info, Escrow>,
+    // The `maker`'s receipt token account, for escrows that minted one
+    receipt: Option<InterfaceAccount<'info, TokenAccount>>,
+    // The taker holding an active reservation, needed to refund before it lapses
+    #[account(
+        mut,
+        constraint = escrow.reservation.map(|reservation| reservation.taker) == Some(reserver.key())
+            @ ErrorCode::ReserverMismatch,
+    )]
+    reserver: Option<SystemAccount<'info>>,
+    #[account(
+        mut,
+        seeds = [b"maker", escrow.maker.as_ref()],
+        bump = maker_state.bump,
+    )]
+    maker_state: Account<'info, MakerState>,
     #[account(
         mut,
         associated_token::mint = mint_a,
         associated_token::authority = escrow,
+        associated_token::token_program = token_program,
     )]
-    vault: InterfaceAccount<'info, TokenAccount>,
+    vault: Option<InterfaceAccount<'info, TokenAccount>>,
     token_program: Interface<'info, TokenInterface>,
     system_program: Program<'info, System>,
 }
 
 impl<'info> Refund<'info> {
-    pub fn refund_and_close_vault(&mut self) -> Result<()> {
-        let signer_seeds: [&[&[u8]]; 1] = [&[
-            b"escrow",
-            self.maker.key.as_ref(),
-            &self.escrow.seed.to_le_bytes()[..],
-            &[self.escrow.bump],
-        ]];
-
-        let cpi_program = self.token_program.to_account_info();
-
-        let cpi_accounts = TransferChecked {
-            from: self.vault.to_account_info(),
-            to: self.maker_ata_a.to_account_info(),
-            mint: self.mint_a.to_account_info(),
-            authority: self.escrow.to_account_info(),
-        };
+    pub fn refund_and_close_vault(
+        &mut self,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        self.escrow.check_refundable(now)?;
+        self.escrow.check_owner(self.maker.key, &self.receipt)?;
+        if let Some(reservation) = self.escrow.active_reservation(now) {
+            self.compensate_reserver(reservation.bond)?;
+        }
+        self.maker_state.close_offer();
 
-        let cpi_context = CpiContext::new_with_signer(cpi_program, cpi_accounts, &signer_seeds);
+        return_vault_and_close(
+            &self.escrow,
+            &self.vault,
+            payout_account(self.escrow.refund_to, &self.refund_to, &self.maker_ata_a)?,
+            &self.mint_a,
+            &self.maker.to_account_info(),
+            &self.token_program,
+            remaining_accounts,
+        )
+    }
 
-        transfer_checked(cpi_context, self.vault.amount, self.mint_a.decimals)?;
+    // Backing out under an active reservation hands the reserver their bond back,
+    // plus a penalty of the same size paid by the maker
+    fn compensate_reserver(&mut self, penalty: u64) -> Result<()> {
+        let reserver = self
+            .reserver
+            .as_ref()
+            .ok_or(ErrorCode::ReserverMismatch)?
+            .to_account_info();
 
-        let cpi_program = self.token_program.to_account_info();
+        let cpi_program = self.system_program.to_account_info();
 
-        let cpi_accounts = CloseAccount {
-            account: self.vault.to_account_info(),
-            destination: self.maker.to_account_info(),
-            authority: self.escrow.to_account_info(),
+        let cpi_accounts = Transfer {
+            from: self.maker.to_account_info(),
+            to: reserver.clone(),
         };
 
-        let cpi_context = CpiContext::new_with_signer(cpi_program, cpi_accounts, &signer_seeds);
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+
+        transfer(cpi_ctx, penalty)?;
+
+        release_bond(&mut self.escrow, &reserver)
+    }
+}
 
-        close_account(cpi_context)?;
+pub fn refunded_event(escrow: &Account<Escrow>, expired: bool) -> Result<EscrowRefunded> {
+    Ok(EscrowRefunded {
+        escrow: escrow.key(),
+        maker: escrow.maker,
+        seed: escrow.seed,
+        mint_a: escrow.mint_a,
+        amount: escrow.deposit,
+        expired,
+        timestamp: Clock::get()?.unix_timestamp,
+    })
+}
 
-        Ok(())
+// Sends everything in the vault back to the maker and closes the vault, signed by the escrow PDA.
+// Shared by `refund` and the permissionless `expire` crank. Native SOL offers have no vault,
+// their lamports go back to the maker when the escrow account itself is closed.
+pub fn return_vault_and_close<'info>(
+    escrow: &Account<'info, Escrow>,
+    vault: &Option<InterfaceAccount<'info, TokenAccount>>,
+    maker_ata_a: &Option<InterfaceAccount<'info, TokenAccount>>,
+    mint_a: &Option<InterfaceAccount<'info, Mint>>,
+    maker: &AccountInfo<'info>,
+    token_program: &Interface<'info, TokenInterface>,
+    remaining_accounts: &[AccountInfo<'info>],
+) -> Result<()> {
+    if escrow.is_native_a() {
+        return Ok(());
     }
+
+    let (Some(vault), Some(maker_ata_a), Some(mint_a)) = (vault, maker_ata_a, mint_a) else {
+        return err!(ErrorCode::MissingTokenAccounts);
+    };
+
+    let signer_seeds: [&[&[u8]]; 1] = [&[
+        b"escrow",
+        escrow.maker.as_ref(),
+        &escrow.seed.to_le_bytes()[..],
+        &[escrow.bump],
+    ]];
+
+    let cpi_program = token_program.to_account_info();
+
+    let cpi_accounts = TransferChecked {
+        from: vault.to_account_info(),
+        to: maker_ata_a.to_account_info(),
+        mint: mint_a.to_account_info(),
+        authority: escrow.to_account_info(),
+    };
+
+    let cpi_context = CpiContext::new_with_signer(cpi_program, cpi_accounts, &signer_seeds)
+        .with_remaining_accounts(remaining_accounts.to_vec());
+
+    transfer_checked(cpi_context, vault.amount, mint_a.decimals)?;
+
+    let cpi_program = token_program.to_account_info();
+
+    let cpi_accounts = CloseAccount {
+        account: vault.to_account_info(),
+        destination: maker.clone(),
+        authority: escrow.to_account_info(),
+    };
+
+    let cpi_context = CpiContext::new_with_signer(cpi_program, cpi_accounts, &signer_seeds);
+
+    close_account(cpi_context, mint_a.to_account_info())?;
+
+    Ok(())
 }
@@ -0,0 +1,154 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::get_associated_token_address_with_program_id,
+    token_2022::spl_token_2022::{
+        self,
+        extension::{BaseStateWithExtensions, ExtensionType, StateWithExtensions},
+    },
+    token_interface::{
+        close_account, CloseAccount, Mint, TokenAccount, TokenInterface, TransferChecked,
+    },
+};
+
+use crate::error::ErrorCode;
+use crate::events::BasketRefunded;
+use crate::state::BasketEscrow;
+use crate::transfer::transfer_checked;
+
+// Offered legs come in as remaining accounts, one `[mint, vault, maker_ata]` triple per leg,
+// in the order they are stored on the basket
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RefundBasket<'info> {
+    #[account(mut)]
+    maker: Signer<'info>,
+    #[account(
+        mut,
+        close = maker,
+        has_one = maker,
+        seeds = [b"basket", maker.key().as_ref(), basket.seed.to_le_bytes().as_ref()],
+        bump = basket.bump,
+    )]
+    pub basket: Account<'info, BasketEscrow>,
+    token_program: Interface<'info, TokenInterface>,
+    system_program: Program<'info, System>,
+}
+
+impl<'info> RefundBasket<'info> {
+    pub fn refund_and_close_vaults(
+        &mut self,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> Result<()> {
+        require!(
+            remaining_accounts.len() == self.basket.offered.len() * 3,
+            ErrorCode::BasketAccountsMismatch
+        );
+
+        for (leg, accounts) in self.basket.offered.iter().zip(remaining_accounts.chunks(3)) {
+            let [mint, vault, maker_ata] = accounts else {
+                return err!(ErrorCode::BasketAccountsMismatch);
+            };
+            require_keys_eq!(mint.key(), leg.mint, ErrorCode::BasketAccountsMismatch);
+
+            release_basket_vault(
+                &self.basket,
+                mint,
+                vault,
+                maker_ata,
+                &self.maker.to_account_info(),
+                &self.token_program,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    pub fn refunded_event(&self) -> Result<BasketRefunded> {
+        Ok(BasketRefunded {
+            basket: self.basket.key(),
+            maker: self.maker.key(),
+            seed: self.basket.seed,
+            offered: self.basket.offered.clone(),
+            timestamp: Clock::get()?.unix_timestamp,
+        })
+    }
+}
+
+// Empties one basket vault into `to` and closes it, rent going back to the maker.
+// Shared by `refund_basket` and `take_basket`.
+pub fn release_basket_vault<'info>(
+    basket: &Account<'info, BasketEscrow>,
+    mint: &AccountInfo<'info>,
+    vault: &AccountInfo<'info>,
+    to: &AccountInfo<'info>,
+    maker: &AccountInfo<'info>,
+    token_program: &Interface<'info, TokenInterface>,
+) -> Result<()> {
+    require_keys_eq!(
+        vault.key(),
+        get_associated_token_address_with_program_id(&basket.key(), mint.key, token_program.key),
+        ErrorCode::BasketAccountsMismatch
+    );
+    let mint_account = read_mint(mint)?;
+    let vault_account = read_token_account(vault)?;
+
+    let signer_seeds: [&[&[u8]]; 1] = [&[
+        b"basket",
+        basket.maker.as_ref(),
+        &basket.seed.to_le_bytes()[..],
+        &[basket.bump],
+    ]];
+
+    let cpi_program = token_program.to_account_info();
+
+    let cpi_accounts = TransferChecked {
+        from: vault.clone(),
+        to: to.clone(),
+        mint: mint.clone(),
+        authority: basket.to_account_info(),
+    };
+
+    let cpi_context = CpiContext::new_with_signer(cpi_program, cpi_accounts, &signer_seeds);
+
+    transfer_checked(cpi_context, vault_account.amount, mint_account.decimals)?;
+
+    let cpi_program = token_program.to_account_info();
+
+    let cpi_accounts = CloseAccount {
+        account: vault.clone(),
+        destination: maker.clone(),
+        authority: basket.to_account_info(),
+    };
+
+    let cpi_context = CpiContext::new_with_signer(cpi_program, cpi_accounts, &signer_seeds);
+
+    close_account(cpi_context)
+}
+
+// Basket legs arrive as raw remaining accounts; the token program CPIs that follow check
+// ownership, so plain deserialization is enough here
+pub fn read_mint(mint: &AccountInfo) -> Result<Mint> {
+    Mint::try_deserialize(&mut &mint.try_borrow_data()?[..])
+}
+
+// Basket transfers forward no transfer-hook accounts and pay nominal amounts, so mints
+// with either extension are turned away instead of failing or short-paying later
+pub fn check_basket_mint(mint: &AccountInfo) -> Result<()> {
+    if *mint.owner != spl_token_2022::ID {
+        return Ok(());
+    }
+
+    let mint_data = mint.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint_data)?;
+    let extensions = mint_state.get_extension_types()?;
+    require!(
+        !extensions.contains(&ExtensionType::TransferHook)
+            && !extensions.contains(&ExtensionType::TransferFeeConfig),
+        ErrorCode::UnsupportedBasketMint
+    );
+    Ok(())
+}
+
+pub fn read_token_account(account: &AccountInfo) -> Result<TokenAccount> {
+    TokenAccount::try_deserialize(&mut &account.try_borrow_data()?[..])
+}
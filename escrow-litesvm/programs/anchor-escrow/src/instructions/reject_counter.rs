@@ -0,0 +1,116 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{
+    CloseAccount, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+
+use crate::error::ErrorCode;
+use crate::events::CounterRejected;
+use crate::state::{leg_mint, CounterOffer, Escrow, NATIVE_SOL};
+use crate::transfer::{close_account, transfer_checked};
+
+// Closes a counter-offer and gives the taker back whatever it holds. `authority` is the
+// maker (or the holder of the escrow's receipt) rejecting it, or the taker withdrawing it.
+// `escrow` can be left out once it is gone, in which case only the taker can withdraw.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RejectCounter<'info> {
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub taker: SystemAccount<'info>,
+    #[account(mut)]
+    pub mint_b: Option<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_ata_b: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(address = counter.escrow)]
+    pub escrow: Option<Account<'info, Escrow>>,
+    // The `authority`'s receipt token account, for escrows that minted one
+    pub receipt: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        close = taker,
+        has_one = taker,
+        constraint = leg_mint(&mint_b) == counter.mint_b @ ErrorCode::MintMismatch,
+        seeds = [b"counter", counter.escrow.as_ref(), taker.key().as_ref()],
+        bump = counter.bump,
+    )]
+    pub counter: Account<'info, CounterOffer>,
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = counter,
+        associated_token::token_program = token_program,
+    )]
+    pub counter_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+//Return a funded counter's vault to the taker and close it. Native SOL comes back
+//with the counter's own lamports, and an unfunded counter only leaves a delegation
+//on the taker's account, which the taker can revoke.
+impl<'info> RejectCounter<'info> {
+    pub fn reject(&mut self, remaining_accounts: &[AccountInfo<'info>]) -> Result<()> {
+        if self.authority.key() != self.taker.key() {
+            let escrow = self.escrow.as_ref().ok_or(ErrorCode::NotEscrowOwner)?;
+            escrow.check_owner(self.authority.key, &self.receipt)?;
+        }
+
+        let (Some(mint_b), Some(taker_ata_b), Some(counter_vault)) =
+            (&self.mint_b, &self.taker_ata_b, &self.counter_vault)
+        else {
+            require!(
+                !self.counter.funded || self.counter.mint_b == NATIVE_SOL,
+                ErrorCode::MissingTokenAccounts
+            );
+            return Ok(());
+        };
+
+        let signer_seeds: [&[&[u8]]; 1] = [&[
+            b"counter",
+            self.counter.escrow.as_ref(),
+            self.taker.key.as_ref(),
+            &[self.counter.bump],
+        ]];
+
+        let cpi_program = self.token_program.to_account_info();
+
+        let cpi_accounts = TransferChecked {
+            from: counter_vault.to_account_info(),
+            to: taker_ata_b.to_account_info(),
+            authority: self.counter.to_account_info(),
+            mint: mint_b.to_account_info(),
+        };
+
+        let cpi_context = CpiContext::new_with_signer(cpi_program, cpi_accounts, &signer_seeds)
+            .with_remaining_accounts(remaining_accounts.to_vec());
+
+        transfer_checked(cpi_context, counter_vault.amount, mint_b.decimals)?;
+
+        let cpi_program = self.token_program.to_account_info();
+
+        let cpi_accounts = CloseAccount {
+            account: counter_vault.to_account_info(),
+            destination: self.taker.to_account_info(),
+            authority: self.counter.to_account_info(),
+        };
+
+        let cpi_context = CpiContext::new_with_signer(cpi_program, cpi_accounts, &signer_seeds);
+
+        close_account(cpi_context, mint_b.to_account_info())
+    }
+
+    pub fn rejected_event(&self) -> Result<CounterRejected> {
+        Ok(CounterRejected {
+            counter: self.counter.key(),
+            escrow: self.counter.escrow,
+            taker: self.taker.key(),
+            withdrawn: self.authority.key() == self.taker.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        })
+    }
+}
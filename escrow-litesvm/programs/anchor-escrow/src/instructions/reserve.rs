@@ -0,0 +1,82 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
+
+use crate::error::ErrorCode;
+use crate::events::EscrowReserved;
+use crate::state::{release_bond, Config, Escrow, Reservation};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct Reserve<'info> {
+    #[account(mut)]
+    pub taker: Signer<'info>,
+    // Gets the bond of a lapsed reservation this one replaces
+    #[account(mut, address = escrow.maker)]
+    pub maker: SystemAccount<'info>,
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [b"escrow", escrow.maker.as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, Escrow>,
+    pub system_program: Program<'info, System>,
+}
+
+//Hold the escrow for the taker for `duration` seconds against a bond in lamports, as set by
+//the maker's reservation terms
+//Any fill settles the reservation: the reserver's own gets the bond back, anyone else's
+//after it lapsed forfeits it to the maker, as do an expiry or refund after it lapsed
+impl<'info> Reserve<'info> {
+    pub fn reserve(&mut self, duration: i64) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        self.config.check_not_paused()?;
+        self.escrow.check_takeable(now)?;
+        self.escrow.check_taker(self.taker.key)?;
+        let terms = self.escrow.reservation_terms.ok_or(ErrorCode::NotReservable)?;
+        require!(self.escrow.active_reservation(now).is_none(), ErrorCode::Reserved);
+        let expires_at = now.checked_add(duration).ok_or(ErrorCode::MathOverflow)?;
+        require!(
+            duration > 0 && duration <= terms.max_duration && expires_at <= self.escrow.expiry_time,
+            ErrorCode::InvalidReservation
+        );
+
+        release_bond(&mut self.escrow, &self.maker.to_account_info())?;
+
+        let cpi_program = self.system_program.to_account_info();
+
+        let cpi_accounts = Transfer {
+            from: self.taker.to_account_info(),
+            to: self.escrow.to_account_info(),
+        };
+
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+
+        transfer(cpi_ctx, terms.bond)?;
+
+        self.escrow.reservation = Some(Reservation {
+            taker: self.taker.key(),
+            bond: terms.bond,
+            expires_at,
+        });
+        Ok(())
+    }
+
+    pub fn reserved_event(&self) -> Result<EscrowReserved> {
+        let reservation = self.escrow.reservation.ok_or(ErrorCode::NotReservable)?;
+
+        Ok(EscrowReserved {
+            escrow: self.escrow.key(),
+            maker: self.escrow.maker,
+            taker: reservation.taker,
+            seed: self.escrow.seed,
+            bond: reservation.bond,
+            expires_at: reservation.expires_at,
+            timestamp: Clock::get()?.unix_timestamp,
+        })
+    }
+}
@@ -0,0 +1,55 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+use crate::events::ReservationTermsSet;
+use crate::state::{Escrow, ReservationTerms};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetReservationTerms<'info> {
+    maker: Signer<'info>,
+    #[account(
+        mut,
+        has_one = maker @ ErrorCode::NotEscrowOwner,
+        seeds = [b"escrow", escrow.maker.as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+//Let takers reserve the escrow against a bond, or stop them with `None`
+//Terms cannot change under an active reservation
+impl<'info> SetReservationTerms<'info> {
+    pub fn set_reservation_terms(&mut self, terms: Option<ReservationTerms>) -> Result<()> {
+        self.escrow.check_open()?;
+        require!(
+            self.escrow.allowlist_root.is_none()
+                && self.escrow.vesting.is_none()
+                && self.escrow.arbiter.is_none()
+                && self.escrow.receipt_mint.is_none(),
+            ErrorCode::NotReservable
+        );
+        require!(
+            self.escrow
+                .active_reservation(Clock::get()?.unix_timestamp)
+                .is_none(),
+            ErrorCode::Reserved
+        );
+        if let Some(terms) = terms {
+            terms.check()?;
+        }
+
+        self.escrow.reservation_terms = terms;
+        Ok(())
+    }
+
+    pub fn terms_set_event(&self) -> Result<ReservationTermsSet> {
+        Ok(ReservationTermsSet {
+            escrow: self.escrow.key(),
+            maker: self.escrow.maker,
+            seed: self.escrow.seed,
+            terms: self.escrow.reservation_terms,
+            timestamp: Clock::get()?.unix_timestamp,
+        })
+    }
+}
@@ -0,0 +1,264 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{
+    CloseAccount, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+
+use crate::error::ErrorCode;
+use crate::events::{EscrowApproved, EscrowResolved};
+use crate::state::{leg_mint, Escrow};
+use crate::transfer::{close_account, transfer_checked};
+
+// Pays out a taken arbiter escrow, whose vaults hold both the maker's `mint_a`
+// and the taker's `mint_b`. Used by `approve` and the arbiter's `resolve`.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct Settle<'info> {
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub maker: SystemAccount<'info>,
+    #[account(mut)]
+    pub taker: SystemAccount<'info>,
+    #[account(mut)]
+    pub mint_a: Option<InterfaceAccount<'info, Mint>>,
+    #[account(mut)]
+    pub mint_b: Option<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program,
+    )]
+    pub maker_ata_a: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program,
+    )]
+    pub maker_ata_b: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_ata_a: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_ata_b: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        has_one = maker,
+        constraint = leg_mint(&mint_a) == escrow.mint_a @ ErrorCode::MintMismatch,
+        constraint = leg_mint(&mint_b) == escrow.mint_b @ ErrorCode::MintMismatch,
+        constraint = escrow.beneficiary == Some(taker.key()) @ ErrorCode::NotArbitrated,
+        seeds = [b"escrow", maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, Escrow>,
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = escrow,
+        associated_token::token_program = token_program,
+    )]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = escrow,
+        associated_token::token_program = token_program,
+    )]
+    pub vault_b: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> Settle<'info> {
+    // The swap goes through once both the maker and the taker have approved,
+    // which is what the returned flag says
+    pub fn approve(&mut self, remaining_accounts: &[AccountInfo<'info>]) -> Result<bool> {
+        let party = self.authority.key();
+        self.escrow.check_party(&party)?;
+        require!(!self.escrow.disputed, ErrorCode::Disputed);
+
+        if party == self.escrow.maker {
+            self.escrow.maker_approved = true;
+        } else {
+            self.escrow.taker_approved = true;
+        }
+        if !(self.escrow.maker_approved && self.escrow.taker_approved) {
+            return Ok(false);
+        }
+
+        let (held_a, _) = self.held();
+        self.settle(held_a, 0, remaining_accounts)?;
+        Ok(true)
+    }
+
+    // Gives the taker `taker_share_bps` of both legs and the maker the rest,
+    // so 10000 releases everything to the taker and 0 everything to the maker.
+    // Returns what the taker got of either leg.
+    pub fn resolve(
+        &mut self,
+        taker_share_bps: u16,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> Result<(u64, u64)> {
+        require!(
+            self.escrow.arbiter == Some(self.authority.key()),
+            ErrorCode::NotArbiter
+        );
+        require!(self.escrow.disputed, ErrorCode::NotDisputed);
+        require!(taker_share_bps <= 10_000, ErrorCode::InvalidShare);
+
+        let (held_a, held_b) = self.held();
+        let share = |amount: u64| (amount as u128 * taker_share_bps as u128 / 10_000) as u64;
+
+        let (amount_a, amount_b) = (share(held_a), share(held_b));
+
+        self.settle(amount_a, amount_b, remaining_accounts)?;
+        Ok((amount_a, amount_b))
+    }
+
+    pub fn approved_event(&self, settled: bool) -> Result<EscrowApproved> {
+        Ok(EscrowApproved {
+            escrow: self.escrow.key(),
+            maker: self.escrow.maker,
+            party: self.authority.key(),
+            seed: self.escrow.seed,
+            settled,
+            timestamp: Clock::get()?.unix_timestamp,
+        })
+    }
+
+    pub fn resolved_event(
+        &self,
+        taker_share_bps: u16,
+        taker_amount_a: u64,
+        taker_amount_b: u64,
+    ) -> Result<EscrowResolved> {
+        Ok(EscrowResolved {
+            escrow: self.escrow.key(),
+            maker: self.escrow.maker,
+            taker: self.taker.key(),
+            arbiter: self.authority.key(),
+            seed: self.escrow.seed,
+            taker_share_bps,
+            taker_amount_a,
+            taker_amount_b,
+            timestamp: Clock::get()?.unix_timestamp,
+        })
+    }
+
+    fn held(&self) -> (u64, u64) {
+        (
+            self.vault.as_ref().map_or(self.escrow.deposit, |vault| vault.amount),
+            self.vault_b.as_ref().map_or(self.escrow.held_b, |vault| vault.amount),
+        )
+    }
+
+    // Sends `a_to_taker` and `b_to_taker` to the taker and everything else to the maker,
+    // then closes the vaults and escrow. Native legs are held as escrow lamports, so the
+    // maker's part of those leaves when the escrow is closed.
+    fn settle(
+        &mut self,
+        a_to_taker: u64,
+        b_to_taker: u64,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> Result<()> {
+        if let (Some(mint_a), Some(maker_ata_a), Some(taker_ata_a), Some(vault)) =
+            (&self.mint_a, &self.maker_ata_a, &self.taker_ata_a, &self.vault)
+        {
+            let to_maker = vault.amount - a_to_taker;
+            self.release(mint_a, vault, taker_ata_a, a_to_taker, remaining_accounts)?;
+            self.release(mint_a, vault, maker_ata_a, to_maker, remaining_accounts)?;
+            self.close_vault(vault, mint_a, &self.maker.to_account_info())?;
+        } else {
+            require!(self.escrow.is_native_a(), ErrorCode::MissingTokenAccounts);
+
+            self.escrow.sub_lamports(a_to_taker)?;
+            self.taker.add_lamports(a_to_taker)?;
+        }
+
+        if let (Some(mint_b), Some(maker_ata_b), Some(taker_ata_b), Some(vault_b)) =
+            (&self.mint_b, &self.maker_ata_b, &self.taker_ata_b, &self.vault_b)
+        {
+            let to_maker = vault_b.amount - b_to_taker;
+            self.release(mint_b, vault_b, taker_ata_b, b_to_taker, remaining_accounts)?;
+            self.release(mint_b, vault_b, maker_ata_b, to_maker, remaining_accounts)?;
+            // The taker paid for this vault when taking
+            self.close_vault(vault_b, mint_b, &self.taker.to_account_info())?;
+        } else {
+            require!(self.escrow.is_native_b(), ErrorCode::MissingTokenAccounts);
+
+            self.escrow.sub_lamports(b_to_taker)?;
+            self.taker.add_lamports(b_to_taker)?;
+        }
+
+        self.escrow.close(self.maker.to_account_info())
+    }
+
+    fn release(
+        &self,
+        mint: &InterfaceAccount<'info, Mint>,
+        vault: &InterfaceAccount<'info, TokenAccount>,
+        to: &InterfaceAccount<'info, TokenAccount>,
+        amount: u64,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+
+        let signer_seeds: [&[&[u8]]; 1] = [&[
+            b"escrow",
+            self.maker.key.as_ref(),
+            &self.escrow.seed.to_le_bytes()[..],
+            &[self.escrow.bump],
+        ]];
+
+        let cpi_program = self.token_program.to_account_info();
+
+        let cpi_accounts = TransferChecked {
+            from: vault.to_account_info(),
+            to: to.to_account_info(),
+            authority: self.escrow.to_account_info(),
+            mint: mint.to_account_info(),
+        };
+
+        let cpi_context = CpiContext::new_with_signer(cpi_program, cpi_accounts, &signer_seeds)
+            .with_remaining_accounts(remaining_accounts.to_vec());
+
+        transfer_checked(cpi_context, amount, mint.decimals)
+    }
+
+    fn close_vault(
+        &self,
+        vault: &InterfaceAccount<'info, TokenAccount>,
+        mint: &InterfaceAccount<'info, Mint>,
+        destination: &AccountInfo<'info>,
+    ) -> Result<()> {
+        let signer_seeds: [&[&[u8]]; 1] = [&[
+            b"escrow",
+            self.maker.key.as_ref(),
+            &self.escrow.seed.to_le_bytes()[..],
+            &[self.escrow.bump],
+        ]];
+
+        let cpi_program = self.token_program.to_account_info();
+
+        let cpi_accounts = CloseAccount {
+            account: vault.to_account_info(),
+            destination: destination.clone(),
+            authority: self.escrow.to_account_info(),
+        };
+
+        let cpi_context = CpiContext::new_with_signer(cpi_program, cpi_accounts, &signer_seeds);
+
+        close_account(cpi_context, mint.to_account_info())
+    }
+}
@@ -1,90 +1,320 @@
 use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
 use anchor_spl::{
     associated_token::AssociatedToken,
     token_interface::{
-        close_account, transfer_checked, CloseAccount, Mint, TokenAccount, TokenInterface,
-        TransferChecked,
+        CloseAccount, Mint, TokenAccount, TokenInterface, TransferChecked,
     },
 };
-use crate::state::Escrow;
-use crate::error::ErrorCode ;
+use crate::error::ErrorCode;
+use crate::events::EscrowTaken;
+use crate::fees::gross_up_for_fee;
+use crate::merkle::AllowlistProof;
+use crate::state::{
+    leg_decimals, leg_mint, payout_account, settle_bond, AllowlistFill, Config, Escrow,
+    MakerState, Pricing,
+};
+use crate::transfer::{close_account, transfer_checked};
 //Create context
+//The A-side accounts are left out when the escrow offers native SOL,
+//the B-side accounts when the maker asked for native SOL
+#[event_cpi]
 #[derive(Accounts)]
 pub struct Take<'info> {
     #[account(mut)]
     pub taker: Signer<'info>,
+    // Receives the proceeds: the maker, or the holder of the escrow's receipt
     #[account(mut)]
     pub maker: SystemAccount<'info>,
-    pub mint_a: InterfaceAccount<'info, Mint>,
-    pub mint_b: InterfaceAccount<'info, Mint>,
+    #[account(mut)]
+    pub mint_a: Option<InterfaceAccount<'info, Mint>>,
+    pub mint_b: Option<InterfaceAccount<'info, Mint>>,
     #[account(
         init_if_needed,
         payer = taker,
         associated_token::mint = mint_a,
         associated_token::authority = taker,
+        associated_token::token_program = token_program,
     )]
-    pub taker_ata_a: InterfaceAccount<'info, TokenAccount>,
+    pub taker_ata_a: Option<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
         associated_token::mint = mint_b,
         associated_token::authority = taker,
+        associated_token::token_program = token_program,
     )]
-    pub taker_ata_b: InterfaceAccount<'info, TokenAccount>,
+    pub taker_ata_b: Option<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         init_if_needed,
         payer = taker,
         associated_token::mint = mint_b,
         associated_token::authority = maker,
+        associated_token::token_program = token_program,
     )]
-    pub maker_ata_b: InterfaceAccount<'info, TokenAccount>,
+    pub maker_ata_b: Option<InterfaceAccount<'info, TokenAccount>>,
+    // Paid instead of `maker_ata_b` when the escrow chose a proceeds account on `make_for`
     #[account(
         mut,
-        close = maker,
-        has_one = maker,
-        has_one = mint_a,
-        has_one = mint_b,
-        seeds = [b"escrow", maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        constraint = escrow.proceeds_to == Some(proceeds_to.key())
+            @ ErrorCode::PayoutAccountMismatch,
+    )]
+    pub proceeds_to: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, Config>,
+    #[account(mut, address = config.treasury)]
+    pub treasury: SystemAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = mint_b,
+        associated_token::authority = treasury,
+        associated_token::token_program = token_program,
+    )]
+    pub treasury_ata_b: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = leg_mint(&mint_a) == escrow.mint_a @ ErrorCode::MintMismatch,
+        constraint = leg_mint(&mint_b) == escrow.mint_b @ ErrorCode::MintMismatch,
+        seeds = [b"escrow", escrow.maker.as_ref(), escrow.seed.to_le_bytes().as_ref()],
         bump = escrow.bump,
     )]
     pub escrow: Account<'info, Escrow>,
+    // The `maker`'s receipt token account, for escrows that minted one
+    pub receipt: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [b"maker", escrow.maker.as_ref()],
+        bump = maker_state.bump,
+    )]
+    pub maker_state: Account<'info, MakerState>,
     #[account(
         mut,
         associated_token::mint = mint_a,
         associated_token::authority = escrow,
+        associated_token::token_program = token_program,
     )]
-    pub vault: InterfaceAccount<'info, TokenAccount>,
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    // Holds the taker's payment on arbiter escrows
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = mint_b,
+        associated_token::authority = escrow,
+        associated_token::token_program = token_program,
+    )]
+    pub vault_b: Option<InterfaceAccount<'info, TokenAccount>>,
+    // Shared with `take_partial`, so a full take counts against what the taker already filled
+    #[account(
+        init_if_needed,
+        payer = taker,
+        space = 8 + AllowlistFill::INIT_SPACE,
+        seeds = [b"fill", escrow.key().as_ref(), taker.key().as_ref()],
+        bump,
+    )]
+    pub allowlist_fill: Option<Account<'info, AllowlistFill>>,
+    /// CHECK: Only read for oracle-priced escrows, where its owner and layout are checked
+    pub price_update: Option<UncheckedAccount<'info>>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
 }
 
-//Deposit tokens from taker to maker
-//Transfer tokens from vault to taker
-//Close vault account
+//Deposit the current price in tokens (or lamports) from taker to maker,
+//less the protocol fee sent to treasury
+//Transfer tokens from vault (or lamports from escrow) to taker
+//Close vault and escrow, unless the escrow vests to the taker
 impl<'info> Take<'info> {
-    pub fn deposit(&mut self) -> Result<()> {
-        let creation_time = self.escrow.creation_time ;
-        let time_5_days : i64 = 60 * 60 * 24 * 5 ;
-        let allowed_time = creation_time + time_5_days ;
-        require!(Clock::get()?.unix_timestamp > allowed_time , ErrorCode::Locked) ;
+    pub fn deposit(
+        &mut self,
+        expected_version: u32,
+        max_payment: u64,
+        allowlist: Option<AllowlistProof>,
+        bumps: &TakeBumps,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> Result<u64> {
+        let now = Clock::get()?.unix_timestamp;
+        self.escrow.check_takeable(now)?;
+        self.escrow.check_taker(self.taker.key)?;
+        self.escrow.check_reservation(self.taker.key, now)?;
+        self.escrow.check_owner(self.maker.key, &self.receipt)?;
+        let max_fill = self.escrow.check_allowlist(self.taker.key, &allowlist)?;
+        self.escrow.check_version(expected_version)?;
+        self.config.check_not_paused()?;
+        settle_bond(
+            &mut self.escrow,
+            &self.taker.to_account_info(),
+            &self.maker.to_account_info(),
+        )?;
+
+        let price = match self.escrow.pricing {
+            Pricing::Oracle(oracle) => {
+                let price_update = self
+                    .price_update
+                    .as_ref()
+                    .ok_or(ErrorCode::InvalidPriceUpdate)?;
+                let quote = oracle.quote(
+                    price_update,
+                    self.escrow.deposit,
+                    leg_decimals(&self.mint_a),
+                    leg_decimals(&self.mint_b),
+                    now,
+                )?;
+                quote.max(self.escrow.receive)
+            }
+            _ => self.escrow.current_receive(now)?,
+        };
+        if self.escrow.allowlist_root.is_some() {
+            let allowlist_fill = self
+                .allowlist_fill
+                .as_mut()
+                .ok_or(ErrorCode::MissingAllowlistFill)?;
+            allowlist_fill.bump = bumps.allowlist_fill.unwrap_or_default();
+            allowlist_fill.record(price, max_fill)?;
+        }
+
+        let fee = self.config.fee(price)?;
+        let maker_amount = price - fee;
+
+        let maker_ata_b =
+            payout_account(self.escrow.proceeds_to, &self.proceeds_to, &self.maker_ata_b)?;
+        let (Some(mint_b), Some(taker_ata_b), Some(maker_ata_b)) =
+            (&self.mint_b, &self.taker_ata_b, maker_ata_b)
+        else {
+            require!(self.escrow.is_native_b(), ErrorCode::MissingTokenAccounts);
+            require!(price <= max_payment, ErrorCode::SlippageExceeded);
+
+            // Arbiter escrows keep the maker's share until the deal is settled
+            let to = if self.escrow.arbiter.is_some() {
+                self.escrow.held_b = maker_amount;
+                self.escrow.to_account_info()
+            } else {
+                self.maker.to_account_info()
+            };
+            self.pay_lamports(&to, maker_amount)?;
+            self.pay_lamports(&self.treasury.to_account_info(), fee)?;
+            return Ok(price);
+        };
+
+        let amount = if self.escrow.receive_net_of_fee {
+            gross_up_for_fee(mint_b, maker_amount)?
+        } else {
+            maker_amount
+        };
+        // The cap bounds what leaves the taker's account, grossed-up transfer fee included
+        let debit = amount.checked_add(fee).ok_or(ErrorCode::MathOverflow)?;
+        require!(debit <= max_payment, ErrorCode::SlippageExceeded);
+
+        let to = if self.escrow.arbiter.is_some() {
+            self.escrow.held_b = amount;
+            self.vault_b.as_ref().ok_or(ErrorCode::MissingTokenAccounts)?
+        } else {
+            maker_ata_b
+        };
+        self.pay_tokens(mint_b, taker_ata_b, to, amount, remaining_accounts)?;
+
+        if fee > 0 {
+            let treasury_ata_b = self
+                .treasury_ata_b
+                .as_ref()
+                .ok_or(ErrorCode::MissingTokenAccounts)?;
+
+            self.pay_tokens(mint_b, taker_ata_b, treasury_ata_b, fee, remaining_accounts)?;
+        }
+
+        Ok(price)
+    }
+
+    pub fn taken_event(&self, amount_a: u64, amount_b: u64) -> Result<EscrowTaken> {
+        Ok(EscrowTaken {
+            escrow: self.escrow.key(),
+            maker: self.escrow.maker,
+            taker: self.taker.key(),
+            seed: self.escrow.seed,
+            mint_a: self.escrow.mint_a,
+            mint_b: self.escrow.mint_b,
+            amount_a,
+            amount_b,
+            fee: self.config.fee(amount_b)?,
+            remaining_deposit: 0,
+            remaining_receive: 0,
+            timestamp: Clock::get()?.unix_timestamp,
+        })
+    }
+
+    fn pay_lamports(&self, to: &AccountInfo<'info>, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+
+        let cpi_program = self.system_program.to_account_info();
+
+        let cpi_accounts = Transfer {
+            from: self.taker.to_account_info(),
+            to: to.clone(),
+        };
+
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+
+        transfer(cpi_ctx, amount)
+    }
+
+    fn pay_tokens(
+        &self,
+        mint_b: &InterfaceAccount<'info, Mint>,
+        taker_ata_b: &InterfaceAccount<'info, TokenAccount>,
+        to: &InterfaceAccount<'info, TokenAccount>,
+        amount: u64,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> Result<()> {
         let cpi_program = self.token_program.to_account_info();
 
         let cpi_accounts = TransferChecked {
-            from: self.taker_ata_b.to_account_info(),
-            to: self.maker_ata_b.to_account_info(),
+            from: taker_ata_b.to_account_info(),
+            to: to.to_account_info(),
             authority: self.taker.to_account_info(),
-            mint: self.mint_b.to_account_info(),
+            mint: mint_b.to_account_info(),
         };
 
-        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts)
+            .with_remaining_accounts(remaining_accounts.to_vec());
 
-        transfer_checked(cpi_ctx, self.escrow.receive, self.mint_b.decimals)
+        transfer_checked(cpi_ctx, amount, mint_b.decimals)
     }
 
-    pub fn withdraw_and_close_vault(&mut self) -> Result<()> {
+    pub fn withdraw_and_close_vault(
+        &mut self,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> Result<()> {
+        self.maker_state.close_offer();
+
+        // Vesting escrows release the vault through `claim`,
+        // arbiter escrows through `approve` or `resolve`
+        if self.escrow.vesting.is_some() || self.escrow.arbiter.is_some() {
+            self.escrow.beneficiary = Some(self.taker.key());
+            self.escrow.vesting_start = Clock::get()?.unix_timestamp;
+            return Ok(());
+        }
+
+        let (Some(mint_a), Some(taker_ata_a), Some(vault)) =
+            (&self.mint_a, &self.taker_ata_a, &self.vault)
+        else {
+            require!(self.escrow.is_native_a(), ErrorCode::MissingTokenAccounts);
+
+            // The escrow PDA is program owned, so its lamports can be moved directly.
+            // Its rent goes back to the maker when it is closed.
+            let amount = self.escrow.deposit;
+            self.escrow.sub_lamports(amount)?;
+            self.taker.add_lamports(amount)?;
+
+            return self.escrow.close(self.maker.to_account_info());
+        };
+
         let signer_seeds: [&[&[u8]]; 1] = [&[
             b"escrow",
-            self.maker.key.as_ref(),
+            self.escrow.maker.as_ref(),
             &self.escrow.seed.to_le_bytes()[..],
             &[self.escrow.bump],
         ]];
@@ -92,26 +322,29 @@ impl<'info> Take<'info> {
         let cpi_program = self.token_program.to_account_info();
 
         let cpi_accounts = TransferChecked {
-            from: self.vault.to_account_info(),
-            to: self.taker_ata_a.to_account_info(),
+            from: vault.to_account_info(),
+            to: taker_ata_a.to_account_info(),
             authority: self.escrow.to_account_info(),
-            mint: self.mint_a.to_account_info(),
+            mint: mint_a.to_account_info(),
         };
 
-        let cpi_context = CpiContext::new_with_signer(cpi_program, cpi_accounts, &signer_seeds);
+        let cpi_context = CpiContext::new_with_signer(cpi_program, cpi_accounts, &signer_seeds)
+            .with_remaining_accounts(remaining_accounts.to_vec());
 
-        transfer_checked(cpi_context, self.vault.amount, self.mint_a.decimals)?;
+        transfer_checked(cpi_context, vault.amount, mint_a.decimals)?;
 
         let cpi_program = self.token_program.to_account_info();
 
         let cpi_accounts = CloseAccount {
-            account: self.vault.to_account_info(),
+            account: vault.to_account_info(),
             destination: self.maker.to_account_info(),
             authority: self.escrow.to_account_info(),
         };
 
         let cpi_context = CpiContext::new_with_signer(cpi_program, cpi_accounts, &signer_seeds);
 
-        close_account(cpi_context)
+        close_account(cpi_context, mint_a.to_account_info())?;
+
+        self.escrow.close(self.maker.to_account_info())
     }
 }
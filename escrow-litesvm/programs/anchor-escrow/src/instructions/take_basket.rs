@@ -0,0 +1,157 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::{create_idempotent, AssociatedToken, Create},
+    token_interface::{TokenInterface, TransferChecked},
+};
+
+use crate::error::ErrorCode;
+use crate::events::BasketTaken;
+use crate::instructions::refund_basket::{check_basket_mint, read_mint, release_basket_vault};
+use crate::state::{BasketEscrow, Config};
+use crate::transfer::transfer_checked;
+
+// Remaining accounts: a `[mint, vault, taker_ata]` triple per offered leg, followed by
+// `[mint, taker_ata, maker_ata, treasury_ata]` per requested leg, each in basket order.
+// Missing ATAs on the receiving side are created, paid for by the taker.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct TakeBasket<'info> {
+    #[account(mut)]
+    pub taker: Signer<'info>,
+    #[account(mut)]
+    pub maker: SystemAccount<'info>,
+    #[account(
+        mut,
+        close = maker,
+        has_one = maker,
+        seeds = [b"basket", maker.key().as_ref(), basket.seed.to_le_bytes().as_ref()],
+        bump = basket.bump,
+    )]
+    pub basket: Account<'info, BasketEscrow>,
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, Config>,
+    #[account(address = config.treasury)]
+    pub treasury: SystemAccount<'info>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+//Pay every requested leg from taker to maker, less the protocol fee sent to treasury
+//Release every vault to taker and close it
+impl<'info> TakeBasket<'info> {
+    pub fn deposit(&mut self, remaining_accounts: &[AccountInfo<'info>]) -> Result<()> {
+        self.config.check_not_paused()?;
+
+        let offered_accounts = self.basket.offered.len() * 3;
+        require!(
+            remaining_accounts.len() == offered_accounts + self.basket.requested.len() * 4,
+            ErrorCode::BasketAccountsMismatch
+        );
+
+        let requested_accounts = remaining_accounts[offered_accounts..].chunks(4);
+        for (leg, accounts) in self.basket.requested.iter().zip(requested_accounts) {
+            let [mint, taker_ata, maker_ata, treasury_ata] = accounts else {
+                return err!(ErrorCode::BasketAccountsMismatch);
+            };
+            require_keys_eq!(mint.key(), leg.mint, ErrorCode::BasketAccountsMismatch);
+            let mint_account = read_mint(mint)?;
+            check_basket_mint(mint)?;
+
+            let fee = self.config.fee(leg.amount)?;
+
+            self.create_ata_if_needed(maker_ata, &self.maker.to_account_info(), mint)?;
+            self.pay(mint, taker_ata, maker_ata, leg.amount - fee, mint_account.decimals)?;
+
+            if fee > 0 {
+                self.create_ata_if_needed(treasury_ata, &self.treasury.to_account_info(), mint)?;
+                self.pay(mint, taker_ata, treasury_ata, fee, mint_account.decimals)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn withdraw_and_close_vaults(
+        &mut self,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> Result<()> {
+        let offered_accounts = remaining_accounts[..self.basket.offered.len() * 3].chunks(3);
+        for (leg, accounts) in self.basket.offered.iter().zip(offered_accounts) {
+            let [mint, vault, taker_ata] = accounts else {
+                return err!(ErrorCode::BasketAccountsMismatch);
+            };
+            require_keys_eq!(mint.key(), leg.mint, ErrorCode::BasketAccountsMismatch);
+
+            self.create_ata_if_needed(taker_ata, &self.taker.to_account_info(), mint)?;
+
+            release_basket_vault(
+                &self.basket,
+                mint,
+                vault,
+                taker_ata,
+                &self.maker.to_account_info(),
+                &self.token_program,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    pub fn taken_event(&self) -> Result<BasketTaken> {
+        Ok(BasketTaken {
+            basket: self.basket.key(),
+            maker: self.maker.key(),
+            taker: self.taker.key(),
+            seed: self.basket.seed,
+            offered: self.basket.offered.clone(),
+            requested: self.basket.requested.clone(),
+            timestamp: Clock::get()?.unix_timestamp,
+        })
+    }
+
+    fn pay(
+        &self,
+        mint: &AccountInfo<'info>,
+        taker_ata: &AccountInfo<'info>,
+        to: &AccountInfo<'info>,
+        amount: u64,
+        decimals: u8,
+    ) -> Result<()> {
+        let cpi_program = self.token_program.to_account_info();
+
+        let cpi_accounts = TransferChecked {
+            from: taker_ata.clone(),
+            to: to.clone(),
+            authority: self.taker.to_account_info(),
+            mint: mint.clone(),
+        };
+
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+
+        transfer_checked(cpi_ctx, amount, decimals)
+    }
+
+    fn create_ata_if_needed(
+        &self,
+        ata: &AccountInfo<'info>,
+        authority: &AccountInfo<'info>,
+        mint: &AccountInfo<'info>,
+    ) -> Result<()> {
+        let cpi_program = self.associated_token_program.to_account_info();
+
+        let cpi_accounts = Create {
+            payer: self.taker.to_account_info(),
+            associated_token: ata.clone(),
+            authority: authority.clone(),
+            mint: mint.clone(),
+            system_program: self.system_program.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+        };
+
+        create_idempotent(CpiContext::new(cpi_program, cpi_accounts))
+    }
+}
@@ -0,0 +1,292 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::{
+        create_idempotent, get_associated_token_address_with_program_id, AssociatedToken, Create,
+    },
+    token_interface::{
+        CloseAccount, Mint, TokenAccount, TokenInterface, TransferChecked,
+    },
+};
+
+use crate::error::ErrorCode;
+use crate::events::EscrowTaken;
+use crate::fees::gross_up_for_fee;
+use crate::instructions::refund_basket::read_token_account;
+use crate::state::{Config, Escrow, MakerState, Pricing};
+use crate::transfer::{close_account, transfer_checked};
+
+// Every escrow costs a handful of CPIs and an event, so a full batch has to fit the default
+// 200k compute units of a lone instruction. Each escrow also adds 5 accounts, and 4 keeps the
+// transaction under the 1232-byte packet limit without a lookup table.
+pub const MAX_TAKE_MANY: usize = 4;
+
+// Fills several escrows for the same token pair at once.
+// Remaining accounts: `[escrow, vault, maker, maker_ata_b, maker_state]` per escrow.
+// Only fixed-price and auction escrows without an allowlist, vesting, arbiter, receipt,
+// proceeds account or reservation terms can be batched, and transfer-hook mints are not
+// supported since the remaining accounts are taken.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct TakeMany<'info> {
+    #[account(mut)]
+    pub taker: Signer<'info>,
+    #[account(mut)]
+    pub mint_a: InterfaceAccount<'info, Mint>,
+    pub mint_b: InterfaceAccount<'info, Mint>,
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = mint_a,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_ata_a: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_ata_b: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, Config>,
+    #[account(address = config.treasury)]
+    pub treasury: SystemAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = mint_b,
+        associated_token::authority = treasury,
+        associated_token::token_program = token_program,
+    )]
+    pub treasury_ata_b: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+//For each escrow, pay its current price to the maker, less the protocol fee sent to treasury,
+//then release its vault to the taker and close the vault and escrow
+//The sum of all debits from the taker, fees included, is capped by `max_total_payment`
+impl<'info> TakeMany<'info> {
+    pub fn take_many(
+        &mut self,
+        max_total_payment: u64,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> Result<Vec<EscrowTaken>> {
+        self.config.check_not_paused()?;
+        let batch = remaining_accounts.chunks_exact(5);
+        require!(
+            batch.remainder().is_empty() && (1..=MAX_TAKE_MANY).contains(&batch.len()),
+            ErrorCode::InvalidBatchSize
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        let mut total_payment: u64 = 0;
+        let mut events = Vec::with_capacity(batch.len());
+
+        for accounts in batch {
+            let [escrow_info, vault, maker, maker_ata_b, maker_state] = accounts else {
+                return err!(ErrorCode::BatchAccountsMismatch);
+            };
+            let escrow = load_escrow(escrow_info, maker)?;
+
+            require_keys_eq!(escrow.mint_a, self.mint_a.key(), ErrorCode::MintMismatch);
+            require_keys_eq!(escrow.mint_b, self.mint_b.key(), ErrorCode::MintMismatch);
+            escrow.check_takeable(now)?;
+            escrow.check_taker(self.taker.key)?;
+            require!(
+                !matches!(escrow.pricing, Pricing::Oracle(_))
+                    && escrow.allowlist_root.is_none()
+                    && escrow.vesting.is_none()
+                    && escrow.arbiter.is_none()
+                    && escrow.receipt_mint.is_none()
+                    && escrow.proceeds_to.is_none()
+                    && escrow.reservation_terms.is_none(),
+                ErrorCode::BatchUnsupported
+            );
+
+            let price = escrow.current_receive(now)?;
+            let fee = self.config.fee(price)?;
+            let amount = maker_payment(&escrow, &self.mint_b, price - fee)?;
+            // The cap bounds what leaves the taker's account, grossed-up transfer fees included
+            total_payment = total_payment
+                .checked_add(amount)
+                .and_then(|total| total.checked_add(fee))
+                .ok_or(ErrorCode::MathOverflow)?;
+            require!(total_payment <= max_total_payment, ErrorCode::SlippageExceeded);
+
+            self.pay_maker(maker, maker_ata_b, amount, fee)?;
+            let amount_a = self.release_vault(&escrow, escrow_info, vault, maker)?;
+            close_maker_offer(maker_state, maker)?;
+            close_escrow(escrow_info, maker)?;
+
+            events.push(EscrowTaken {
+                escrow: escrow_info.key(),
+                maker: maker.key(),
+                taker: self.taker.key(),
+                seed: escrow.seed,
+                mint_a: escrow.mint_a,
+                mint_b: escrow.mint_b,
+                amount_a,
+                amount_b: price,
+                fee,
+                remaining_deposit: 0,
+                remaining_receive: 0,
+                timestamp: now,
+            });
+        }
+
+        Ok(events)
+    }
+
+    fn pay_maker(
+        &self,
+        maker: &AccountInfo<'info>,
+        maker_ata_b: &AccountInfo<'info>,
+        amount: u64,
+        fee: u64,
+    ) -> Result<()> {
+        self.create_ata_if_needed(maker_ata_b, maker)?;
+        self.pay(maker_ata_b, amount)?;
+
+        if fee > 0 {
+            let treasury_ata_b = self
+                .treasury_ata_b
+                .as_ref()
+                .ok_or(ErrorCode::MissingTokenAccounts)?;
+
+            self.pay(&treasury_ata_b.to_account_info(), fee)?;
+        }
+
+        Ok(())
+    }
+
+    fn pay(&self, to: &AccountInfo<'info>, amount: u64) -> Result<()> {
+        let cpi_program = self.token_program.to_account_info();
+
+        let cpi_accounts = TransferChecked {
+            from: self.taker_ata_b.to_account_info(),
+            to: to.clone(),
+            authority: self.taker.to_account_info(),
+            mint: self.mint_b.to_account_info(),
+        };
+
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+
+        transfer_checked(cpi_ctx, amount, self.mint_b.decimals)
+    }
+
+    fn release_vault(
+        &self,
+        escrow: &Escrow,
+        escrow_info: &AccountInfo<'info>,
+        vault: &AccountInfo<'info>,
+        maker: &AccountInfo<'info>,
+    ) -> Result<u64> {
+        require_keys_eq!(
+            vault.key(),
+            get_associated_token_address_with_program_id(
+                escrow_info.key,
+                &self.mint_a.key(),
+                self.token_program.key
+            ),
+            ErrorCode::BatchAccountsMismatch
+        );
+        let amount = read_token_account(vault)?.amount;
+
+        let signer_seeds: [&[&[u8]]; 1] = [&[
+            b"escrow",
+            maker.key.as_ref(),
+            &escrow.seed.to_le_bytes()[..],
+            &[escrow.bump],
+        ]];
+
+        let cpi_program = self.token_program.to_account_info();
+
+        let cpi_accounts = TransferChecked {
+            from: vault.clone(),
+            to: self.taker_ata_a.to_account_info(),
+            authority: escrow_info.clone(),
+            mint: self.mint_a.to_account_info(),
+        };
+
+        let cpi_context = CpiContext::new_with_signer(cpi_program, cpi_accounts, &signer_seeds);
+
+        transfer_checked(cpi_context, amount, self.mint_a.decimals)?;
+
+        let cpi_program = self.token_program.to_account_info();
+
+        let cpi_accounts = CloseAccount {
+            account: vault.clone(),
+            destination: maker.clone(),
+            authority: escrow_info.clone(),
+        };
+
+        let cpi_context = CpiContext::new_with_signer(cpi_program, cpi_accounts, &signer_seeds);
+
+        close_account(cpi_context, self.mint_a.to_account_info())?;
+
+        Ok(amount)
+    }
+
+    fn create_ata_if_needed(
+        &self,
+        ata: &AccountInfo<'info>,
+        authority: &AccountInfo<'info>,
+    ) -> Result<()> {
+        let cpi_program = self.associated_token_program.to_account_info();
+
+        let cpi_accounts = Create {
+            payer: self.taker.to_account_info(),
+            associated_token: ata.clone(),
+            authority: authority.clone(),
+            mint: self.mint_b.to_account_info(),
+            system_program: self.system_program.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+        };
+
+        create_idempotent(CpiContext::new(cpi_program, cpi_accounts))
+    }
+}
+
+// What the taker sends the maker, grossed up when the maker asked to receive net of transfer fees
+fn maker_payment(
+    escrow: &Escrow,
+    mint_b: &InterfaceAccount<Mint>,
+    maker_amount: u64,
+) -> Result<u64> {
+    if escrow.receive_net_of_fee {
+        gross_up_for_fee(mint_b, maker_amount)
+    } else {
+        Ok(maker_amount)
+    }
+}
+
+// Remaining accounts are not checked by Anchor, so ownership, discriminator and maker are here
+fn load_escrow(escrow: &AccountInfo, maker: &AccountInfo) -> Result<Escrow> {
+    require_keys_eq!(*escrow.owner, crate::ID, ErrorCode::BatchAccountsMismatch);
+    let escrow = Escrow::try_deserialize(&mut &escrow.try_borrow_data()?[..])?;
+    require_keys_eq!(escrow.maker, maker.key(), ErrorCode::BatchAccountsMismatch);
+    Ok(escrow)
+}
+
+fn close_maker_offer(maker_state: &AccountInfo, maker: &AccountInfo) -> Result<()> {
+    require_keys_eq!(*maker_state.owner, crate::ID, ErrorCode::BatchAccountsMismatch);
+    let mut state = MakerState::try_deserialize(&mut &maker_state.try_borrow_data()?[..])?;
+    require_keys_eq!(state.maker, maker.key(), ErrorCode::BatchAccountsMismatch);
+
+    state.close_offer();
+    state.try_serialize(&mut &mut maker_state.try_borrow_mut_data()?[..])
+}
+
+// Same as Anchor's `close` constraint, which only applies to declared accounts
+fn close_escrow(escrow: &AccountInfo, maker: &AccountInfo) -> Result<()> {
+    maker.add_lamports(escrow.lamports())?;
+    escrow.sub_lamports(escrow.lamports())?;
+    escrow.assign(&System::id());
+    escrow.realloc(0, false).map_err(Into::into)
+}
@@ -0,0 +1,201 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::sysvar;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+
+use crate::error::ErrorCode;
+use crate::events::OfferTaken;
+use crate::signed_offer::{verify_signature, SignedOffer};
+use crate::state::{Config, OfferNonces};
+use crate::transfer::transfer_checked;
+
+// Fills an offer the maker signed off-chain. Nothing is deposited up front: the maker approves
+// their `delegate` PDA on `maker_ata_a` and the offer's `amount_a` is pulled from there.
+// The transaction must carry an Ed25519 precompile instruction verifying the maker's signature
+// over `SignedOffer::message`, placed right before this one.
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(offer: SignedOffer)]
+pub struct TakeOffer<'info> {
+    #[account(mut)]
+    pub taker: Signer<'info>,
+    pub maker: SystemAccount<'info>,
+    /// CHECK: Holds no data, only signs as the delegate of `maker_ata_a`
+    #[account(seeds = [b"delegate", maker.key().as_ref()], bump)]
+    pub delegate: UncheckedAccount<'info>,
+    #[account(address = offer.mint_a @ ErrorCode::MintMismatch)]
+    pub mint_a: InterfaceAccount<'info, Mint>,
+    #[account(address = offer.mint_b @ ErrorCode::MintMismatch)]
+    pub mint_b: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program,
+    )]
+    pub maker_ata_a: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = mint_b,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program,
+    )]
+    pub maker_ata_b: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = mint_a,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_ata_a: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_ata_b: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, Config>,
+    #[account(address = config.treasury)]
+    pub treasury: SystemAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = mint_b,
+        associated_token::authority = treasury,
+        associated_token::token_program = token_program,
+    )]
+    pub treasury_ata_b: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = taker,
+        space = 8 + OfferNonces::INIT_SPACE,
+        seeds = [
+            b"nonces",
+            maker.key().as_ref(),
+            OfferNonces::page(offer.nonce).to_le_bytes().as_ref(),
+        ],
+        bump,
+    )]
+    pub offer_nonces: Account<'info, OfferNonces>,
+    /// CHECK: Address is checked, read to find the Ed25519 precompile instruction
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+//Check the maker signed the offer and burn its nonce
+//Pay amount_b from taker to maker, less the protocol fee sent to treasury
+//Pull amount_a from the maker to the taker through the delegate approval
+impl<'info> TakeOffer<'info> {
+    pub fn verify_and_consume(
+        &mut self,
+        offer: &SignedOffer,
+        bumps: &TakeOfferBumps,
+    ) -> Result<()> {
+        self.config.check_not_paused()?;
+        require!(
+            Clock::get()?.unix_timestamp < offer.expiry_time,
+            ErrorCode::Expired
+        );
+        require!(
+            offer.amount_a > 0 && offer.amount_b > 0,
+            ErrorCode::InvalidAmount
+        );
+        verify_signature(&self.instructions_sysvar, self.maker.key, &offer.message()?)?;
+
+        self.offer_nonces
+            .consume(self.maker.key(), offer.nonce, bumps.offer_nonces)
+    }
+
+    pub fn pay_maker(
+        &self,
+        offer: &SignedOffer,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> Result<u64> {
+        let fee = self.config.fee(offer.amount_b)?;
+
+        self.pay(&self.maker_ata_b, offer.amount_b - fee, remaining_accounts)?;
+
+        if fee > 0 {
+            let treasury_ata_b = self
+                .treasury_ata_b
+                .as_ref()
+                .ok_or(ErrorCode::MissingTokenAccounts)?;
+
+            self.pay(treasury_ata_b, fee, remaining_accounts)?;
+        }
+
+        Ok(fee)
+    }
+
+    fn pay(
+        &self,
+        to: &InterfaceAccount<'info, TokenAccount>,
+        amount: u64,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> Result<()> {
+        let cpi_program = self.token_program.to_account_info();
+
+        let cpi_accounts = TransferChecked {
+            from: self.taker_ata_b.to_account_info(),
+            to: to.to_account_info(),
+            authority: self.taker.to_account_info(),
+            mint: self.mint_b.to_account_info(),
+        };
+
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts)
+            .with_remaining_accounts(remaining_accounts.to_vec());
+
+        transfer_checked(cpi_ctx, amount, self.mint_b.decimals)
+    }
+
+    // Fails in the token program if the maker's approval does not cover `amount_a`
+    pub fn pull_from_maker(
+        &self,
+        offer: &SignedOffer,
+        bumps: &TakeOfferBumps,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> Result<()> {
+        let signer_seeds: [&[&[u8]]; 1] =
+            [&[b"delegate", self.maker.key.as_ref(), &[bumps.delegate]]];
+
+        let cpi_program = self.token_program.to_account_info();
+
+        let cpi_accounts = TransferChecked {
+            from: self.maker_ata_a.to_account_info(),
+            to: self.taker_ata_a.to_account_info(),
+            authority: self.delegate.to_account_info(),
+            mint: self.mint_a.to_account_info(),
+        };
+
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, &signer_seeds)
+            .with_remaining_accounts(remaining_accounts.to_vec());
+
+        transfer_checked(cpi_ctx, offer.amount_a, self.mint_a.decimals)
+    }
+
+    pub fn taken_event(&self, offer: &SignedOffer, fee: u64) -> Result<OfferTaken> {
+        Ok(OfferTaken {
+            maker: self.maker.key(),
+            taker: self.taker.key(),
+            nonce: offer.nonce,
+            mint_a: offer.mint_a,
+            mint_b: offer.mint_b,
+            amount_a: offer.amount_a,
+            amount_b: offer.amount_b,
+            fee,
+            timestamp: Clock::get()?.unix_timestamp,
+        })
+    }
+}
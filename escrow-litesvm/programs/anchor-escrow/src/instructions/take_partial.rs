@@ -0,0 +1,329 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{
+        CloseAccount, Mint, TokenAccount, TokenInterface, TransferChecked,
+    },
+};
+
+use crate::error::ErrorCode;
+use crate::events::EscrowTaken;
+use crate::fees::gross_up_for_fee;
+use crate::merkle::AllowlistProof;
+use crate::state::{
+    leg_mint, payout_account, settle_bond, AllowlistFill, Config, Escrow, MakerState, Pricing,
+};
+use crate::transfer::{close_account, transfer_checked};
+
+// Same accounts as `Take`, except the escrow is only closed once it is fully filled
+#[event_cpi]
+#[derive(Accounts)]
+pub struct TakePartial<'info> {
+    #[account(mut)]
+    pub taker: Signer<'info>,
+    // Receives the proceeds: the maker, or the holder of the escrow's receipt
+    #[account(mut)]
+    pub maker: SystemAccount<'info>,
+    #[account(mut)]
+    pub mint_a: Option<InterfaceAccount<'info, Mint>>,
+    pub mint_b: Option<InterfaceAccount<'info, Mint>>,
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = mint_a,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_ata_a: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_ata_b: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = mint_b,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program,
+    )]
+    pub maker_ata_b: Option<InterfaceAccount<'info, TokenAccount>>,
+    // Paid instead of `maker_ata_b` when the escrow chose a proceeds account on `make_for`
+    #[account(
+        mut,
+        constraint = escrow.proceeds_to == Some(proceeds_to.key())
+            @ ErrorCode::PayoutAccountMismatch,
+    )]
+    pub proceeds_to: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, Config>,
+    #[account(mut, address = config.treasury)]
+    pub treasury: SystemAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = mint_b,
+        associated_token::authority = treasury,
+        associated_token::token_program = token_program,
+    )]
+    pub treasury_ata_b: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = leg_mint(&mint_a) == escrow.mint_a @ ErrorCode::MintMismatch,
+        constraint = leg_mint(&mint_b) == escrow.mint_b @ ErrorCode::MintMismatch,
+        seeds = [b"escrow", escrow.maker.as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, Escrow>,
+    // The `maker`'s receipt token account, for escrows that minted one
+    pub receipt: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [b"maker", escrow.maker.as_ref()],
+        bump = maker_state.bump,
+    )]
+    pub maker_state: Account<'info, MakerState>,
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = escrow,
+        associated_token::token_program = token_program,
+    )]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    // Tracks the taker's running total against their cap on allowlisted escrows
+    #[account(
+        init_if_needed,
+        payer = taker,
+        space = 8 + AllowlistFill::INIT_SPACE,
+        seeds = [b"fill", escrow.key().as_ref(), taker.key().as_ref()],
+        bump,
+    )]
+    pub allowlist_fill: Option<Account<'info, AllowlistFill>>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+//Deposit amount_b from taker to maker, less the protocol fee sent to treasury
+//Release the pro-rata share of the vault to taker
+//Close vault and escrow once fully filled
+impl<'info> TakePartial<'info> {
+    pub fn deposit(
+        &mut self,
+        amount_b: u64,
+        expected_version: u32,
+        allowlist: Option<AllowlistProof>,
+        bumps: &TakePartialBumps,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> Result<u64> {
+        let now = Clock::get()?.unix_timestamp;
+        self.escrow.check_takeable(now)?;
+        self.escrow.check_taker(self.taker.key)?;
+        self.escrow.check_reservation(self.taker.key, now)?;
+        self.escrow.check_owner(self.maker.key, &self.receipt)?;
+        if self.escrow.allowlist_root.is_some() {
+            let max_fill = self.escrow.check_allowlist(self.taker.key, &allowlist)?;
+            let allowlist_fill = self
+                .allowlist_fill
+                .as_mut()
+                .ok_or(ErrorCode::MissingAllowlistFill)?;
+            allowlist_fill.bump = bumps.allowlist_fill.unwrap_or_default();
+            allowlist_fill.record(amount_b, max_fill)?;
+        }
+        self.escrow.check_version(expected_version)?;
+        self.config.check_not_paused()?;
+        require!(
+            self.escrow.pricing == Pricing::Fixed
+                && self.escrow.vesting.is_none()
+                && self.escrow.arbiter.is_none(),
+            ErrorCode::FullFillOnly
+        );
+        require!(
+            amount_b > 0 && amount_b <= self.escrow.receive,
+            ErrorCode::InvalidFillAmount
+        );
+        settle_bond(
+            &mut self.escrow,
+            &self.taker.to_account_info(),
+            &self.maker.to_account_info(),
+        )?;
+
+        // The last fill sweeps whatever is left so rounding never strands anything in escrow
+        let amount_a = if amount_b == self.escrow.receive {
+            match &self.vault {
+                Some(vault) => vault.amount,
+                None => self.escrow.deposit,
+            }
+        } else {
+            u64::try_from(
+                (self.escrow.deposit as u128)
+                    .checked_mul(amount_b as u128)
+                    .ok_or(ErrorCode::MathOverflow)?
+                    / self.escrow.receive as u128,
+            )
+            .map_err(|_| ErrorCode::MathOverflow)?
+        };
+        require!(amount_a > 0, ErrorCode::FillTooSmall);
+
+        let fee = self.config.fee(amount_b)?;
+        let maker_amount = amount_b - fee;
+
+        let maker_ata_b =
+            payout_account(self.escrow.proceeds_to, &self.proceeds_to, &self.maker_ata_b)?;
+        if let (Some(mint_b), Some(taker_ata_b), Some(maker_ata_b)) =
+            (&self.mint_b, &self.taker_ata_b, maker_ata_b)
+        {
+            let amount = if self.escrow.receive_net_of_fee {
+                gross_up_for_fee(mint_b, maker_amount)?
+            } else {
+                maker_amount
+            };
+
+            self.pay_tokens(mint_b, taker_ata_b, maker_ata_b, amount, remaining_accounts)?;
+
+            if fee > 0 {
+                let treasury_ata_b = self
+                    .treasury_ata_b
+                    .as_ref()
+                    .ok_or(ErrorCode::MissingTokenAccounts)?;
+
+                self.pay_tokens(mint_b, taker_ata_b, treasury_ata_b, fee, remaining_accounts)?;
+            }
+        } else {
+            require!(self.escrow.is_native_b(), ErrorCode::MissingTokenAccounts);
+
+            self.pay_lamports(&self.maker.to_account_info(), maker_amount)?;
+            self.pay_lamports(&self.treasury.to_account_info(), fee)?;
+        }
+
+        self.escrow.receive -= amount_b;
+        self.escrow.deposit = self.escrow.deposit.saturating_sub(amount_a);
+
+        Ok(amount_a)
+    }
+
+    pub fn taken_event(&self, amount_a: u64, amount_b: u64) -> Result<EscrowTaken> {
+        Ok(EscrowTaken {
+            escrow: self.escrow.key(),
+            maker: self.escrow.maker,
+            taker: self.taker.key(),
+            seed: self.escrow.seed,
+            mint_a: self.escrow.mint_a,
+            mint_b: self.escrow.mint_b,
+            amount_a,
+            amount_b,
+            fee: self.config.fee(amount_b)?,
+            remaining_deposit: self.escrow.deposit,
+            remaining_receive: self.escrow.receive,
+            timestamp: Clock::get()?.unix_timestamp,
+        })
+    }
+
+    fn pay_lamports(&self, to: &AccountInfo<'info>, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+
+        let cpi_program = self.system_program.to_account_info();
+
+        let cpi_accounts = Transfer {
+            from: self.taker.to_account_info(),
+            to: to.clone(),
+        };
+
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+
+        transfer(cpi_ctx, amount)
+    }
+
+    fn pay_tokens(
+        &self,
+        mint_b: &InterfaceAccount<'info, Mint>,
+        taker_ata_b: &InterfaceAccount<'info, TokenAccount>,
+        to: &InterfaceAccount<'info, TokenAccount>,
+        amount: u64,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> Result<()> {
+        let cpi_program = self.token_program.to_account_info();
+
+        let cpi_accounts = TransferChecked {
+            from: taker_ata_b.to_account_info(),
+            to: to.to_account_info(),
+            authority: self.taker.to_account_info(),
+            mint: mint_b.to_account_info(),
+        };
+
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts)
+            .with_remaining_accounts(remaining_accounts.to_vec());
+
+        transfer_checked(cpi_ctx, amount, mint_b.decimals)
+    }
+
+    pub fn withdraw(
+        &mut self,
+        amount_a: u64,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> Result<()> {
+        let (Some(mint_a), Some(taker_ata_a), Some(vault)) =
+            (&self.mint_a, &self.taker_ata_a, &self.vault)
+        else {
+            require!(self.escrow.is_native_a(), ErrorCode::MissingTokenAccounts);
+
+            self.escrow.sub_lamports(amount_a)?;
+            self.taker.add_lamports(amount_a)?;
+
+            if self.escrow.receive > 0 {
+                return Ok(());
+            }
+
+            self.maker_state.close_offer();
+            return self.escrow.close(self.maker.to_account_info());
+        };
+
+        let signer_seeds: [&[&[u8]]; 1] = [&[
+            b"escrow",
+            self.escrow.maker.as_ref(),
+            &self.escrow.seed.to_le_bytes()[..],
+            &[self.escrow.bump],
+        ]];
+
+        let cpi_program = self.token_program.to_account_info();
+
+        let cpi_accounts = TransferChecked {
+            from: vault.to_account_info(),
+            to: taker_ata_a.to_account_info(),
+            authority: self.escrow.to_account_info(),
+            mint: mint_a.to_account_info(),
+        };
+
+        let cpi_context = CpiContext::new_with_signer(cpi_program, cpi_accounts, &signer_seeds)
+            .with_remaining_accounts(remaining_accounts.to_vec());
+
+        transfer_checked(cpi_context, amount_a, mint_a.decimals)?;
+
+        if self.escrow.receive > 0 {
+            return Ok(());
+        }
+
+        let cpi_program = self.token_program.to_account_info();
+
+        let cpi_accounts = CloseAccount {
+            account: vault.to_account_info(),
+            destination: self.maker.to_account_info(),
+            authority: self.escrow.to_account_info(),
+        };
+
+        let cpi_context = CpiContext::new_with_signer(cpi_program, cpi_accounts, &signer_seeds);
+
+        close_account(cpi_context, mint_a.to_account_info())?;
+
+        self.maker_state.close_offer();
+        self.escrow.close(self.maker.to_account_info())
+    }
+}
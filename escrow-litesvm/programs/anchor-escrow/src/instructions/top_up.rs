@@ -0,0 +1,115 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface, TransferChecked};
+
+use crate::error::ErrorCode;
+use crate::events::EscrowAmended;
+use crate::state::{leg_mint, Escrow};
+use crate::transfer::transfer_checked;
+
+// Leave `mint_a`, `maker_ata_a` and `vault` out when the escrow offers native SOL
+#[event_cpi]
+#[derive(Accounts)]
+pub struct TopUp<'info> {
+    #[account(mut)]
+    maker: Signer<'info>,
+    mint_a: Option<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program,
+    )]
+    maker_ata_a: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = leg_mint(&mint_a) == escrow.mint_a @ ErrorCode::MintMismatch,
+        seeds = [b"escrow", escrow.maker.as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, Escrow>,
+    // The `maker`'s receipt token account, for escrows that minted one
+    receipt: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = escrow,
+        associated_token::token_program = token_program,
+    )]
+    vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    token_program: Interface<'info, TokenInterface>,
+    system_program: Program<'info, System>,
+}
+
+//Add more of the offered asset to the vault (or the escrow PDA for native SOL)
+impl<'info> TopUp<'info> {
+    pub fn top_up(&mut self, amount: u64, remaining_accounts: &[AccountInfo<'info>]) -> Result<()> {
+        self.escrow.check_open()?;
+        self.escrow.check_owner(self.maker.key, &self.receipt)?;
+        // A top-up bumps the version, which would void the reserver's pending take
+        require!(
+            self.escrow
+                .active_reservation(Clock::get()?.unix_timestamp)
+                .is_none(),
+            ErrorCode::Reserved
+        );
+        require!(amount > 0, ErrorCode::InvalidAmount);
+
+        let (Some(mint_a), Some(maker_ata_a), Some(vault)) =
+            (&self.mint_a, &self.maker_ata_a, &mut self.vault)
+        else {
+            require!(self.escrow.is_native_a(), ErrorCode::MissingTokenAccounts);
+
+            let cpi_program = self.system_program.to_account_info();
+
+            let cpi_accounts = Transfer {
+                from: self.maker.to_account_info(),
+                to: self.escrow.to_account_info(),
+            };
+
+            let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+
+            transfer(cpi_ctx, amount)?;
+
+            self.escrow.deposit = self
+                .escrow
+                .deposit
+                .checked_add(amount)
+                .ok_or(ErrorCode::MathOverflow)?;
+
+            return self.escrow.bump_version();
+        };
+
+        let cpi_program = self.token_program.to_account_info();
+
+        let cpi_accounts = TransferChecked {
+            from: maker_ata_a.to_account_info(),
+            to: vault.to_account_info(),
+            authority: self.maker.to_account_info(),
+            mint: mint_a.to_account_info(),
+        };
+
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts)
+            .with_remaining_accounts(remaining_accounts.to_vec());
+
+        transfer_checked(cpi_ctx, amount, mint_a.decimals)?;
+
+        // As in `make`, the vault balance is the source of truth for fee mints
+        vault.reload()?;
+        self.escrow.deposit = vault.amount;
+
+        self.escrow.bump_version()
+    }
+
+    pub fn amended_event(&self) -> Result<EscrowAmended> {
+        Ok(EscrowAmended {
+            escrow: self.escrow.key(),
+            maker: self.escrow.maker,
+            seed: self.escrow.seed,
+            deposit: self.escrow.deposit,
+            receive: self.escrow.receive,
+            version: self.escrow.version,
+            timestamp: Clock::get()?.unix_timestamp,
+        })
+    }
+}
@@ -0,0 +1,34 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+use crate::state::{Config, MAX_FEE_BPS};
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    admin: Signer<'info>,
+    #[account(
+        mut,
+        has_one = admin,
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, Config>,
+}
+
+impl<'info> UpdateConfig<'info> {
+    pub fn update_config(&mut self, fee_bps: u16, treasury: Pubkey) -> Result<()> {
+        require!(fee_bps <= MAX_FEE_BPS, ErrorCode::InvalidFee);
+
+        self.config.fee_bps = fee_bps;
+        self.config.treasury = treasury;
+
+        Ok(())
+    }
+
+    // Halts `make` and every take path; refunds and expiry stay open so funds can always leave
+    pub fn set_paused(&mut self, paused: bool) -> Result<()> {
+        self.config.paused = paused;
+
+        Ok(())
+    }
+}
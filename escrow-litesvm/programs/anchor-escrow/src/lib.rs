@@ -1,13 +1,24 @@
 #![allow(unexpected_cfgs)]
 #![allow(deprecated)]
+// The `cpi` functions generated from long handlers can't carry the handlers' own allow
+#![cfg_attr(feature = "cpi", allow(clippy::too_many_arguments))]
 
 use anchor_lang::prelude::*;
 
 mod instructions;
-mod state;
+pub mod state;
 mod tests;
 mod error;
+pub mod events;
+mod fees;
+pub mod merkle;
+mod oracle;
+pub mod signed_offer;
+mod transfer;
 use instructions::*;
+use merkle::AllowlistProof;
+use signed_offer::SignedOffer;
+use state::{BasketLeg, Pricing, ReservationTerms, TimeWindows, Vesting};
 
 declare_id!("FircrADQ2wgGuvpm8qneNCfKM7o5zoHTWnDQxngpTQ3J");
 
@@ -15,17 +26,272 @@ declare_id!("FircrADQ2wgGuvpm8qneNCfKM7o5zoHTWnDQxngpTQ3J");
 pub mod anchor_escrow {
     use super::*;
 
-    pub fn make(ctx: Context<Make>, seed: u64, deposit: u64, receive: u64) -> Result<()> {
-        ctx.accounts.init_escrow(seed, receive, &ctx.bumps)?;
-        ctx.accounts.deposit(deposit)
+    pub fn initialize_config(
+        ctx: Context<InitializeConfig>,
+        fee_bps: u16,
+        treasury: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.initialize_config(fee_bps, treasury, &ctx.bumps)
     }
 
-    pub fn refund(ctx: Context<Refund>) -> Result<()> {
-        ctx.accounts.refund_and_close_vault()
+    pub fn update_config(ctx: Context<UpdateConfig>, fee_bps: u16, treasury: Pubkey) -> Result<()> {
+        ctx.accounts.update_config(fee_bps, treasury)
     }
 
-    pub fn take(ctx: Context<Take>) -> Result<()> {
-        ctx.accounts.deposit()?;
-        ctx.accounts.withdraw_and_close_vault()
+    pub fn set_paused(ctx: Context<UpdateConfig>, paused: bool) -> Result<()> {
+        ctx.accounts.set_paused(paused)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn make<'info>(
+        ctx: Context<'_, '_, '_, 'info, Make<'info>>,
+        deposit: u64,
+        receive: u64,
+        windows: TimeWindows,
+        allowed_taker: Option<Pubkey>,
+        allowlist_root: Option<[u8; 32]>,
+        receive_net_of_fee: bool,
+        pricing: Pricing,
+        vesting: Option<Vesting>,
+        arbiter: Option<Pubkey>,
+    ) -> Result<()> {
+        ctx.accounts.init_escrow(
+            receive,
+            windows,
+            allowed_taker,
+            allowlist_root,
+            receive_net_of_fee,
+            pricing,
+            vesting,
+            arbiter,
+            &ctx.bumps,
+        )?;
+        ctx.accounts.mint_receipt()?;
+        ctx.accounts.deposit(deposit, ctx.remaining_accounts)?;
+        emit_cpi!(ctx.accounts.made_event()?);
+        Ok(())
+    }
+
+    pub fn make_for<'info>(
+        ctx: Context<'_, '_, '_, 'info, MakeFor<'info>>,
+        deposit: u64,
+        receive: u64,
+        windows: TimeWindows,
+        allowed_taker: Option<Pubkey>,
+        receive_net_of_fee: bool,
+        pricing: Pricing,
+    ) -> Result<()> {
+        ctx.accounts.init_escrow(
+            receive,
+            windows,
+            allowed_taker,
+            receive_net_of_fee,
+            pricing,
+            &ctx.bumps,
+        )?;
+        ctx.accounts.deposit(deposit, ctx.remaining_accounts)?;
+        emit_cpi!(ctx.accounts.made_event()?);
+        Ok(())
+    }
+
+    pub fn refund<'info>(ctx: Context<'_, '_, '_, 'info, Refund<'info>>) -> Result<()> {
+        let event = refunded_event(&ctx.accounts.escrow, false)?;
+        ctx.accounts.refund_and_close_vault(ctx.remaining_accounts)?;
+        emit_cpi!(event);
+        Ok(())
+    }
+
+    pub fn expire<'info>(ctx: Context<'_, '_, '_, 'info, Expire<'info>>) -> Result<()> {
+        let event = refunded_event(&ctx.accounts.escrow, true)?;
+        ctx.accounts.expire_and_close_vault(ctx.remaining_accounts)?;
+        emit_cpi!(event);
+        Ok(())
+    }
+
+    pub fn take<'info>(
+        ctx: Context<'_, '_, '_, 'info, Take<'info>>,
+        expected_version: u32,
+        max_payment: u64,
+        allowlist: Option<AllowlistProof>,
+    ) -> Result<()> {
+        let amount_a = ctx.accounts.escrow.deposit;
+        let amount_b = ctx.accounts.deposit(
+            expected_version,
+            max_payment,
+            allowlist,
+            &ctx.bumps,
+            ctx.remaining_accounts,
+        )?;
+        ctx.accounts.withdraw_and_close_vault(ctx.remaining_accounts)?;
+        emit_cpi!(ctx.accounts.taken_event(amount_a, amount_b)?);
+        Ok(())
+    }
+
+    pub fn take_many<'info>(
+        ctx: Context<'_, '_, '_, 'info, TakeMany<'info>>,
+        max_total_payment: u64,
+    ) -> Result<()> {
+        let events = ctx
+            .accounts
+            .take_many(max_total_payment, ctx.remaining_accounts)?;
+        for event in events {
+            emit_cpi!(event);
+        }
+        Ok(())
+    }
+
+    pub fn match_escrows<'info>(
+        ctx: Context<'_, '_, '_, 'info, MatchEscrows<'info>>,
+    ) -> Result<()> {
+        let events = ctx.accounts.match_escrows(ctx.remaining_accounts)?;
+        for event in events {
+            emit_cpi!(event);
+        }
+        Ok(())
+    }
+
+    pub fn take_offer<'info>(
+        ctx: Context<'_, '_, '_, 'info, TakeOffer<'info>>,
+        offer: SignedOffer,
+    ) -> Result<()> {
+        ctx.accounts.verify_and_consume(&offer, &ctx.bumps)?;
+        let fee = ctx.accounts.pay_maker(&offer, ctx.remaining_accounts)?;
+        ctx.accounts.pull_from_maker(&offer, &ctx.bumps, ctx.remaining_accounts)?;
+        emit_cpi!(ctx.accounts.taken_event(&offer, fee)?);
+        Ok(())
+    }
+
+    pub fn cancel_offer(ctx: Context<CancelOffer>, nonce: u64) -> Result<()> {
+        ctx.accounts.cancel_offer(nonce, &ctx.bumps)
+    }
+
+    pub fn take_partial<'info>(
+        ctx: Context<'_, '_, '_, 'info, TakePartial<'info>>,
+        amount_b: u64,
+        expected_version: u32,
+        allowlist: Option<AllowlistProof>,
+    ) -> Result<()> {
+        let amount_a = ctx.accounts.deposit(
+            amount_b,
+            expected_version,
+            allowlist,
+            &ctx.bumps,
+            ctx.remaining_accounts,
+        )?;
+        ctx.accounts.withdraw(amount_a, ctx.remaining_accounts)?;
+        emit_cpi!(ctx.accounts.taken_event(amount_a, amount_b)?);
+        Ok(())
+    }
+
+    pub fn claim<'info>(ctx: Context<'_, '_, '_, 'info, Claim<'info>>) -> Result<()> {
+        let amount = ctx.accounts.claim(ctx.remaining_accounts)?;
+        emit_cpi!(ctx.accounts.claimed_event(amount)?);
+        Ok(())
+    }
+
+    pub fn dispute(ctx: Context<Dispute>) -> Result<()> {
+        ctx.accounts.dispute()?;
+        emit_cpi!(ctx.accounts.disputed_event()?);
+        Ok(())
+    }
+
+    pub fn approve<'info>(ctx: Context<'_, '_, '_, 'info, Settle<'info>>) -> Result<()> {
+        let settled = ctx.accounts.approve(ctx.remaining_accounts)?;
+        emit_cpi!(ctx.accounts.approved_event(settled)?);
+        Ok(())
+    }
+
+    pub fn resolve<'info>(
+        ctx: Context<'_, '_, '_, 'info, Settle<'info>>,
+        taker_share_bps: u16,
+    ) -> Result<()> {
+        let (amount_a, amount_b) =
+            ctx.accounts.resolve(taker_share_bps, ctx.remaining_accounts)?;
+        emit_cpi!(ctx.accounts.resolved_event(taker_share_bps, amount_a, amount_b)?);
+        Ok(())
+    }
+
+    pub fn post_counter<'info>(
+        ctx: Context<'_, '_, '_, 'info, PostCounter<'info>>,
+        amount_a: u64,
+        amount_b: u64,
+        funded: bool,
+    ) -> Result<()> {
+        ctx.accounts
+            .post(amount_a, amount_b, funded, &ctx.bumps, ctx.remaining_accounts)?;
+        emit_cpi!(ctx.accounts.posted_event()?);
+        Ok(())
+    }
+
+    pub fn accept_counter<'info>(
+        ctx: Context<'_, '_, '_, 'info, AcceptCounter<'info>>,
+    ) -> Result<()> {
+        let (amount_a, amount_b) = ctx.accounts.accept(ctx.remaining_accounts)?;
+        emit_cpi!(ctx.accounts.taken_event(amount_a, amount_b)?);
+        Ok(())
+    }
+
+    pub fn reject_counter<'info>(
+        ctx: Context<'_, '_, '_, 'info, RejectCounter<'info>>,
+    ) -> Result<()> {
+        ctx.accounts.reject(ctx.remaining_accounts)?;
+        emit_cpi!(ctx.accounts.rejected_event()?);
+        Ok(())
+    }
+
+    pub fn amend(ctx: Context<Amend>, receive: u64) -> Result<()> {
+        ctx.accounts.amend(receive)?;
+        emit_cpi!(ctx.accounts.amended_event()?);
+        Ok(())
+    }
+
+    pub fn top_up<'info>(
+        ctx: Context<'_, '_, '_, 'info, TopUp<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        ctx.accounts.top_up(amount, ctx.remaining_accounts)?;
+        emit_cpi!(ctx.accounts.amended_event()?);
+        Ok(())
+    }
+
+    pub fn set_reservation_terms(
+        ctx: Context<SetReservationTerms>,
+        terms: Option<ReservationTerms>,
+    ) -> Result<()> {
+        ctx.accounts.set_reservation_terms(terms)?;
+        emit_cpi!(ctx.accounts.terms_set_event()?);
+        Ok(())
+    }
+
+    pub fn reserve(ctx: Context<Reserve>, duration: i64) -> Result<()> {
+        ctx.accounts.reserve(duration)?;
+        emit_cpi!(ctx.accounts.reserved_event()?);
+        Ok(())
+    }
+
+    pub fn make_basket<'info>(
+        ctx: Context<'_, '_, '_, 'info, MakeBasket<'info>>,
+        deposits: Vec<u64>,
+        requested: Vec<BasketLeg>,
+    ) -> Result<()> {
+        ctx.accounts.init_basket(requested, &ctx.bumps)?;
+        ctx.accounts.deposit(deposits, ctx.remaining_accounts)?;
+        emit_cpi!(ctx.accounts.made_event()?);
+        Ok(())
+    }
+
+    pub fn refund_basket<'info>(
+        ctx: Context<'_, '_, '_, 'info, RefundBasket<'info>>,
+    ) -> Result<()> {
+        ctx.accounts.refund_and_close_vaults(ctx.remaining_accounts)?;
+        emit_cpi!(ctx.accounts.refunded_event()?);
+        Ok(())
+    }
+
+    pub fn take_basket<'info>(ctx: Context<'_, '_, '_, 'info, TakeBasket<'info>>) -> Result<()> {
+        ctx.accounts.deposit(ctx.remaining_accounts)?;
+        ctx.accounts.withdraw_and_close_vaults(ctx.remaining_accounts)?;
+        emit_cpi!(ctx.accounts.taken_event()?);
+        Ok(())
     }
 }
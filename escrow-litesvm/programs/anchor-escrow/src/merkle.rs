@@ -0,0 +1,28 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+
+// Leaves and inner nodes are hashed with different prefixes so a node can never pass as a leaf.
+// Pairs are sorted before hashing, so proofs carry no left/right flags.
+const LEAF_PREFIX: &[u8] = &[0];
+const NODE_PREFIX: &[u8] = &[1];
+
+// Proof that the taker's leaf is in an escrow's allowlist. Use a `max_fill` of `u64::MAX`
+// for takers without a cap on how much `mint_b` they may pay.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct AllowlistProof {
+    pub max_fill: u64,
+    pub proof: Vec<[u8; 32]>,
+}
+
+pub fn leaf(taker: &Pubkey, max_fill: u64) -> [u8; 32] {
+    hashv(&[LEAF_PREFIX, taker.as_ref(), &max_fill.to_le_bytes()]).to_bytes()
+}
+
+pub fn hash_pair(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let (first, second) = if a <= b { (a, b) } else { (b, a) };
+    hashv(&[NODE_PREFIX, first, second]).to_bytes()
+}
+
+pub fn verify(proof: &[[u8; 32]], root: [u8; 32], leaf: [u8; 32]) -> bool {
+    proof.iter().fold(leaf, |node, sibling| hash_pair(&node, sibling)) == root
+}
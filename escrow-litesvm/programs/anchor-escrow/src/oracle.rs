@@ -0,0 +1,60 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+
+// Pyth pull-oracle receiver program, owner of every `PriceUpdateV2` account
+pub const PYTH_RECEIVER_ID: Pubkey = pubkey!("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ");
+
+pub const PRICE_UPDATE_V2_DISCRIMINATOR: [u8; 8] = [34, 241, 35, 99, 157, 126, 244, 205];
+
+// Local mirror of the pyth-solana-receiver-sdk `PriceUpdateV2` layout, so reading one
+// account does not pull the Pyth SDK into the program
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub enum VerificationLevel {
+    Partial { num_signatures: u8 },
+    Full,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub struct PriceFeedMessage {
+    pub feed_id: [u8; 32],
+    pub price: i64,
+    pub conf: u64,
+    pub exponent: i32,
+    pub publish_time: i64,
+    pub prev_publish_time: i64,
+    pub ema_price: i64,
+    pub ema_conf: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub struct PriceUpdateV2 {
+    pub write_authority: Pubkey,
+    pub verification_level: VerificationLevel,
+    pub price_message: PriceFeedMessage,
+    pub posted_slot: u64,
+}
+
+impl PriceUpdateV2 {
+    pub fn load(price_update: &AccountInfo) -> Result<Self> {
+        require_keys_eq!(
+            *price_update.owner,
+            PYTH_RECEIVER_ID,
+            ErrorCode::InvalidPriceUpdate
+        );
+
+        let data = price_update.try_borrow_data()?;
+        let Some(mut data) = data.strip_prefix(&PRICE_UPDATE_V2_DISCRIMINATOR) else {
+            return err!(ErrorCode::InvalidPriceUpdate);
+        };
+        let update = Self::deserialize(&mut data).map_err(|_| ErrorCode::InvalidPriceUpdate)?;
+
+        // Partially verified updates only carry a subset of guardian signatures
+        require!(
+            update.verification_level == VerificationLevel::Full,
+            ErrorCode::InvalidPriceUpdate
+        );
+
+        Ok(update)
+    }
+}
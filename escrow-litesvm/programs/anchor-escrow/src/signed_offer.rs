@@ -0,0 +1,75 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{
+    ed25519_program,
+    sysvar::instructions::{load_current_index_checked, load_instruction_at_checked},
+};
+
+use crate::error::ErrorCode;
+
+// Layout of an Ed25519 precompile instruction: a signature count and a padding byte, then
+// one 14-byte block of u16 offsets per signature
+const OFFSETS_START: usize = 2;
+const OFFSETS_LEN: usize = 14;
+// Instruction index the precompile reads as "the data of this same instruction"
+const THIS_INSTRUCTION: u16 = u16::MAX;
+
+// Terms a maker signs off-chain instead of opening an escrow. `amount_a` of `mint_a` is pulled
+// from the maker's ATA through the delegate approval, for `amount_b` of `mint_b`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SignedOffer {
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    pub amount_a: u64,
+    pub amount_b: u64,
+    pub expiry_time: i64,
+    pub nonce: u64,
+}
+
+impl SignedOffer {
+    // The bytes the maker signs: the program id, so signatures cannot be replayed against
+    // another deployment, followed by the borsh-encoded offer
+    pub fn message(&self) -> Result<Vec<u8>> {
+        let mut message = crate::ID.to_bytes().to_vec();
+        self.serialize(&mut message)?;
+        Ok(message)
+    }
+}
+
+// The instruction right before the current one has to be an Ed25519 precompile check of a
+// single signature by `signer` over `message`, with all of it inline in that instruction.
+// The runtime fails the whole transaction before we get here if the signature is invalid.
+pub fn verify_signature(instructions: &AccountInfo, signer: &Pubkey, message: &[u8]) -> Result<()> {
+    let current = load_current_index_checked(instructions)?;
+    let Some(previous) = current.checked_sub(1) else {
+        return err!(ErrorCode::InvalidOfferSignature);
+    };
+    let ix = load_instruction_at_checked(previous as usize, instructions)?;
+    require_keys_eq!(ix.program_id, ed25519_program::ID, ErrorCode::InvalidOfferSignature);
+
+    let data = &ix.data;
+    require!(
+        data.len() >= OFFSETS_START + OFFSETS_LEN && data[0] == 1,
+        ErrorCode::InvalidOfferSignature
+    );
+    let offset = |i: usize| {
+        let at = OFFSETS_START + 2 * i;
+        u16::from_le_bytes([data[at], data[at + 1]])
+    };
+    let (signature_ix, public_key_offset, public_key_ix) = (offset(1), offset(2), offset(3));
+    let (message_offset, message_size, message_ix) = (offset(4), offset(5), offset(6));
+    require!(
+        [signature_ix, public_key_ix, message_ix] == [THIS_INSTRUCTION; 3],
+        ErrorCode::InvalidOfferSignature
+    );
+
+    let public_key_start = public_key_offset as usize;
+    let message_start = message_offset as usize;
+    let public_key = data.get(public_key_start..public_key_start + 32);
+    let signed = data.get(message_start..message_start + message_size as usize);
+    require!(
+        public_key == Some(signer.as_ref()) && signed == Some(message),
+        ErrorCode::InvalidOfferSignature
+    );
+
+    Ok(())
+}
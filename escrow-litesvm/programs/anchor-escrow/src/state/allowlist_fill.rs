@@ -0,0 +1,20 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+
+// How much `mint_b` one allowlisted taker has paid into a partially filled escrow,
+// so their `max_fill` holds across fills
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct AllowlistFill {
+    pub filled: u64,
+    pub bump: u8,
+}
+
+impl AllowlistFill {
+    pub fn record(&mut self, amount_b: u64, max_fill: u64) -> Result<()> {
+        self.filled = self.filled.checked_add(amount_b).ok_or(ErrorCode::MathOverflow)?;
+        require!(self.filled <= max_fill, ErrorCode::AllowlistFillExceeded);
+        Ok(())
+    }
+}
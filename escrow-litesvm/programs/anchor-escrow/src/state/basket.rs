@@ -0,0 +1,42 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+
+pub const MAX_BASKET_LEGS: usize = 5;
+
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, Debug, PartialEq)]
+pub struct BasketLeg {
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+// Several mints offered for several mints, each offered leg sits in its own vault ATA
+// owned by this PDA
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct BasketEscrow {
+    pub seed: u64,
+    pub maker: Pubkey,
+    #[max_len(MAX_BASKET_LEGS)]
+    pub offered: Vec<BasketLeg>,
+    #[max_len(MAX_BASKET_LEGS)]
+    pub requested: Vec<BasketLeg>,
+    pub creation_time: i64,
+    pub bump: u8,
+}
+
+impl BasketEscrow {
+    pub fn check_legs(legs: &[BasketLeg]) -> Result<()> {
+        require!(
+            !legs.is_empty() && legs.len() <= MAX_BASKET_LEGS,
+            ErrorCode::InvalidBasketSize
+        );
+        for (i, leg) in legs.iter().enumerate() {
+            require!(
+                legs[..i].iter().all(|other| other.mint != leg.mint),
+                ErrorCode::DuplicateBasketMint
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,35 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+
+// Highest protocol fee the admin can set, 10%
+pub const MAX_FEE_BPS: u16 = 1_000;
+
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct Config {
+    pub admin: Pubkey,
+    // Wallet whose ATAs (or itself, for native SOL) receive the protocol fee
+    pub treasury: Pubkey,
+    pub fee_bps: u16,
+    pub paused: bool,
+    pub bump: u8,
+}
+
+impl Config {
+    pub fn check_not_paused(&self) -> Result<()> {
+        require!(!self.paused, ErrorCode::Paused);
+        Ok(())
+    }
+
+    // Protocol fee carved out of `amount` before it reaches the maker, rounded down
+    pub fn fee(&self, amount: u64) -> Result<u64> {
+        u64::try_from(
+            (amount as u128)
+                .checked_mul(self.fee_bps as u128)
+                .ok_or(ErrorCode::MathOverflow)?
+                / 10_000,
+        )
+        .map_err(|_| ErrorCode::MathOverflow.into())
+    }
+}
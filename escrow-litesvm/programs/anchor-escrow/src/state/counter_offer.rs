@@ -0,0 +1,16 @@
+use anchor_lang::prelude::*;
+
+// A taker's proposed terms for an open escrow: `amount_a` of its deposit for `amount_b`.
+// A `funded` counter holds `amount_b` in its own vault (or as lamports for native SOL),
+// otherwise the taker's `mint_b` account delegates `amount_b` to it until it is settled.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct CounterOffer {
+    pub escrow: Pubkey,
+    pub taker: Pubkey,
+    pub mint_b: Pubkey,
+    pub amount_a: u64,
+    pub amount_b: u64,
+    pub funded: bool,
+    pub bump: u8,
+}
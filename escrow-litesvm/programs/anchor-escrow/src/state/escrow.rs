@@ -1,4 +1,61 @@
 use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount};
+
+use crate::error::ErrorCode;
+use crate::merkle::{self, AllowlistProof};
+use crate::oracle::PriceUpdateV2;
+
+// Stored in place of a mint when that leg of the escrow is plain lamports
+pub const NATIVE_SOL: Pubkey = anchor_lang::system_program::ID;
+pub const NATIVE_SOL_DECIMALS: u8 = 9;
+
+// Key recorded for an optional mint account, `NATIVE_SOL` when the leg is lamports
+pub fn leg_mint(mint: &Option<InterfaceAccount<Mint>>) -> Pubkey {
+    mint.as_ref().map_or(NATIVE_SOL, |mint| mint.key())
+}
+
+pub fn leg_decimals(mint: &Option<InterfaceAccount<Mint>>) -> u8 {
+    mint.as_ref().map_or(NATIVE_SOL_DECIMALS, |mint| mint.decimals)
+}
+
+// Token account a leg is paid out to: `account` when the escrow chose one at make-time,
+// which then has to be passed, otherwise the owner's `ata`
+pub fn payout_account<'a, 'info>(
+    chosen: Option<Pubkey>,
+    account: &'a Option<InterfaceAccount<'info, TokenAccount>>,
+    ata: &'a Option<InterfaceAccount<'info, TokenAccount>>,
+) -> Result<&'a Option<InterfaceAccount<'info, TokenAccount>>> {
+    if chosen.is_none() {
+        return Ok(ata);
+    }
+    require!(account.is_some(), ErrorCode::MissingTokenAccounts);
+    Ok(account)
+}
+
+// Clears the escrow's reservation and pays its bond out of the escrow account to `to`
+pub fn release_bond<'info>(
+    escrow: &mut Account<'info, Escrow>,
+    to: &AccountInfo<'info>,
+) -> Result<()> {
+    let Some(reservation) = escrow.reservation.take() else {
+        return Ok(());
+    };
+    escrow.sub_lamports(reservation.bond)?;
+    to.add_lamports(reservation.bond)?;
+    Ok(())
+}
+
+// On a fill the reserver gets their bond back. Anyone else only gets past
+// `check_reservation` once it has lapsed, and then the bond is the maker's.
+pub fn settle_bond<'info>(
+    escrow: &mut Account<'info, Escrow>,
+    taker: &AccountInfo<'info>,
+    maker: &AccountInfo<'info>,
+) -> Result<()> {
+    let reserver = escrow.reservation.map(|reservation| reservation.taker);
+    let to = if reserver == Some(taker.key()) { taker } else { maker };
+    release_bond(escrow, to)
+}
 
 #[account]
 #[derive(InitSpace, Debug)]
@@ -7,7 +64,361 @@ pub struct Escrow {
     pub maker: Pubkey,
     pub mint_a: Pubkey,
     pub mint_b: Pubkey,
+    pub deposit: u64,
     pub receive: u64,
+    // When set, takers gross up their transfer so the maker nets `receive` after Token-2022 fees
+    pub receive_net_of_fee: bool,
     pub creation_time: i64,
+    pub take_open_time: i64,
+    pub expiry_time: i64,
+    pub refund_lock_time: i64,
+    pub allowed_taker: Option<Pubkey>,
+    // Merkle root of the takers allowed in, each with a cap on what they may pay
+    pub allowlist_root: Option<[u8; 32]>,
+    pub pricing: Pricing,
+    // Bumped by every `amend` and `top_up` so takers can pin the terms they saw
+    pub version: u32,
+    pub vesting: Option<Vesting>,
+    // Set by `take` on vesting escrows, which stay open until the taker has claimed everything
+    pub beneficiary: Option<Pubkey>,
+    pub vesting_start: i64,
+    pub claimed: u64,
+    // Arbiter escrows hold the taker's payment too, until both sides approve or the arbiter
+    // resolves a dispute. `held_b` is what was paid in, before any Token-2022 transfer fee.
+    pub arbiter: Option<Pubkey>,
+    pub held_b: u64,
+    pub maker_approved: bool,
+    pub taker_approved: bool,
+    pub disputed: bool,
+    // 1-of-1 Token-2022 receipt minted on `make`; its holder takes the maker's place
+    pub receipt_mint: Option<Pubkey>,
+    // Token accounts chosen on `make_for` that take proceeds and refunds are paid to,
+    // in place of the maker's ATAs
+    pub proceeds_to: Option<Pubkey>,
+    pub refund_to: Option<Pubkey>,
+    // Set by the maker to let takers reserve the escrow; `reservation` is the current or
+    // last reservation, whose bond sits on the escrow account until it is settled
+    pub reservation_terms: Option<ReservationTerms>,
+    pub reservation: Option<Reservation>,
     pub bump: u8,
 }
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct TimeWindows {
+    pub take_open_time: i64,
+    pub expiry_time: i64,
+    pub refund_lock_time: i64,
+}
+
+impl TimeWindows {
+    pub fn check(&self, now: i64) -> Result<()> {
+        require!(
+            self.take_open_time < self.expiry_time && self.expiry_time > now,
+            ErrorCode::InvalidTimeWindow
+        );
+        Ok(())
+    }
+}
+
+// Releases `mint_a` to the taker linearly over `duration` seconds from the take,
+// with nothing claimable before `cliff` seconds have passed
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, Debug, PartialEq)]
+pub struct Vesting {
+    pub cliff: i64,
+    pub duration: i64,
+}
+
+impl Vesting {
+    pub fn check(&self) -> Result<()> {
+        require!(
+            self.duration > 0 && self.cliff >= 0 && self.cliff <= self.duration,
+            ErrorCode::InvalidVesting
+        );
+        Ok(())
+    }
+}
+
+// A reserving taker posts `bond` lamports and holds the escrow for up to `max_duration` seconds.
+// A maker refunding while it is held pays the reserver a penalty equal to the bond.
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, Debug, PartialEq)]
+pub struct ReservationTerms {
+    pub bond: u64,
+    pub max_duration: i64,
+}
+
+impl ReservationTerms {
+    pub fn check(&self) -> Result<()> {
+        require!(self.bond > 0 && self.max_duration > 0, ErrorCode::InvalidReservation);
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, Debug, PartialEq)]
+pub struct Reservation {
+    pub taker: Pubkey,
+    pub bond: u64,
+    pub expires_at: i64,
+}
+
+// How a take is priced. `receive` is the fixed price, the auction start price,
+// or the least an oracle quote is allowed to fall to.
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, Debug, PartialEq)]
+pub enum Pricing {
+    Fixed,
+    DutchAuction(DutchAuction),
+    Oracle(OraclePricing),
+}
+
+impl Pricing {
+    pub fn check(&self, receive: u64) -> Result<()> {
+        match self {
+            Pricing::Fixed => Ok(()),
+            Pricing::DutchAuction(auction) => auction.check(receive),
+            Pricing::Oracle(oracle) => oracle.check(),
+        }
+    }
+}
+
+// Linear decay from the escrow's `receive` at `decay_start` down to `floor_receive` at `decay_end`
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, Debug, PartialEq)]
+pub struct DutchAuction {
+    pub floor_receive: u64,
+    pub decay_start: i64,
+    pub decay_end: i64,
+}
+
+impl DutchAuction {
+    pub fn check(&self, start_receive: u64) -> Result<()> {
+        require!(
+            self.floor_receive <= start_receive && self.decay_start < self.decay_end,
+            ErrorCode::InvalidAuction
+        );
+        Ok(())
+    }
+}
+
+// Quotes the whole deposit off a Pyth `PriceUpdateV2` feed giving the price of one `mint_a`
+// token in `mint_b` tokens, adjusted by `premium_bps` (negative for a discount)
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, Debug, PartialEq)]
+pub struct OraclePricing {
+    pub feed_id: [u8; 32],
+    pub premium_bps: i16,
+    pub max_staleness: u32,
+    pub max_confidence_bps: u16,
+}
+
+impl OraclePricing {
+    pub fn check(&self) -> Result<()> {
+        require!(
+            self.premium_bps > -10_000 && self.max_confidence_bps <= 10_000,
+            ErrorCode::InvalidOraclePricing
+        );
+        Ok(())
+    }
+
+    // Amount of `mint_b` base units the `deposit` of `mint_a` base units is worth at `now`
+    pub fn quote(
+        &self,
+        price_update: &AccountInfo,
+        deposit: u64,
+        decimals_a: u8,
+        decimals_b: u8,
+        now: i64,
+    ) -> Result<u64> {
+        let message = PriceUpdateV2::load(price_update)?.price_message;
+        require!(message.feed_id == self.feed_id, ErrorCode::InvalidPriceUpdate);
+        require!(
+            message.publish_time.saturating_add(self.max_staleness as i64) >= now,
+            ErrorCode::StalePrice
+        );
+        require!(message.price > 0, ErrorCode::InvalidPriceUpdate);
+
+        let price = message.price as u128;
+        require!(
+            (message.conf as u128) * 10_000 <= price * self.max_confidence_bps as u128,
+            ErrorCode::PriceConfidenceTooWide
+        );
+
+        // deposit / 10^decimals_a * price * 10^exponent * 10^decimals_b * (1 + premium)
+        let value = (deposit as u128)
+            .checked_mul(price)
+            .and_then(|value| value.checked_mul((10_000 + self.premium_bps as i32) as u128))
+            .ok_or(ErrorCode::MathOverflow)?;
+        let scale = message.exponent + decimals_b as i32 - decimals_a as i32;
+        let scaled = if scale >= 0 {
+            10u128
+                .checked_pow(scale as u32)
+                .and_then(|factor| value.checked_mul(factor))
+        } else {
+            10u128
+                .checked_pow(scale.unsigned_abs())
+                .map(|divisor| value / divisor)
+        }
+        .ok_or(ErrorCode::MathOverflow)?;
+
+        u64::try_from(scaled / 10_000).map_err(|_| ErrorCode::MathOverflow.into())
+    }
+}
+
+impl Escrow {
+    pub fn is_native_a(&self) -> bool {
+        self.mint_a == NATIVE_SOL
+    }
+
+    pub fn is_native_b(&self) -> bool {
+        self.mint_b == NATIVE_SOL
+    }
+
+    // `owner` is entitled to what the maker would get: the maker itself, or the holder
+    // of the receipt when the escrow minted one
+    pub fn check_owner(
+        &self,
+        owner: &Pubkey,
+        receipt: &Option<InterfaceAccount<TokenAccount>>,
+    ) -> Result<()> {
+        let Some(receipt_mint) = self.receipt_mint else {
+            require_keys_eq!(*owner, self.maker, ErrorCode::NotEscrowOwner);
+            return Ok(());
+        };
+        let receipt = receipt.as_ref().ok_or(ErrorCode::NotEscrowOwner)?;
+        require!(
+            receipt.mint == receipt_mint && receipt.owner == *owner && receipt.amount == 1,
+            ErrorCode::NotEscrowOwner
+        );
+        Ok(())
+    }
+
+    // Vesting escrows that have been taken only accept claims
+    pub fn check_open(&self) -> Result<()> {
+        require!(self.beneficiary.is_none(), ErrorCode::AlreadyTaken);
+        Ok(())
+    }
+
+    pub fn check_takeable(&self, now: i64) -> Result<()> {
+        self.check_open()?;
+        require!(now >= self.take_open_time, ErrorCode::Locked);
+        require!(now < self.expiry_time, ErrorCode::Expired);
+        Ok(())
+    }
+
+    pub fn check_taker(&self, taker: &Pubkey) -> Result<()> {
+        if let Some(allowed_taker) = self.allowed_taker {
+            require_keys_eq!(allowed_taker, *taker, ErrorCode::UnauthorizedTaker);
+        }
+        // Otherwise the arbiter could take, dispute and resolve the whole deal in its own favour
+        if let Some(arbiter) = self.arbiter {
+            require_keys_neq!(arbiter, *taker, ErrorCode::ArbiterCannotTake);
+        }
+        Ok(())
+    }
+
+    // Most `mint_b` the taker may pay in, `u64::MAX` when the escrow has no allowlist
+    pub fn check_allowlist(&self, taker: &Pubkey, proof: &Option<AllowlistProof>) -> Result<u64> {
+        let Some(root) = self.allowlist_root else {
+            return Ok(u64::MAX);
+        };
+        let proof = proof.as_ref().ok_or(ErrorCode::NotAllowlisted)?;
+        require!(
+            merkle::verify(&proof.proof, root, merkle::leaf(taker, proof.max_fill)),
+            ErrorCode::NotAllowlisted
+        );
+        Ok(proof.max_fill)
+    }
+
+    // Counter-offers are settled by the maker, so escrows with taker-side rules beyond
+    // a designated taker are left out
+    pub fn check_counterable(&self, taker: &Pubkey, amount_a: u64, amount_b: u64) -> Result<()> {
+        require!(
+            self.allowlist_root.is_none()
+                && self.vesting.is_none()
+                && self.arbiter.is_none()
+                && self.proceeds_to.is_none()
+                && self.refund_to.is_none()
+                && self.reservation_terms.is_none(),
+            ErrorCode::CounterUnsupported
+        );
+        require!(
+            amount_a > 0 && amount_a <= self.deposit && amount_b > 0,
+            ErrorCode::InvalidCounterOffer
+        );
+        self.check_taker(taker)
+    }
+
+    // The reservation holding the escrow at `now`, if it has not lapsed
+    pub fn active_reservation(&self, now: i64) -> Option<Reservation> {
+        self.reservation.filter(|reservation| now < reservation.expires_at)
+    }
+
+    // Only the reserver can take while a reservation is active
+    pub fn check_reservation(&self, taker: &Pubkey, now: i64) -> Result<()> {
+        if let Some(reservation) = self.active_reservation(now) {
+            require_keys_eq!(reservation.taker, *taker, ErrorCode::Reserved);
+        }
+        Ok(())
+    }
+
+    pub fn check_version(&self, expected_version: u32) -> Result<()> {
+        require_eq!(self.version, expected_version, ErrorCode::VersionMismatch);
+        Ok(())
+    }
+
+    pub fn bump_version(&mut self) -> Result<()> {
+        self.version = self.version.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    // Amount of `mint_b` a full take of a fixed-price or auction escrow costs at `now`
+    pub fn current_receive(&self, now: i64) -> Result<u64> {
+        let Pricing::DutchAuction(auction) = self.pricing else {
+            return Ok(self.receive);
+        };
+        if now <= auction.decay_start {
+            return Ok(self.receive);
+        }
+        if now >= auction.decay_end {
+            return Ok(auction.floor_receive);
+        }
+
+        let elapsed = (now - auction.decay_start) as u128;
+        let window = (auction.decay_end - auction.decay_start) as u128;
+        let decay = (self.receive - auction.floor_receive) as u128 * elapsed / window;
+
+        u64::try_from(self.receive as u128 - decay).map_err(|_| ErrorCode::MathOverflow.into())
+    }
+
+    pub fn check_refundable(&self, now: i64) -> Result<()> {
+        self.check_open()?;
+        require!(now >= self.refund_lock_time, ErrorCode::RefundLocked);
+        Ok(())
+    }
+
+    // Taken arbiter escrows, where `party` is the maker or the taker
+    pub fn check_party(&self, party: &Pubkey) -> Result<()> {
+        require!(
+            self.arbiter.is_some() && self.beneficiary.is_some(),
+            ErrorCode::NotArbitrated
+        );
+        require!(
+            *party == self.maker || Some(*party) == self.beneficiary,
+            ErrorCode::NotEscrowParty
+        );
+        Ok(())
+    }
+
+    // Amount of `mint_a` unlocked to the beneficiary by `now`, claimed or not
+    pub fn vested(&self, now: i64) -> Result<u64> {
+        let Some(vesting) = self.vesting else {
+            return Ok(0);
+        };
+        let elapsed = now.saturating_sub(self.vesting_start);
+        if elapsed < vesting.cliff {
+            return Ok(0);
+        }
+        if elapsed >= vesting.duration {
+            return Ok(self.deposit);
+        }
+
+        u64::try_from(self.deposit as u128 * elapsed as u128 / vesting.duration as u128)
+            .map_err(|_| ErrorCode::MathOverflow.into())
+    }
+}
@@ -0,0 +1,33 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+
+// One per maker, created on their first `make` or `make_basket`. Escrow and basket seeds are
+// both handed out from `next_seed`, so every escrow a maker ever opened lives at a seed in
+// `0..next_seed`. Only escrows count towards `open_offers`.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct MakerState {
+    pub maker: Pubkey,
+    pub next_seed: u64,
+    pub open_offers: u32,
+    pub bump: u8,
+}
+
+impl MakerState {
+    pub fn open_offer(&mut self) -> Result<u64> {
+        let seed = self.take_seed()?;
+        self.open_offers = self.open_offers.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        Ok(seed)
+    }
+
+    pub fn take_seed(&mut self) -> Result<u64> {
+        let seed = self.next_seed;
+        self.next_seed = seed.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        Ok(seed)
+    }
+
+    pub fn close_offer(&mut self) {
+        self.open_offers = self.open_offers.saturating_sub(1);
+    }
+}
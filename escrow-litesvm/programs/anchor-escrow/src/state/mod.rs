@@ -1,3 +1,15 @@
+pub mod allowlist_fill;
+pub mod basket;
+pub mod config;
+pub mod counter_offer;
 pub mod escrow;
+pub mod maker_state;
+pub mod offer_nonces;
 
+pub use allowlist_fill::*;
+pub use basket::*;
+pub use config::*;
+pub use counter_offer::*;
 pub use escrow::*;
+pub use maker_state::*;
+pub use offer_nonces::*;
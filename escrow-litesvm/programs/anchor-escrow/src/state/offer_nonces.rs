@@ -0,0 +1,36 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+
+pub const NONCES_PER_PAGE: u64 = 2048;
+
+// One page of a maker's signed-offer nonce bitmap, covering nonces
+// `page * NONCES_PER_PAGE..(page + 1) * NONCES_PER_PAGE`. A set bit is a nonce that was
+// taken or cancelled. Created by whoever first uses a nonce on the page.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct OfferNonces {
+    pub maker: Pubkey,
+    pub page: u64,
+    pub bitmap: [u8; 256],
+    pub bump: u8,
+}
+
+impl OfferNonces {
+    pub fn page(nonce: u64) -> u64 {
+        nonce / NONCES_PER_PAGE
+    }
+
+    // Marks `nonce` as used, the page may have just been created
+    pub fn consume(&mut self, maker: Pubkey, nonce: u64, bump: u8) -> Result<()> {
+        self.maker = maker;
+        self.page = Self::page(nonce);
+        self.bump = bump;
+
+        let bit = nonce % NONCES_PER_PAGE;
+        let (byte, mask) = ((bit / 8) as usize, 1u8 << (bit % 8));
+        require!(self.bitmap[byte] & mask == 0, ErrorCode::NonceAlreadyUsed);
+        self.bitmap[byte] |= mask;
+        Ok(())
+    }
+}
@@ -4,6 +4,17 @@ mod tests {
 
     use {
         crate::{instructions::refund, state::escrow},
+        anchor_escrow_client::{
+            AcceptCounter, AllowlistProof, Amend, BasketLeg, CancelOffer, Claim, Dispute,
+            DutchAuction, Expire, InitializeConfig, Make, MakeBasket, MatchEscrows,
+            OraclePricing, PostCounter, Pricing, Refund, RejectCounter, Reserve,
+            ReservationTerms, SetReservationTerms, Settle, SignedOffer, Take, TakeBasket,
+            TakeMany, TakeOffer, TakePartial, TimeWindows, TopUp, UpdateConfig, Vesting,
+            allowlist_fill_address, basket_address, config_address, counter_address,
+            escrow_address, event_authority_address, maker_state_address,
+            offer_delegate_address, offer_signature_instruction, program_data_address,
+            receipt_mint_address, vault_address,
+        },
         anchor_lang::{
             AccountDeserialize, InstructionData, ToAccountMetas, prelude::{Clock, msg}, solana_program::program_pack::Pack
         },
@@ -17,13 +28,13 @@ mod tests {
         },
         solana_account::Account,
         solana_address::Address,
-        solana_instruction::Instruction,
+        solana_instruction::{AccountMeta, Instruction},
         solana_keypair::Keypair,
         solana_message::Message,
         solana_native_token::LAMPORTS_PER_SOL,
         solana_pubkey::Pubkey,
         solana_rpc_client::rpc_client::RpcClient,
-        solana_sdk_ids::system_program::ID as SYSTEM_PROGRAM_ID,
+        solana_sdk_ids::{bpf_loader_upgradeable, system_program::ID as SYSTEM_PROGRAM_ID},
         solana_signer::Signer,
         solana_transaction::Transaction,
         std::{
@@ -34,6 +45,44 @@ mod tests {
     };
 
     static PROGRAM_ID: Pubkey = crate::ID;
+    static TREASURY: Pubkey = Pubkey::new_from_array([7; 32]);
+
+    // Deploys the program through the upgradeable loader so InitializeConfig can read its
+    // upgrade authority; the ProgramData account must exist before the Program account
+    fn deploy_upgradeable(program: &mut LiteSVM, elf: &[u8], upgrade_authority: Pubkey) {
+        let mut program_data = vec![3, 0, 0, 0];
+        program_data.extend_from_slice(&0u64.to_le_bytes());
+        program_data.push(1);
+        program_data.extend_from_slice(upgrade_authority.as_ref());
+        program_data.extend_from_slice(elf);
+        program
+            .set_account(
+                program_data_address(),
+                Account {
+                    lamports: program.minimum_balance_for_rent_exemption(program_data.len()),
+                    data: program_data,
+                    owner: bpf_loader_upgradeable::ID,
+                    executable: false,
+                    rent_epoch: 0,
+                },
+            )
+            .unwrap();
+
+        let mut program_account = vec![2, 0, 0, 0];
+        program_account.extend_from_slice(program_data_address().as_ref());
+        program
+            .set_account(
+                PROGRAM_ID,
+                Account {
+                    lamports: program.minimum_balance_for_rent_exemption(program_account.len()),
+                    data: program_account,
+                    owner: bpf_loader_upgradeable::ID,
+                    executable: true,
+                    rent_epoch: 0,
+                },
+            )
+            .unwrap();
+    }
 
     // Setup function to initialize LiteSVM and create a payer keypair
     // Also loads an account from devnet into the LiteSVM environment (for testing purposes)
@@ -53,7 +102,17 @@ mod tests {
 
         let program_data = std::fs::read(so_path).expect("Failed to read program SO file");
 
-        program.add_program(PROGRAM_ID, &program_data);
+        deploy_upgradeable(&mut program, &program_data, payer.pubkey());
+
+        // Every test runs against a config with no protocol fee unless it updates it
+        let initialize_config_ix = InitializeConfig {
+            admin: payer.pubkey(),
+            fee_bps: 0,
+            treasury: TREASURY,
+        }
+        .instruction();
+        send(&mut program, &[initialize_config_ix], &[&payer])
+            .expect("Failed to initialize config");
 
         // Example on how to Load an account from devnet
         // LiteSVM does not have access to real Solana network data since it does not have network access,
@@ -86,119 +145,250 @@ mod tests {
         (program, payer)
     }
 
-    #[test]
-    fn test_make() {
-        let (mut program, payer) = setup();
-
-        let maker = payer.pubkey();
-
-        let mint_a = CreateMint::new(&mut program, &payer)
-            .decimals(6)
-            .authority(&maker)
-            .send()
-            .unwrap();
-        msg!("Mint A: {}\n", mint_a);
+    // Creates a Token-2022 mint with a TransferFeeConfig extension, `payer` is mint authority
+    fn create_transfer_fee_mint(
+        program: &mut LiteSVM,
+        payer: &Keypair,
+        fee_basis_points: u16,
+    ) -> Pubkey {
+        let mint = Keypair::new();
+        let space = spl_token_2022::extension::ExtensionType::try_calculate_account_len::<
+            spl_token_2022::state::Mint,
+        >(&[spl_token_2022::extension::ExtensionType::TransferFeeConfig])
+        .unwrap();
+        let rent = program.minimum_balance_for_rent_exemption(space);
 
-        let mint_b = CreateMint::new(&mut program, &payer)
-            .decimals(6)
-            .authority(&maker)
-            .send()
-            .unwrap();
-        msg!("Mint B: {}\n", mint_b);
+        let instructions = [
+            solana_system_interface::instruction::create_account(
+                &payer.pubkey(),
+                &mint.pubkey(),
+                rent,
+                space as u64,
+                &spl_token_2022::ID,
+            ),
+            spl_token_2022::extension::transfer_fee::instruction::initialize_transfer_fee_config(
+                &spl_token_2022::ID,
+                &mint.pubkey(),
+                None,
+                None,
+                fee_basis_points,
+                u64::MAX,
+            )
+            .unwrap(),
+            spl_token_2022::instruction::initialize_mint2(
+                &spl_token_2022::ID,
+                &mint.pubkey(),
+                &payer.pubkey(),
+                None,
+                6,
+            )
+            .unwrap(),
+        ];
+        let message = Message::new(&instructions, Some(&payer.pubkey()));
+        let recent_blockhash = program.latest_blockhash();
+        let transaction = Transaction::new(&[payer, &mint], message, recent_blockhash);
+        program.send_transaction(transaction).unwrap();
 
-        let maker_ata_a = CreateAssociatedTokenAccount::new(&mut program, &payer, &mint_a)
-            .owner(&maker)
-            .send()
-            .unwrap();
-        msg!("Maker ATA A: {}\n", maker_ata_a);
+        mint.pubkey()
+    }
 
-        let escrow = Pubkey::find_program_address(
-            &[b"escrow", maker.as_ref(), &123u64.to_le_bytes()],
-            &PROGRAM_ID,
+    fn token_2022_amount(program: &LiteSVM, token_account: &Pubkey) -> u64 {
+        let account = program.get_account(token_account).unwrap();
+        spl_token_2022::extension::StateWithExtensions::<spl_token_2022::state::Account>::unpack(
+            &account.data,
         )
-        .0;
-        msg!("Escrow PDA: {}\n", escrow);
+        .unwrap()
+        .base
+        .amount
+    }
 
-        let vault = associated_token::get_associated_token_address(&escrow, &mint_a);
-        msg!("Vault PDA: {}\n", vault);
+    // Creates a Token-2022 mint whose transfers are hooked by the `test-transfer-hook` program,
+    // and initializes the hook's ExtraAccountMetaList for it
+    fn create_transfer_hook_mint(program: &mut LiteSVM, payer: &Keypair) -> Pubkey {
+        let hook_data = std::fs::read(
+            PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                .join("../../target/deploy/test_transfer_hook.so"),
+        )
+        .expect("Failed to read transfer hook SO file");
+        program.add_program(test_transfer_hook::ID, &hook_data);
 
-        let associated_token_program = spl_associated_token_account::ID;
-        let token_program = TOKEN_PROGRAM_ID;
-        let system_program = SYSTEM_PROGRAM_ID;
+        let mint = Keypair::new();
+        let space = spl_token_2022::extension::ExtensionType::try_calculate_account_len::<
+            spl_token_2022::state::Mint,
+        >(&[spl_token_2022::extension::ExtensionType::TransferHook])
+        .unwrap();
+        let rent = program.minimum_balance_for_rent_exemption(space);
 
-        MintTo::new(&mut program, &payer, &mint_a, &maker_ata_a, 10 * 1000000)
-            .send()
-            .unwrap();
+        let instructions = [
+            solana_system_interface::instruction::create_account(
+                &payer.pubkey(),
+                &mint.pubkey(),
+                rent,
+                space as u64,
+                &spl_token_2022::ID,
+            ),
+            spl_token_2022::extension::transfer_hook::instruction::initialize(
+                &spl_token_2022::ID,
+                &mint.pubkey(),
+                None,
+                Some(test_transfer_hook::ID),
+            )
+            .unwrap(),
+            spl_token_2022::instruction::initialize_mint2(
+                &spl_token_2022::ID,
+                &mint.pubkey(),
+                &payer.pubkey(),
+                None,
+                6,
+            )
+            .unwrap(),
+        ];
+        let message = Message::new(&instructions, Some(&payer.pubkey()));
+        let recent_blockhash = program.latest_blockhash();
+        let transaction = Transaction::new(&[payer, &mint], message, recent_blockhash);
+        program.send_transaction(transaction).unwrap();
 
-        // Create the "Make" instruction to deposit tokens into the escrow
-        let make_ix = Instruction {
-            program_id: PROGRAM_ID,
-            accounts: crate::accounts::Make {
-                maker: maker,
-                mint_a: mint_a,
-                mint_b: mint_b,
-                maker_ata_a: maker_ata_a,
-                escrow: escrow,
-                vault: vault,
-                associated_token_program: associated_token_program,
-                token_program: token_program,
-                system_program: system_program,
+        let (extra_account_meta_list, counter) = transfer_hook_accounts(&mint.pubkey());
+        let init_hook_ix = Instruction {
+            program_id: test_transfer_hook::ID,
+            accounts: test_transfer_hook::accounts::InitializeExtraAccountMetaList {
+                payer: payer.pubkey(),
+                extra_account_meta_list,
+                counter,
+                mint: mint.pubkey(),
+                system_program: SYSTEM_PROGRAM_ID,
             }
             .to_account_metas(None),
-            data: crate::instruction::Make {
-                deposit: 10 * 1000000,
-                seed: 123u64,
-                receive: 10 * 1000000,
-            }
-            .data(),
+            data: test_transfer_hook::instruction::InitializeExtraAccountMetaList {}.data(),
         };
-
-        let message = Message::new(&[make_ix], Some(&payer.pubkey()));
+        let message = Message::new(&[init_hook_ix], Some(&payer.pubkey()));
         let recent_blockhash = program.latest_blockhash();
+        let transaction = Transaction::new(&[payer], message, recent_blockhash);
+        program.send_transaction(transaction).unwrap();
 
-        let transaction = Transaction::new(&[&payer], message, recent_blockhash);
+        mint.pubkey()
+    }
 
-        let tx = program.send_transaction(transaction).unwrap();
+    // The hook's validation account and the counter PDA it lists as an extra account
+    fn transfer_hook_accounts(mint: &Pubkey) -> (Pubkey, Pubkey) {
+        let extra_account_meta_list = Pubkey::find_program_address(
+            &[b"extra-account-metas", mint.as_ref()],
+            &test_transfer_hook::ID,
+        )
+        .0;
+        let counter =
+            Pubkey::find_program_address(&[b"counter", mint.as_ref()], &test_transfer_hook::ID).0;
+        (extra_account_meta_list, counter)
+    }
 
-        msg!("\n\nMake transaction sucessfull");
-        msg!("CUs Consumed: {}", tx.compute_units_consumed);
-        msg!("Tx Signature: {}", tx.signature);
+    // Writes a fully verified Pyth `PriceUpdateV2` account, as the receiver program would
+    fn set_price_update(
+        program: &mut LiteSVM,
+        address: Pubkey,
+        feed_id: [u8; 32],
+        price: i64,
+        conf: u64,
+        publish_time: i64,
+    ) {
+        let update = crate::oracle::PriceUpdateV2 {
+            write_authority: Pubkey::new_unique(),
+            verification_level: crate::oracle::VerificationLevel::Full,
+            price_message: crate::oracle::PriceFeedMessage {
+                feed_id,
+                price,
+                conf,
+                exponent: -8,
+                publish_time,
+                prev_publish_time: publish_time - 1,
+                ema_price: price,
+                ema_conf: conf,
+            },
+            posted_slot: 0,
+        };
+        let mut data = crate::oracle::PRICE_UPDATE_V2_DISCRIMINATOR.to_vec();
+        anchor_lang::AnchorSerialize::serialize(&update, &mut data).unwrap();
 
-        let vault_account = program.get_account(&vault).unwrap();
-        let vault_data = spl_token::state::Account::unpack(&vault_account.data).unwrap();
-        assert_eq!(vault_data.amount, 10 * 1000000);
-        assert_eq!(vault_data.owner, escrow);
-        assert_eq!(vault_data.mint, mint_a);
+        program
+            .set_account(
+                address,
+                Account {
+                    lamports: LAMPORTS_PER_SOL,
+                    data,
+                    owner: crate::oracle::PYTH_RECEIVER_ID,
+                    executable: false,
+                    rent_epoch: 0,
+                },
+            )
+            .unwrap();
+    }
 
-        let escrow_account = program.get_account(&escrow).unwrap();
-        let escrow_data =
-            crate::state::Escrow::try_deserialize(&mut escrow_account.data.as_ref()).unwrap();
-        assert_eq!(escrow_data.seed, 123u64);
-        assert_eq!(escrow_data.maker, maker);
-        assert_eq!(escrow_data.mint_a, mint_a);
-        assert_eq!(escrow_data.mint_b, mint_b);
-        assert_eq!(escrow_data.receive, 10 * 1000000);
+    // Checks a transaction failed on the program's own `code`, not just any error
+    fn assert_program_error(
+        result: litesvm::types::TransactionResult,
+        code: crate::error::ErrorCode,
+    ) {
+        let failed = result.expect_err("transaction should have failed");
+        let expected = format!("Error Code: {}.", code.name());
+        assert!(
+            failed.meta.logs.iter().any(|log| log.contains(&expected)),
+            "expected {} in {:#?}",
+            expected,
+            failed.meta.logs
+        );
     }
 
-    #[test]
-    fn test_take() {
+
+    // Sends `instructions` in one transaction, the first signer pays the fee
+    fn send(
+        program: &mut LiteSVM,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> litesvm::types::TransactionResult {
+        let message = Message::new(instructions, Some(&signers[0].pubkey()));
+        let recent_blockhash = program.latest_blockhash();
+        let transaction = Transaction::new(signers, message, recent_blockhash);
+        program.send_transaction(transaction)
+    }
+
+    // The account keys `send` compiles `instructions` to, which `decode_events` resolves against
+    fn account_keys(instructions: &[Instruction], payer: &Keypair) -> Vec<Pubkey> {
+        Message::new(instructions, Some(&payer.pubkey())).account_keys
+    }
+
+    // What most tests start from: `payer` is the config admin and the maker, and the
+    // authority of two legacy SPL mints. Both parties have an ATA for each mint, the maker
+    // holds 10 A and the funded `taker` holds 10 B.
+    struct Fixture {
+        program: LiteSVM,
+        payer: Keypair,
+        taker: Keypair,
+        mint_a: Pubkey,
+        mint_b: Pubkey,
+        maker_ata_a: Pubkey,
+        maker_ata_b: Pubkey,
+        taker_ata_a: Pubkey,
+        taker_ata_b: Pubkey,
+    }
+
+    fn fixture() -> Fixture {
         let (mut program, payer) = setup();
         let maker = payer.pubkey();
         let taker = Keypair::new();
-        // program.airdrop(&payer.pubkey(), 10 * LAMPORTS_PER_SOL).expect("failed airdrop at maker " ) ;
         program
             .airdrop(&taker.pubkey(), 10 * LAMPORTS_PER_SOL)
             .expect("failed airdrop at taker ");
+
         let mint_a = CreateMint::new(&mut program, &payer)
             .authority(&maker)
             .decimals(6)
             .send()
             .unwrap();
-        let mint_b = CreateMint::new(&mut program, &taker)
-            .authority(&taker.pubkey())
+        let mint_b = CreateMint::new(&mut program, &payer)
+            .authority(&maker)
             .decimals(6)
             .send()
             .unwrap();
+
         let maker_ata_a = CreateAssociatedTokenAccount::new(&mut program, &payer, &mint_a)
             .owner(&maker)
             .send()
@@ -216,52 +406,138 @@ mod tests {
             .send()
             .unwrap();
 
-        let escrow = Pubkey::find_program_address(
-            &[b"escrow", maker.as_ref(), &123u64.to_le_bytes()],
-            &PROGRAM_ID,
-        )
-        .0;
-        msg!("Escrow PDA: {}\n", escrow);
-
-        let vault = associated_token::get_associated_token_address(&escrow, &mint_a);
-        msg!("Vault PDA: {}\n", vault);
-
-        let associated_token_program = spl_associated_token_account::ID;
-        let token_program = TOKEN_PROGRAM_ID;
-        let system_program = SYSTEM_PROGRAM_ID;
-
         MintTo::new(&mut program, &payer, &mint_a, &maker_ata_a, 10 * 1000000)
             .send()
             .unwrap();
-        MintTo::new(&mut program, &taker, &mint_b, &taker_ata_b, 10 * 1000000)
+        MintTo::new(&mut program, &payer, &mint_b, &taker_ata_b, 10 * 1000000)
             .send()
             .unwrap();
 
-        let make_ix = Instruction {
-            program_id: PROGRAM_ID,
-            accounts: crate::accounts::Make {
-                maker,
-                mint_a,
-                mint_b,
-                maker_ata_a,
-                escrow,
-                vault,
-                associated_token_program,
-                token_program,
-                system_program,
-            }
-            .to_account_metas(None),
-            data: crate::instruction::Make {
-                seed: 123u64,
-                deposit: 10 * 1000000,
-                receive: 10 * 1000000,
-            }
-            .data(),
-        };
-        let message = Message::new(&[make_ix], Some(&payer.pubkey()));
-        let recent_blockhash = program.latest_blockhash();
-        let transaction1 = Transaction::new(&[&payer], message, recent_blockhash);
-        let make_tx = program.send_transaction(transaction1).unwrap();
+        Fixture {
+            program,
+            payer,
+            taker,
+            mint_a,
+            mint_b,
+            maker_ata_a,
+            maker_ata_b,
+            taker_ata_a,
+            taker_ata_b,
+        }
+    }
+
+    // A fixed-price escrow of 10 A for 10 B, open to anyone from now on.
+    // Tests override the fields they care about with struct update syntax.
+    fn make(maker: Pubkey, seed: u64, mint_a: Pubkey, mint_b: Pubkey) -> Make {
+        Make {
+            maker,
+            seed,
+            mint_a: Some(mint_a),
+            mint_b: Some(mint_b),
+            deposit: 10 * 1000000,
+            receive: 10 * 1000000,
+            windows: TimeWindows {
+                take_open_time: 0,
+                expiry_time: i64::MAX,
+                refund_lock_time: 0,
+            },
+            allowed_taker: None,
+            allowlist_root: None,
+            receive_net_of_fee: false,
+            pricing: Pricing::Fixed,
+            vesting: None,
+            arbiter: None,
+            receipt: false,
+            token_program: TOKEN_PROGRAM_ID,
+        }
+    }
+
+    // Takes the `make` escrow at `seed` in full, paying out to the maker's ATA
+    fn take(taker: Pubkey, maker: Pubkey, seed: u64, mint_a: Pubkey, mint_b: Pubkey) -> Take {
+        Take {
+            taker,
+            maker,
+            seed,
+            mint_a: Some(mint_a),
+            mint_b: Some(mint_b),
+            treasury: TREASURY,
+            expected_version: 0,
+            max_payment: u64::MAX,
+            allowlist: None,
+            price_update: None,
+            arbitrated: false,
+            receipt_holder: None,
+            proceeds_to: None,
+            token_program: TOKEN_PROGRAM_ID,
+        }
+    }
+
+    #[test]
+    fn test_make() {
+        let Fixture { mut program, payer, mint_a, mint_b, .. } = fixture();
+        let maker = payer.pubkey();
+
+        let escrow = escrow_address(&maker, 0);
+        msg!("Escrow PDA: {}\n", escrow);
+
+        let vault = vault_address(&escrow, &mint_a, &TOKEN_PROGRAM_ID);
+        msg!("Vault PDA: {}\n", vault);
+
+        // Create the "Make" instruction to deposit tokens into the escrow
+        let make_ix = make(maker, 0, mint_a, mint_b).instruction();
+        let tx = send(&mut program, &[make_ix], &[&payer]).unwrap();
+
+        msg!("\n\nMake transaction sucessfull");
+        msg!("CUs Consumed: {}", tx.compute_units_consumed);
+        msg!("Tx Signature: {}", tx.signature);
+
+        let vault_account = program.get_account(&vault).unwrap();
+        let vault_data = spl_token::state::Account::unpack(&vault_account.data).unwrap();
+        assert_eq!(vault_data.amount, 10 * 1000000);
+        assert_eq!(vault_data.owner, escrow);
+        assert_eq!(vault_data.mint, mint_a);
+
+        let escrow_account = program.get_account(&escrow).unwrap();
+        let escrow_data =
+            crate::state::Escrow::try_deserialize(&mut escrow_account.data.as_ref()).unwrap();
+        assert_eq!(escrow_data.seed, 0u64);
+        assert_eq!(escrow_data.maker, maker);
+        assert_eq!(escrow_data.mint_a, mint_a);
+        assert_eq!(escrow_data.mint_b, mint_b);
+        assert_eq!(escrow_data.receive, 10 * 1000000);
+    }
+
+    #[test]
+    fn test_take() {
+        let Fixture {
+            mut program,
+            payer,
+            taker,
+            mint_a,
+            mint_b,
+            taker_ata_a,
+            taker_ata_b,
+            ..
+        } = fixture();
+        let maker = payer.pubkey();
+
+        let escrow = escrow_address(&maker, 0);
+        msg!("Escrow PDA: {}\n", escrow);
+
+        let vault = vault_address(&escrow, &mint_a, &TOKEN_PROGRAM_ID);
+        msg!("Vault PDA: {}\n", vault);
+
+        let make_ix = Make {
+            windows: TimeWindows {
+                take_open_time: program.get_sysvar::<Clock>().unix_timestamp + 60 * 60 * 24 * 5,
+                expiry_time: i64::MAX,
+                refund_lock_time: 0,
+            },
+            ..make(maker, 0, mint_a, mint_b)
+        }
+        .instruction();
+        let make_keys = account_keys(&[make_ix.clone()], &payer);
+        let make_tx = send(&mut program, &[make_ix], &[&payer]).unwrap();
         msg!("\n\nMake transaction sucessfull");
         msg!("CUs Consumed: {}", make_tx.compute_units_consumed);
         msg!("make_tx Signature: {}", make_tx.signature);
@@ -273,40 +549,25 @@ mod tests {
         let escrow_account = program.get_account(&escrow).unwrap();
         let escrow_data =
             crate::state::Escrow::try_deserialize(&mut escrow_account.data.as_ref()).unwrap();
-        assert_eq!(escrow_data.seed, 123u64);
+        assert_eq!(escrow_data.seed, 0u64);
         assert_eq!(escrow_data.maker, maker);
         assert_eq!(escrow_data.mint_a, mint_a);
         assert_eq!(escrow_data.mint_b, mint_b);
         assert_eq!(escrow_data.receive, 10 * 1000000);
 
+        let take_ix = take(taker.pubkey(), maker, 0, mint_a, mint_b).instruction();
+
+        // Taking before the window opens is rejected
+        assert!(send(&mut program, &[take_ix.clone()], &[&taker]).is_err());
+
         let time_5_days : i64 = 60 * 60 * 24 * 5 ;
         let mut initial_clock = program.get_sysvar::<Clock>();
         initial_clock.unix_timestamp += time_5_days + 1 ;  // + 1 second 
         program.set_sysvar::<Clock>(&initial_clock);
-        
-        let take_ix = Instruction {
-            program_id: PROGRAM_ID,
-            accounts: crate::accounts::Take {
-                taker: taker.pubkey(),
-                maker,
-                mint_a,
-                mint_b,
-                taker_ata_a,
-                taker_ata_b,
-                maker_ata_b,
-                escrow,
-                vault,
-                associated_token_program,
-                token_program,
-                system_program,
-            }
-            .to_account_metas(None),
-            data: crate::instruction::Take {}.data(),
-        };
-        let message = Message::new(&[take_ix], Some(&taker.pubkey()));
-        let block_hash = program.latest_blockhash();
-        let transaction2 = Transaction::new(&[&taker], message, block_hash);
-        let take_tx = program.send_transaction(transaction2).unwrap();
+        program.expire_blockhash();
+
+        let take_keys = account_keys(&[take_ix.clone()], &taker);
+        let take_tx = send(&mut program, &[take_ix], &[&taker]).unwrap();
         msg!("\n\nTake transaction sucessfull");
         msg!("Cu consumed , {}", take_tx.compute_units_consumed);
         let taker_ata_a_data = program.get_account(&taker_ata_a).unwrap();
@@ -317,81 +578,44 @@ mod tests {
         let taker_ata_b_metadata =
             spl_token::state::Account::unpack(&taker_ata_b_data.data).unwrap();
         assert_eq!(taker_ata_b_metadata.amount, 0);
+
+        // Both transitions are recoverable from the transaction metadata alone
+        let made_events = crate::events::decode_events(&make_keys, &make_tx.inner_instructions);
+        let [crate::events::EscrowEvent::Made(made)] = made_events.as_slice() else {
+            panic!("expected a single EscrowMade event, got {:?}", made_events);
+        };
+        assert_eq!(made.escrow, escrow);
+        assert_eq!(made.maker, maker);
+        assert_eq!(made.mint_a, mint_a);
+        assert_eq!(made.deposit, 10 * 1000000);
+
+        let taken_events = crate::events::decode_events(&take_keys, &take_tx.inner_instructions);
+        let [crate::events::EscrowEvent::Taken(taken)] = taken_events.as_slice() else {
+            panic!("expected a single EscrowTaken event, got {:?}", taken_events);
+        };
+        assert_eq!(taken.escrow, escrow);
+        assert_eq!(taken.taker, taker.pubkey());
+        assert_eq!(taken.amount_a, 10 * 1000000);
+        assert_eq!(taken.amount_b, made.receive);
+        assert_eq!(taken.fee, 0);
+        assert_eq!(taken.remaining_deposit, 0);
     }
 
+
     #[test]
     fn test_refund() {
-        let (mut program, payer) = setup();
-
+        let Fixture { mut program, payer, mint_a, mint_b, maker_ata_a, .. } = fixture();
         let maker = payer.pubkey();
 
-        let mint_a = CreateMint::new(&mut program, &payer)
-            .decimals(6)
-            .authority(&maker)
-            .send()
-            .unwrap();
-        msg!("Mint A: {}\n", mint_a);
-
-        let mint_b = CreateMint::new(&mut program, &payer)
-            .decimals(6)
-            .authority(&maker)
-            .send()
-            .unwrap();
-        msg!("Mint B: {}\n", mint_b);
-
-        let maker_ata_a = CreateAssociatedTokenAccount::new(&mut program, &payer, &mint_a)
-            .owner(&maker)
-            .send()
-            .unwrap();
-        msg!("Maker ATA A: {}\n", maker_ata_a);
-
-        let escrow = Pubkey::find_program_address(
-            &[b"escrow", maker.as_ref(), &123u64.to_le_bytes()],
-            &PROGRAM_ID,
-        )
-        .0;
+        let escrow = escrow_address(&maker, 0);
         msg!("Escrow PDA: {}\n", escrow);
 
-        let vault = associated_token::get_associated_token_address(&escrow, &mint_a);
+        let vault = vault_address(&escrow, &mint_a, &TOKEN_PROGRAM_ID);
         msg!("Vault PDA: {}\n", vault);
 
-        let associated_token_program = spl_associated_token_account::ID;
-        let token_program = TOKEN_PROGRAM_ID;
-        let system_program = SYSTEM_PROGRAM_ID;
-
-        MintTo::new(&mut program, &payer, &mint_a, &maker_ata_a, 10 * 1000000)
-            .send()
-            .unwrap();
-
         // Create the "Make" instruction to deposit tokens into the escrow
-        let make_ix = Instruction {
-            program_id: PROGRAM_ID,
-            accounts: crate::accounts::Make {
-                maker: maker,
-                mint_a: mint_a,
-                mint_b: mint_b,
-                maker_ata_a: maker_ata_a,
-                escrow: escrow,
-                vault: vault,
-                associated_token_program: associated_token_program,
-                token_program: token_program,
-                system_program: system_program,
-            }
-            .to_account_metas(None),
-            data: crate::instruction::Make {
-                deposit: 10 * 1000000,
-                seed: 123u64,
-                receive: 10 * 1000000,
-            }
-            .data(),
-        };
-
-        let message = Message::new(&[make_ix], Some(&payer.pubkey()));
-        let recent_blockhash = program.latest_blockhash();
-
-        let transaction = Transaction::new(&[&payer], message, recent_blockhash);
-
-        let tx = program.send_transaction(transaction).unwrap();
+        let make_ix = make(maker, 0, mint_a, mint_b).instruction();
+        let tx = send(&mut program, &[make_ix], &[&payer]).unwrap();
 
         msg!("\n\nMake transaction sucessfull");
         msg!("CUs Consumed: {}", tx.compute_units_consumed);
@@ -406,30 +630,23 @@ mod tests {
         let escrow_account = program.get_account(&escrow).unwrap();
         let escrow_data =
             crate::state::Escrow::try_deserialize(&mut escrow_account.data.as_ref()).unwrap();
-        assert_eq!(escrow_data.seed, 123u64);
+        assert_eq!(escrow_data.seed, 0u64);
         assert_eq!(escrow_data.maker, maker);
         assert_eq!(escrow_data.mint_a, mint_a);
         assert_eq!(escrow_data.mint_b, mint_b);
         assert_eq!(escrow_data.receive, 10 * 1000000);
 
-        let refund_ix = Instruction {
-            program_id: PROGRAM_ID,
-            accounts: crate::accounts::Refund {
-                maker,
-                mint_a,
-                maker_ata_a,
-                escrow,
-                vault,
-                token_program,
-                system_program,
-            }
-            .to_account_metas(None),
-            data: crate::instruction::Refund {}.data(),
-        };
-        let message = Message::new(&[refund_ix], Some(&maker));
-        let recent_blockhash = program.latest_blockhash();
-        let transaction2 = Transaction::new(&[&payer], message, recent_blockhash);
-        let refund_tx = program.send_transaction(transaction2).unwrap();
+        let refund_ix = Refund {
+            maker,
+            seed: 0,
+            mint_a: Some(mint_a),
+            receipt_holder: None,
+            refund_to: None,
+            reserver: None,
+            token_program: TOKEN_PROGRAM_ID,
+        }
+        .instruction();
+        let refund_tx = send(&mut program, &[refund_ix], &[&payer]).unwrap();
         msg!("\n\nRefund transaction sucessfull");
         msg!("CUs Consumed: {}", refund_tx.compute_units_consumed);
         msg!("Tx Signature: {}", refund_tx.signature);
@@ -445,4 +662,2261 @@ mod tests {
         let escrow_account = program.get_account(&escrow).unwrap();
         assert!(escrow_account.data.is_empty(), "Escrow should be closed");
     }
+
+    #[test]
+    fn test_take_partial() {
+        let Fixture {
+            mut program,
+            payer,
+            taker,
+            mint_a,
+            mint_b,
+            maker_ata_b,
+            taker_ata_a,
+            taker_ata_b,
+            ..
+        } = fixture();
+        let maker = payer.pubkey();
+
+        let escrow = escrow_address(&maker, 0);
+        let vault = vault_address(&escrow, &mint_a, &TOKEN_PROGRAM_ID);
+
+        MintTo::new(&mut program, &payer, &mint_b, &taker_ata_b, 10 * 1000000)
+            .send()
+            .unwrap();
+
+        // Offer 10 A for 20 B
+        let make_ix = Make {
+            receive: 20 * 1000000,
+            windows: TimeWindows {
+                take_open_time: program.get_sysvar::<Clock>().unix_timestamp + 60 * 60 * 24 * 5,
+                expiry_time: i64::MAX,
+                refund_lock_time: 0,
+            },
+            ..make(maker, 0, mint_a, mint_b)
+        }
+        .instruction();
+        send(&mut program, &[make_ix], &[&payer]).unwrap();
+
+        let time_5_days: i64 = 60 * 60 * 24 * 5;
+        let mut initial_clock = program.get_sysvar::<Clock>();
+        initial_clock.unix_timestamp += time_5_days + 1;
+        program.set_sysvar::<Clock>(&initial_clock);
+
+        let take_partial_ix = |amount_b: u64| {
+            TakePartial {
+                taker: taker.pubkey(),
+                maker,
+                seed: 0,
+                mint_a: Some(mint_a),
+                mint_b: Some(mint_b),
+                treasury: TREASURY,
+                amount_b,
+                expected_version: 0,
+                allowlist: None,
+                receipt_holder: None,
+                proceeds_to: None,
+                token_program: TOKEN_PROGRAM_ID,
+            }
+            .instruction()
+        };
+
+        // First fill: 5 B releases a quarter of the vault
+        let take_tx = send(&mut program, &[take_partial_ix(5 * 1000000)], &[&taker]).unwrap();
+        msg!("\n\nPartial take transaction sucessfull");
+        msg!("Cu consumed , {}", take_tx.compute_units_consumed);
+
+        let taker_ata_a_data = program.get_account(&taker_ata_a).unwrap();
+        let taker_ata_a_metadata =
+            spl_token::state::Account::unpack(&taker_ata_a_data.data).unwrap();
+        assert_eq!(taker_ata_a_metadata.amount, 2_500_000);
+
+        let escrow_account = program.get_account(&escrow).unwrap();
+        let escrow_data =
+            crate::state::Escrow::try_deserialize(&mut escrow_account.data.as_ref()).unwrap();
+        assert_eq!(escrow_data.deposit, 7_500_000);
+        assert_eq!(escrow_data.receive, 15 * 1000000);
+
+        // Overfilling the remainder is rejected
+        program.expire_blockhash();
+        assert!(send(&mut program, &[take_partial_ix(16 * 1000000)], &[&taker]).is_err());
+
+        // Second fill takes the rest and closes everything
+        send(&mut program, &[take_partial_ix(15 * 1000000)], &[&taker]).unwrap();
+
+        let taker_ata_a_data = program.get_account(&taker_ata_a).unwrap();
+        let taker_ata_a_metadata =
+            spl_token::state::Account::unpack(&taker_ata_a_data.data).unwrap();
+        assert_eq!(taker_ata_a_metadata.amount, 10 * 1000000);
+
+        let maker_ata_b_data = program.get_account(&maker_ata_b).unwrap();
+        let maker_ata_b_metadata =
+            spl_token::state::Account::unpack(&maker_ata_b_data.data).unwrap();
+        assert_eq!(maker_ata_b_metadata.amount, 20 * 1000000);
+
+        let vault_account = program.get_account(&vault).unwrap();
+        assert!(vault_account.data.is_empty(), "Vault should be closed");
+
+        let escrow_account = program.get_account(&escrow).unwrap();
+        assert!(escrow_account.data.is_empty(), "Escrow should be closed");
+    }
+
+    #[test]
+    fn test_expire() {
+        let Fixture { mut program, payer, mint_a, mint_b, maker_ata_a, .. } = fixture();
+        let maker = payer.pubkey();
+        let cranker = Keypair::new();
+        program
+            .airdrop(&cranker.pubkey(), LAMPORTS_PER_SOL)
+            .expect("failed airdrop at cranker ");
+
+        let escrow = escrow_address(&maker, 0);
+        let vault = vault_address(&escrow, &mint_a, &TOKEN_PROGRAM_ID);
+
+        let time_1_day: i64 = 60 * 60 * 24;
+        let make_ix = Make {
+            windows: TimeWindows {
+                take_open_time: 0,
+                expiry_time: program.get_sysvar::<Clock>().unix_timestamp + time_1_day,
+                refund_lock_time: i64::MAX,
+            },
+            ..make(maker, 0, mint_a, mint_b)
+        }
+        .instruction();
+        send(&mut program, &[make_ix], &[&payer]).unwrap();
+
+        let expire_ix = Expire {
+            maker,
+            seed: 0,
+            mint_a: Some(mint_a),
+            receipt_holder: None,
+            refund_to: None,
+            token_program: TOKEN_PROGRAM_ID,
+        }
+        .instruction();
+
+        // Cranking before expiry is rejected
+        assert!(send(&mut program, &[expire_ix.clone()], &[&cranker]).is_err());
+
+        let mut clock = program.get_sysvar::<Clock>();
+        clock.unix_timestamp += time_1_day;
+        program.set_sysvar::<Clock>(&clock);
+        program.expire_blockhash();
+
+        // Any third party can crank once expired, funds and rent go back to the maker
+        let expire_tx = send(&mut program, &[expire_ix], &[&cranker]).unwrap();
+        msg!("\n\nExpire transaction sucessfull");
+        msg!("CUs Consumed: {}", expire_tx.compute_units_consumed);
+
+        let maker_ata_a_account = program.get_account(&maker_ata_a).unwrap();
+        let maker_ata_a_metadata =
+            spl_token::state::Account::unpack(&maker_ata_a_account.data).unwrap();
+        assert_eq!(maker_ata_a_metadata.amount, 10 * 1000000);
+
+        let vault_account = program.get_account(&vault).unwrap();
+        assert!(vault_account.data.is_empty(), "Vault should be closed");
+
+        let escrow_account = program.get_account(&escrow).unwrap();
+        assert!(escrow_account.data.is_empty(), "Escrow should be closed");
+    }
+
+    #[test]
+    fn test_take_private_escrow() {
+        let Fixture { mut program, payer, taker, mint_a, mint_b, .. } = fixture();
+        let maker = payer.pubkey();
+        let outsider = Keypair::new();
+        program
+            .airdrop(&outsider.pubkey(), 10 * LAMPORTS_PER_SOL)
+            .expect("failed airdrop at outsider ");
+
+        let escrow = escrow_address(&maker, 0);
+
+        let make_ix = Make {
+            allowed_taker: Some(taker.pubkey()),
+            ..make(maker, 0, mint_a, mint_b)
+        }
+        .instruction();
+        send(&mut program, &[make_ix], &[&payer]).unwrap();
+
+        let escrow_account = program.get_account(&escrow).unwrap();
+        let escrow_data =
+            crate::state::Escrow::try_deserialize(&mut escrow_account.data.as_ref()).unwrap();
+        assert_eq!(escrow_data.allowed_taker, Some(taker.pubkey()));
+
+        let outsider_ata_b = CreateAssociatedTokenAccount::new(&mut program, &outsider, &mint_b)
+            .owner(&outsider.pubkey())
+            .send()
+            .unwrap();
+        MintTo::new(&mut program, &payer, &mint_b, &outsider_ata_b, 10 * 1000000)
+            .send()
+            .unwrap();
+
+        // Anyone other than the designated taker is rejected
+        let take_ix = take(outsider.pubkey(), maker, 0, mint_a, mint_b).instruction();
+        assert!(send(&mut program, &[take_ix], &[&outsider]).is_err());
+
+        let take_ix = take(taker.pubkey(), maker, 0, mint_a, mint_b).instruction();
+        send(&mut program, &[take_ix], &[&taker]).unwrap();
+
+        let taker_ata_a =
+            associated_token::get_associated_token_address(&taker.pubkey(), &mint_a);
+        let taker_ata_a_data = program.get_account(&taker_ata_a).unwrap();
+        let taker_ata_a_metadata =
+            spl_token::state::Account::unpack(&taker_ata_a_data.data).unwrap();
+        assert_eq!(taker_ata_a_metadata.amount, 10 * 1000000);
+    }
+
+    #[test]
+    fn test_take_transfer_fee_mints() {
+        let (mut program, payer) = setup();
+        let maker = payer.pubkey();
+        let taker = Keypair::new();
+        program
+            .airdrop(&taker.pubkey(), 10 * LAMPORTS_PER_SOL)
+            .expect("failed airdrop at taker ");
+
+        // Both legs charge a 1% transfer fee
+        let mint_a = create_transfer_fee_mint(&mut program, &payer, 100);
+        let mint_b = create_transfer_fee_mint(&mut program, &payer, 100);
+        let token_program = spl_token_2022::ID;
+
+        let maker_ata_a = CreateAssociatedTokenAccount::new(&mut program, &payer, &mint_a)
+            .owner(&maker)
+            .token_program_id(&token_program)
+            .send()
+            .unwrap();
+        let taker_ata_b = CreateAssociatedTokenAccount::new(&mut program, &taker, &mint_b)
+            .owner(&taker.pubkey())
+            .token_program_id(&token_program)
+            .send()
+            .unwrap();
+        MintTo::new(&mut program, &payer, &mint_a, &maker_ata_a, 10 * 1000000)
+            .token_program_id(&token_program)
+            .send()
+            .unwrap();
+        MintTo::new(&mut program, &payer, &mint_b, &taker_ata_b, 10 * 1000000)
+            .token_program_id(&token_program)
+            .send()
+            .unwrap();
+
+        let escrow = escrow_address(&maker, 0);
+        let vault = vault_address(&escrow, &mint_a, &token_program);
+        let taker_ata_a = associated_token::get_associated_token_address_with_program_id(
+            &taker.pubkey(),
+            &mint_a,
+            &token_program,
+        );
+        let maker_ata_b = associated_token::get_associated_token_address_with_program_id(
+            &maker,
+            &mint_b,
+            &token_program,
+        );
+
+        let make_ix = Make {
+            receive: 5 * 1000000,
+            receive_net_of_fee: true,
+            token_program,
+            ..make(maker, 0, mint_a, mint_b)
+        }
+        .instruction();
+        send(&mut program, &[make_ix], &[&payer]).unwrap();
+
+        // The escrow records what reached the vault, not the nominal deposit
+        assert_eq!(token_2022_amount(&program, &vault), 9_900_000);
+        let escrow_account = program.get_account(&escrow).unwrap();
+        let escrow_data =
+            crate::state::Escrow::try_deserialize(&mut escrow_account.data.as_ref()).unwrap();
+        assert_eq!(escrow_data.deposit, 9_900_000);
+        assert!(escrow_data.receive_net_of_fee);
+
+        let take_ix = |max_payment: u64| {
+            Take {
+                max_payment,
+                token_program,
+                ..take(taker.pubkey(), maker, 0, mint_a, mint_b)
+            }
+            .instruction()
+        };
+        // Capping the payment at the price fails, since the taker is debited the grossed-up amount
+        assert_program_error(
+            send(&mut program, &[take_ix(5 * 1000000)], &[&taker]),
+            crate::error::ErrorCode::SlippageExceeded,
+        );
+
+        let take_tx = send(&mut program, &[take_ix(u64::MAX)], &[&taker]).unwrap();
+        msg!("\n\nTake transaction sucessfull");
+        msg!("Cu consumed , {}", take_tx.compute_units_consumed);
+
+        // The taker's transfer was grossed up so the maker nets exactly `receive`
+        assert_eq!(token_2022_amount(&program, &maker_ata_b), 5 * 1000000);
+        assert!(token_2022_amount(&program, &taker_ata_b) < 5 * 1000000);
+        assert_eq!(token_2022_amount(&program, &taker_ata_a), 9_801_000);
+    }
+
+    #[test]
+    fn test_take_transfer_hook_mint() {
+        let (mut program, payer) = setup();
+        let maker = payer.pubkey();
+        let taker = Keypair::new();
+        program
+            .airdrop(&taker.pubkey(), 10 * LAMPORTS_PER_SOL)
+            .expect("failed airdrop at taker ");
+
+        // mint_a runs the test hook on every transfer, mint_b is a plain Token-2022 mint
+        let mint_a = create_transfer_hook_mint(&mut program, &payer);
+        let token_program = spl_token_2022::ID;
+        let mint_b = CreateMint::new(&mut program, &payer)
+            .authority(&maker)
+            .decimals(6)
+            .token_program_id(&token_program)
+            .send()
+            .unwrap();
+
+        let maker_ata_a = CreateAssociatedTokenAccount::new(&mut program, &payer, &mint_a)
+            .owner(&maker)
+            .token_program_id(&token_program)
+            .send()
+            .unwrap();
+        let taker_ata_b = CreateAssociatedTokenAccount::new(&mut program, &taker, &mint_b)
+            .owner(&taker.pubkey())
+            .token_program_id(&token_program)
+            .send()
+            .unwrap();
+        MintTo::new(&mut program, &payer, &mint_a, &maker_ata_a, 10 * 1000000)
+            .token_program_id(&token_program)
+            .send()
+            .unwrap();
+        MintTo::new(&mut program, &payer, &mint_b, &taker_ata_b, 10 * 1000000)
+            .token_program_id(&token_program)
+            .send()
+            .unwrap();
+
+        let escrow = escrow_address(&maker, 0);
+        let vault = vault_address(&escrow, &mint_a, &token_program);
+        let taker_ata_a = associated_token::get_associated_token_address_with_program_id(
+            &taker.pubkey(),
+            &mint_a,
+            &token_program,
+        );
+        let maker_ata_b = associated_token::get_associated_token_address_with_program_id(
+            &maker,
+            &mint_b,
+            &token_program,
+        );
+
+        // Extra accounts the hook needs, forwarded as remaining accounts
+        let (extra_account_meta_list, counter) = transfer_hook_accounts(&mint_a);
+        let hook_accounts = vec![
+            AccountMeta::new(counter, false),
+            AccountMeta::new_readonly(test_transfer_hook::ID, false),
+            AccountMeta::new_readonly(extra_account_meta_list, false),
+        ];
+
+        let mut make_ix = Make {
+            token_program,
+            ..make(maker, 0, mint_a, mint_b)
+        }
+        .instruction();
+        make_ix.accounts.extend(hook_accounts.clone());
+        send(&mut program, &[make_ix], &[&payer]).unwrap();
+
+        assert_eq!(token_2022_amount(&program, &vault), 10 * 1000000);
+
+        // The vault-PDA-signed leg of take runs the hook too
+        let mut take_ix = Take {
+            token_program,
+            ..take(taker.pubkey(), maker, 0, mint_a, mint_b)
+        }
+        .instruction();
+        take_ix.accounts.extend(hook_accounts);
+        let take_tx = send(&mut program, &[take_ix], &[&taker]).unwrap();
+        msg!("\n\nTake transaction sucessfull");
+        msg!("Cu consumed , {}", take_tx.compute_units_consumed);
+
+        assert_eq!(token_2022_amount(&program, &taker_ata_a), 10 * 1000000);
+        assert_eq!(token_2022_amount(&program, &maker_ata_b), 10 * 1000000);
+
+        let counter_account = program.get_account(&counter).unwrap();
+        let counter_data =
+            test_transfer_hook::Counter::try_deserialize(&mut counter_account.data.as_ref())
+                .unwrap();
+        assert_eq!(counter_data.transfers, 2);
+    }
+
+    #[test]
+    fn test_take_native_sol_legs() {
+        let (mut program, payer) = setup();
+        let maker = payer.pubkey();
+        let taker = Keypair::new();
+        program
+            .airdrop(&taker.pubkey(), 10 * LAMPORTS_PER_SOL)
+            .expect("failed airdrop at taker ");
+        let mint = CreateMint::new(&mut program, &payer)
+            .authority(&maker)
+            .decimals(6)
+            .send()
+            .unwrap();
+        let maker_ata = CreateAssociatedTokenAccount::new(&mut program, &payer, &mint)
+            .owner(&maker)
+            .send()
+            .unwrap();
+        let taker_ata = CreateAssociatedTokenAccount::new(&mut program, &taker, &mint)
+            .owner(&taker.pubkey())
+            .send()
+            .unwrap();
+        MintTo::new(&mut program, &payer, &mint, &maker_ata, 10 * 1000000)
+            .send()
+            .unwrap();
+        MintTo::new(&mut program, &payer, &mint, &taker_ata, 10 * 1000000)
+            .send()
+            .unwrap();
+
+        // Escrow 0 offers 2 SOL held by the escrow PDA, for 10 tokens
+        let sol_offer = escrow_address(&maker, 0);
+        // Escrow 1 offers 10 tokens for 1 SOL paid straight to the maker
+        let token_offer = escrow_address(&maker, 1);
+        let vault = vault_address(&token_offer, &mint, &TOKEN_PROGRAM_ID);
+
+        let make_sol_offer_ix = Make {
+            mint_a: None,
+            deposit: 2 * LAMPORTS_PER_SOL,
+            ..make(maker, 0, mint, mint)
+        }
+        .instruction();
+        let make_token_offer_ix = Make {
+            mint_b: None,
+            receive: LAMPORTS_PER_SOL,
+            ..make(maker, 1, mint, mint)
+        }
+        .instruction();
+        send(&mut program, &[make_sol_offer_ix, make_token_offer_ix], &[&payer]).unwrap();
+
+        let escrow_account = program.get_account(&sol_offer).unwrap();
+        let escrow_data =
+            crate::state::Escrow::try_deserialize(&mut escrow_account.data.as_ref()).unwrap();
+        assert_eq!(escrow_data.mint_a, crate::state::NATIVE_SOL);
+        assert_eq!(escrow_data.deposit, 2 * LAMPORTS_PER_SOL);
+        assert!(escrow_account.lamports > 2 * LAMPORTS_PER_SOL);
+
+        // The two escrows took the maker's first two sequential seeds
+        let maker_state_account = program.get_account(&maker_state_address(&maker)).unwrap();
+        let maker_state_data =
+            crate::state::MakerState::try_deserialize(&mut maker_state_account.data.as_ref())
+                .unwrap();
+        assert_eq!(maker_state_data.next_seed, 2);
+        assert_eq!(maker_state_data.open_offers, 2);
+
+        let take_sol_offer_ix = Take {
+            mint_a: None,
+            ..take(taker.pubkey(), maker, 0, mint, mint)
+        }
+        .instruction();
+        let take_token_offer_ix = Take {
+            mint_b: None,
+            ..take(taker.pubkey(), maker, 1, mint, mint)
+        }
+        .instruction();
+
+        let maker_lamports = program.get_account(&maker).unwrap().lamports;
+        let taker_lamports = program.get_account(&taker.pubkey()).unwrap().lamports;
+
+        let take_tx =
+            send(&mut program, &[take_sol_offer_ix, take_token_offer_ix], &[&taker]).unwrap();
+        msg!("\n\nNative take transaction sucessfull");
+        msg!("Cu consumed , {}", take_tx.compute_units_consumed);
+
+        // Tokens only swapped hands between the two legs, SOL net flowed to the taker
+        let taker_ata_data = program.get_account(&taker_ata).unwrap();
+        let taker_ata_metadata = spl_token::state::Account::unpack(&taker_ata_data.data).unwrap();
+        assert_eq!(taker_ata_metadata.amount, 10 * 1000000);
+        let taker_lamports_after = program.get_account(&taker.pubkey()).unwrap().lamports;
+        assert!(taker_lamports_after > taker_lamports + LAMPORTS_PER_SOL - LAMPORTS_PER_SOL / 100);
+
+        // The maker got 1 SOL from the second leg plus the rent of both closed escrows
+        let maker_lamports_after = program.get_account(&maker).unwrap().lamports;
+        assert!(maker_lamports_after > maker_lamports + LAMPORTS_PER_SOL);
+
+        let escrow_account = program.get_account(&sol_offer).unwrap();
+        assert!(escrow_account.data.is_empty(), "Escrow should be closed");
+        let vault_account = program.get_account(&vault).unwrap();
+        assert!(vault_account.data.is_empty(), "Vault should be closed");
+
+        let maker_state_account = program.get_account(&maker_state_address(&maker)).unwrap();
+        let maker_state_data =
+            crate::state::MakerState::try_deserialize(&mut maker_state_account.data.as_ref())
+                .unwrap();
+        assert_eq!(maker_state_data.next_seed, 2);
+        assert_eq!(maker_state_data.open_offers, 0);
+    }
+
+    #[test]
+    fn test_take_basket() {
+        let (mut program, payer) = setup();
+        let maker = payer.pubkey();
+        let taker = Keypair::new();
+        program
+            .airdrop(&taker.pubkey(), 10 * LAMPORTS_PER_SOL)
+            .expect("failed airdrop at taker ");
+
+        // The maker offers two mints, the taker pays in a third one
+        let mut offered_mints = Vec::new();
+        for _ in 0..2 {
+            let mint = CreateMint::new(&mut program, &payer)
+                .authority(&maker)
+                .decimals(6)
+                .send()
+                .unwrap();
+            let maker_ata = CreateAssociatedTokenAccount::new(&mut program, &payer, &mint)
+                .owner(&maker)
+                .send()
+                .unwrap();
+            MintTo::new(&mut program, &payer, &mint, &maker_ata, 10 * 1000000)
+                .send()
+                .unwrap();
+            offered_mints.push(mint);
+        }
+        let mint_c = CreateMint::new(&mut program, &payer)
+            .authority(&maker)
+            .decimals(6)
+            .send()
+            .unwrap();
+        let taker_ata_c = CreateAssociatedTokenAccount::new(&mut program, &taker, &mint_c)
+            .owner(&taker.pubkey())
+            .send()
+            .unwrap();
+        MintTo::new(&mut program, &payer, &mint_c, &taker_ata_c, 10 * 1000000)
+            .send()
+            .unwrap();
+        let maker_ata_c = associated_token::get_associated_token_address(&maker, &mint_c);
+
+        // The basket takes the maker's first seed, as a first escrow would
+        let basket = basket_address(&maker, 0);
+
+        let make_ix = MakeBasket {
+            maker,
+            seed: 0,
+            offered: vec![(offered_mints[0], 10 * 1000000), (offered_mints[1], 4 * 1000000)],
+            requested: vec![BasketLeg {
+                mint: mint_c,
+                amount: 5 * 1000000,
+            }],
+            token_program: TOKEN_PROGRAM_ID,
+        }
+        .instruction();
+        send(&mut program, &[make_ix], &[&payer]).unwrap();
+
+        let basket_account = program.get_account(&basket).unwrap();
+        let basket_data =
+            crate::state::BasketEscrow::try_deserialize(&mut basket_account.data.as_ref())
+                .unwrap();
+        assert_eq!(basket_data.maker, maker);
+        assert_eq!(basket_data.seed, 0);
+        assert_eq!(basket_data.offered.len(), 2);
+        assert_eq!(basket_data.offered[0].mint, offered_mints[0]);
+        assert_eq!(basket_data.offered[0].amount, 10 * 1000000);
+        assert_eq!(basket_data.offered[1].amount, 4 * 1000000);
+        assert_eq!(basket_data.requested[0].mint, mint_c);
+
+        // The seed is used up, but a basket is not one of the maker's open escrows
+        let maker_state_account = program.get_account(&maker_state_address(&maker)).unwrap();
+        let maker_state_data =
+            crate::state::MakerState::try_deserialize(&mut maker_state_account.data.as_ref())
+                .unwrap();
+        assert_eq!(maker_state_data.next_seed, 1);
+        assert_eq!(maker_state_data.open_offers, 0);
+
+        let take_ix = TakeBasket {
+            taker: taker.pubkey(),
+            maker,
+            seed: 0,
+            offered: offered_mints.clone(),
+            requested: vec![mint_c],
+            treasury: TREASURY,
+            token_program: TOKEN_PROGRAM_ID,
+        }
+        .instruction();
+        let take_tx = send(&mut program, &[take_ix], &[&taker]).unwrap();
+        msg!("\n\nBasket take transaction sucessfull");
+        msg!("Cu consumed , {}", take_tx.compute_units_consumed);
+
+        for (mint, expected) in offered_mints.iter().zip([10 * 1000000, 4 * 1000000]) {
+            let taker_ata = associated_token::get_associated_token_address(&taker.pubkey(), mint);
+            let taker_ata_data = program.get_account(&taker_ata).unwrap();
+            let taker_ata_metadata =
+                spl_token::state::Account::unpack(&taker_ata_data.data).unwrap();
+            assert_eq!(taker_ata_metadata.amount, expected);
+            let vault = associated_token::get_associated_token_address(&basket, mint);
+            let vault_account = program.get_account(&vault).unwrap();
+            assert!(vault_account.data.is_empty(), "Vault should be closed");
+        }
+        let maker_ata_c_data = program.get_account(&maker_ata_c).unwrap();
+        let maker_ata_c_metadata =
+            spl_token::state::Account::unpack(&maker_ata_c_data.data).unwrap();
+        assert_eq!(maker_ata_c_metadata.amount, 5 * 1000000);
+        let basket_account = program.get_account(&basket).unwrap();
+        assert!(basket_account.data.is_empty(), "Basket should be closed");
+    }
+
+    #[test]
+    fn test_amend_and_top_up() {
+        let Fixture {
+            mut program,
+            payer,
+            taker,
+            mint_a,
+            mint_b,
+            maker_ata_a,
+            maker_ata_b,
+            taker_ata_a,
+            taker_ata_b,
+        } = fixture();
+        let maker = payer.pubkey();
+
+        let escrow = escrow_address(&maker, 0);
+
+        MintTo::new(&mut program, &payer, &mint_a, &maker_ata_a, 10 * 1000000)
+            .send()
+            .unwrap();
+        MintTo::new(&mut program, &payer, &mint_b, &taker_ata_b, 20 * 1000000)
+            .send()
+            .unwrap();
+
+        // Offer 10 A for 20 B, then reprice to 30 B and add another 5 A
+        let make_ix = Make {
+            receive: 20 * 1000000,
+            ..make(maker, 0, mint_a, mint_b)
+        }
+        .instruction();
+        let amend_ix = Amend {
+            maker,
+            seed: 0,
+            receive: 30 * 1000000,
+            receipt_holder: None,
+        }
+        .instruction();
+        let top_up_ix = TopUp {
+            maker,
+            seed: 0,
+            mint_a: Some(mint_a),
+            amount: 5 * 1000000,
+            receipt_holder: None,
+            token_program: TOKEN_PROGRAM_ID,
+        }
+        .instruction();
+        send(&mut program, &[make_ix, amend_ix, top_up_ix], &[&payer]).unwrap();
+
+        let escrow_account = program.get_account(&escrow).unwrap();
+        let escrow_data =
+            crate::state::Escrow::try_deserialize(&mut escrow_account.data.as_ref()).unwrap();
+        assert_eq!(escrow_data.deposit, 15 * 1000000);
+        assert_eq!(escrow_data.receive, 30 * 1000000);
+        assert_eq!(escrow_data.version, 2);
+
+        let take_ix = |expected_version: u32| {
+            Take {
+                expected_version,
+                ..take(taker.pubkey(), maker, 0, mint_a, mint_b)
+            }
+            .instruction()
+        };
+
+        // A taker who priced the original terms is rejected
+        assert!(send(&mut program, &[take_ix(0)], &[&taker]).is_err());
+
+        send(&mut program, &[take_ix(2)], &[&taker]).unwrap();
+
+        let taker_ata_a_data = program.get_account(&taker_ata_a).unwrap();
+        let taker_ata_a_metadata =
+            spl_token::state::Account::unpack(&taker_ata_a_data.data).unwrap();
+        assert_eq!(taker_ata_a_metadata.amount, 15 * 1000000);
+
+        let maker_ata_b_data = program.get_account(&maker_ata_b).unwrap();
+        let maker_ata_b_metadata =
+            spl_token::state::Account::unpack(&maker_ata_b_data.data).unwrap();
+        assert_eq!(maker_ata_b_metadata.amount, 30 * 1000000);
+    }
+
+    #[test]
+    fn test_protocol_fee_and_pause() {
+        let Fixture {
+            mut program,
+            payer,
+            taker,
+            mint_a,
+            mint_b,
+            maker_ata_b,
+            taker_ata_a,
+            taker_ata_b,
+            ..
+        } = fixture();
+        let maker = payer.pubkey();
+        let treasury_ata_b = associated_token::get_associated_token_address(&TREASURY, &mint_b);
+
+        MintTo::new(&mut program, &payer, &mint_b, &taker_ata_b, 10 * 1000000)
+            .send()
+            .unwrap();
+
+        // Charge 2.5% and pause the protocol
+        let admin = UpdateConfig { admin: maker };
+        let update_config_ix = admin.update_config(250, TREASURY);
+        send(&mut program, &[update_config_ix, admin.set_paused(true)], &[&payer]).unwrap();
+
+        let config_account = program.get_account(&config_address()).unwrap();
+        let config_data =
+            crate::state::Config::try_deserialize(&mut config_account.data.as_ref()).unwrap();
+        assert_eq!(config_data.fee_bps, 250);
+        assert!(config_data.paused);
+
+        // Only the admin can touch the config
+        let rogue_ix = UpdateConfig { admin: taker.pubkey() }.set_paused(false);
+        assert!(send(&mut program, &[rogue_ix], &[&taker]).is_err());
+
+        // Offer 10 A for 20 B
+        let make_ix = Make {
+            receive: 20 * 1000000,
+            ..make(maker, 0, mint_a, mint_b)
+        }
+        .instruction();
+
+        // Making is rejected while paused
+        assert!(send(&mut program, &[make_ix.clone()], &[&payer]).is_err());
+
+        send(&mut program, &[admin.set_paused(false), make_ix], &[&payer]).unwrap();
+
+        let take_ix = take(taker.pubkey(), maker, 0, mint_a, mint_b).instruction();
+        let take_tx = send(&mut program, &[take_ix], &[&taker]).unwrap();
+        msg!("\n\nTake with protocol fee transaction sucessfull");
+        msg!("Cu consumed , {}", take_tx.compute_units_consumed);
+
+        // 2.5% of the 20 B goes to the treasury, the maker gets the rest
+        let maker_ata_b_data = program.get_account(&maker_ata_b).unwrap();
+        let maker_ata_b_metadata =
+            spl_token::state::Account::unpack(&maker_ata_b_data.data).unwrap();
+        assert_eq!(maker_ata_b_metadata.amount, 19_500_000);
+
+        let treasury_ata_b_data = program.get_account(&treasury_ata_b).unwrap();
+        let treasury_ata_b_metadata =
+            spl_token::state::Account::unpack(&treasury_ata_b_data.data).unwrap();
+        assert_eq!(treasury_ata_b_metadata.amount, 500_000);
+
+        let taker_ata_a_data = program.get_account(&taker_ata_a).unwrap();
+        let taker_ata_a_metadata =
+            spl_token::state::Account::unpack(&taker_ata_a_data.data).unwrap();
+        assert_eq!(taker_ata_a_metadata.amount, 10 * 1000000);
+    }
+
+    #[test]
+    fn test_take_dutch_auction() {
+        let Fixture {
+            mut program,
+            payer,
+            taker,
+            mint_a,
+            mint_b,
+            maker_ata_b,
+            taker_ata_a,
+            taker_ata_b,
+            ..
+        } = fixture();
+        let maker = payer.pubkey();
+
+        MintTo::new(&mut program, &payer, &mint_b, &taker_ata_b, 20 * 1000000)
+            .send()
+            .unwrap();
+
+        // Offer 10 A starting at 20 B, decaying to 10 B over 1000 seconds
+        let now = program.get_sysvar::<Clock>().unix_timestamp;
+        let make_ix = Make {
+            receive: 20 * 1000000,
+            pricing: Pricing::DutchAuction(DutchAuction {
+                floor_receive: 10 * 1000000,
+                decay_start: now,
+                decay_end: now + 1000,
+            }),
+            ..make(maker, 0, mint_a, mint_b)
+        }
+        .instruction();
+        send(&mut program, &[make_ix], &[&payer]).unwrap();
+
+        // Halfway through the window the price is 15 B
+        let mut clock = program.get_sysvar::<Clock>();
+        clock.unix_timestamp = now + 500;
+        program.set_sysvar::<Clock>(&clock);
+
+        let take_ix = |max_payment: u64| {
+            Take {
+                max_payment,
+                ..take(taker.pubkey(), maker, 0, mint_a, mint_b)
+            }
+            .instruction()
+        };
+
+        // A taker capped below the current price is rejected
+        assert!(send(&mut program, &[take_ix(14 * 1000000)], &[&taker]).is_err());
+
+        let take_tx = send(&mut program, &[take_ix(15 * 1000000)], &[&taker]).unwrap();
+        msg!("\n\nDutch auction take transaction sucessfull");
+        msg!("Cu consumed , {}", take_tx.compute_units_consumed);
+
+        let maker_ata_b_data = program.get_account(&maker_ata_b).unwrap();
+        let maker_ata_b_metadata =
+            spl_token::state::Account::unpack(&maker_ata_b_data.data).unwrap();
+        assert_eq!(maker_ata_b_metadata.amount, 15 * 1000000);
+
+        let taker_ata_a_data = program.get_account(&taker_ata_a).unwrap();
+        let taker_ata_a_metadata =
+            spl_token::state::Account::unpack(&taker_ata_a_data.data).unwrap();
+        assert_eq!(taker_ata_a_metadata.amount, 10 * 1000000);
+    }
+
+    #[test]
+    fn test_take_oracle_priced() {
+        let Fixture {
+            mut program,
+            payer,
+            taker,
+            mint_a,
+            mint_b,
+            maker_ata_b,
+            taker_ata_a,
+            taker_ata_b,
+            ..
+        } = fixture();
+        let maker = payer.pubkey();
+        let price_update = Pubkey::new_unique();
+        let feed_id = [3u8; 32];
+
+        MintTo::new(&mut program, &payer, &mint_b, &taker_ata_b, 20 * 1000000)
+            .send()
+            .unwrap();
+
+        // Offer 10 A at the oracle price less 2%, never for less than 1 B
+        let make_ix = Make {
+            receive: 1000000,
+            pricing: Pricing::Oracle(OraclePricing {
+                feed_id,
+                premium_bps: -200,
+                max_staleness: 60,
+                max_confidence_bps: 100,
+            }),
+            ..make(maker, 0, mint_a, mint_b)
+        }
+        .instruction();
+        send(&mut program, &[make_ix], &[&payer]).unwrap();
+
+        let take_ix = Take {
+            max_payment: 24_500_000,
+            price_update: Some(price_update),
+            ..take(taker.pubkey(), maker, 0, mint_a, mint_b)
+        }
+        .instruction();
+        let now = program.get_sysvar::<Clock>().unix_timestamp;
+
+        // A price older than a minute is rejected
+        set_price_update(&mut program, price_update, feed_id, 250_000_000, 10_000, now - 120);
+        assert!(send(&mut program, &[take_ix.clone()], &[&taker]).is_err());
+
+        // So is one whose confidence interval is wider than 1%
+        set_price_update(&mut program, price_update, feed_id, 250_000_000, 5_000_000, now);
+        program.expire_blockhash();
+        assert!(send(&mut program, &[take_ix.clone()], &[&taker]).is_err());
+
+        // 1 A = 2.5 B, so 10 A less 2% costs 24.5 B
+        set_price_update(&mut program, price_update, feed_id, 250_000_000, 10_000, now);
+        program.expire_blockhash();
+        let take_tx = send(&mut program, &[take_ix], &[&taker]).unwrap();
+        msg!("\n\nOracle priced take transaction sucessfull");
+        msg!("Cu consumed , {}", take_tx.compute_units_consumed);
+
+        let maker_ata_b_data = program.get_account(&maker_ata_b).unwrap();
+        let maker_ata_b_metadata =
+            spl_token::state::Account::unpack(&maker_ata_b_data.data).unwrap();
+        assert_eq!(maker_ata_b_metadata.amount, 24_500_000);
+
+        let taker_ata_a_data = program.get_account(&taker_ata_a).unwrap();
+        let taker_ata_a_metadata =
+            spl_token::state::Account::unpack(&taker_ata_a_data.data).unwrap();
+        assert_eq!(taker_ata_a_metadata.amount, 10 * 1000000);
+    }
+
+    #[test]
+    fn test_take_vesting_claim() {
+        let Fixture {
+            mut program,
+            payer,
+            taker,
+            mint_a,
+            mint_b,
+            maker_ata_b,
+            taker_ata_a,
+            taker_ata_b,
+            ..
+        } = fixture();
+        let maker = payer.pubkey();
+
+        let escrow = escrow_address(&maker, 0);
+        let vault = vault_address(&escrow, &mint_a, &TOKEN_PROGRAM_ID);
+
+        MintTo::new(&mut program, &payer, &mint_b, &taker_ata_b, 10 * 1000000)
+            .send()
+            .unwrap();
+
+        // Sell 10 A for 20 B, released over 1000 seconds after a 100 second cliff
+        let make_ix = Make {
+            receive: 20 * 1000000,
+            vesting: Some(Vesting {
+                cliff: 100,
+                duration: 1000,
+            }),
+            ..make(maker, 0, mint_a, mint_b)
+        }
+        .instruction();
+        send(&mut program, &[make_ix], &[&payer]).unwrap();
+
+        let take_ix = take(taker.pubkey(), maker, 0, mint_a, mint_b).instruction();
+        let take_tx = send(&mut program, &[take_ix], &[&taker]).unwrap();
+        msg!("\n\nVesting take transaction sucessfull");
+        msg!("Cu consumed , {}", take_tx.compute_units_consumed);
+
+        // The maker is paid at once while A stays locked in the vault
+        let maker_ata_b_data = program.get_account(&maker_ata_b).unwrap();
+        let maker_ata_b_metadata =
+            spl_token::state::Account::unpack(&maker_ata_b_data.data).unwrap();
+        assert_eq!(maker_ata_b_metadata.amount, 20 * 1000000);
+
+        let escrow_account = program.get_account(&escrow).unwrap();
+        let escrow_data =
+            crate::state::Escrow::try_deserialize(&mut escrow_account.data.as_ref()).unwrap();
+        assert_eq!(escrow_data.beneficiary, Some(taker.pubkey()));
+        let start = escrow_data.vesting_start;
+
+        let claim_ix = Claim {
+            beneficiary: taker.pubkey(),
+            maker,
+            seed: 0,
+            mint_a: Some(mint_a),
+            token_program: TOKEN_PROGRAM_ID,
+        }
+        .instruction();
+        let mut claim_at = |program: &mut LiteSVM, time: i64| {
+            let mut clock = program.get_sysvar::<Clock>();
+            clock.unix_timestamp = time;
+            program.set_sysvar::<Clock>(&clock);
+            program.expire_blockhash();
+
+            send(program, &[claim_ix.clone()], &[&taker])
+        };
+        let taker_a = |program: &LiteSVM| {
+            let taker_ata_a_data = program.get_account(&taker_ata_a).unwrap();
+            spl_token::state::Account::unpack(&taker_ata_a_data.data)
+                .unwrap()
+                .amount
+        };
+
+        // Nothing is claimable before the cliff
+        assert!(claim_at(&mut program, start + 50).is_err());
+
+        // A quarter of the way through, a quarter of A has vested
+        claim_at(&mut program, start + 250).unwrap();
+        assert_eq!(taker_a(&program), 2500000);
+
+        // The maker cannot pull the vested tokens back
+        let refund_ix = Refund {
+            maker,
+            seed: 0,
+            mint_a: Some(mint_a),
+            receipt_holder: None,
+            refund_to: None,
+            reserver: None,
+            token_program: TOKEN_PROGRAM_ID,
+        }
+        .instruction();
+        assert!(send(&mut program, &[refund_ix], &[&payer]).is_err());
+
+        // The last claim releases the rest and closes the vault and escrow
+        claim_at(&mut program, start + 1000).unwrap();
+        assert_eq!(taker_a(&program), 10 * 1000000);
+        let vault_account = program.get_account(&vault).unwrap();
+        assert!(vault_account.data.is_empty(), "Vault should be closed");
+
+        let escrow_account = program.get_account(&escrow).unwrap();
+        assert!(escrow_account.data.is_empty(), "Escrow should be closed");
+    }
+
+    #[test]
+    fn test_arbiter_dispute_split() {
+        let Fixture {
+            mut program,
+            payer,
+            taker,
+            mint_a,
+            mint_b,
+            maker_ata_a,
+            maker_ata_b,
+            taker_ata_a,
+            taker_ata_b,
+        } = fixture();
+        let maker = payer.pubkey();
+        let arbiter = Keypair::new();
+        program
+            .airdrop(&arbiter.pubkey(), LAMPORTS_PER_SOL)
+            .expect("failed airdrop at arbiter ");
+
+        let escrow = escrow_address(&maker, 0);
+        let vault_b = vault_address(&escrow, &mint_b, &TOKEN_PROGRAM_ID);
+
+        MintTo::new(&mut program, &payer, &mint_b, &taker_ata_b, 10 * 1000000)
+            .send()
+            .unwrap();
+
+        let make_ix = |mint_b: Pubkey| {
+            Make {
+                receive: 20 * 1000000,
+                arbiter: Some(arbiter.pubkey()),
+                ..make(maker, 0, mint_a, mint_b)
+            }
+            .instruction()
+        };
+
+        // Both legs would share one escrow ATA, so an arbiter needs two different mints
+        assert_program_error(
+            send(&mut program, &[make_ix(mint_a)], &[&payer]),
+            crate::error::ErrorCode::InvalidArbiter,
+        );
+
+        send(&mut program, &[make_ix(mint_b)], &[&payer]).unwrap();
+
+        let take_ix = |taker: Pubkey| {
+            Take {
+                arbitrated: true,
+                ..take(taker, maker, 0, mint_a, mint_b)
+            }
+            .instruction()
+        };
+
+        // The arbiter cannot take the escrow and then resolve the dispute in its own favour
+        CreateAssociatedTokenAccount::new(&mut program, &arbiter, &mint_b)
+            .owner(&arbiter.pubkey())
+            .send()
+            .unwrap();
+        assert_program_error(
+            send(&mut program, &[take_ix(arbiter.pubkey())], &[&arbiter]),
+            crate::error::ErrorCode::ArbiterCannotTake,
+        );
+
+        send(&mut program, &[take_ix(taker.pubkey())], &[&taker]).unwrap();
+
+        // Both legs now sit in the escrow's vaults
+        let vault_b_data = program.get_account(&vault_b).unwrap();
+        let vault_b_metadata = spl_token::state::Account::unpack(&vault_b_data.data).unwrap();
+        assert_eq!(vault_b_metadata.amount, 20 * 1000000);
+
+        let settle = |authority: Pubkey| Settle {
+            authority,
+            maker,
+            taker: taker.pubkey(),
+            seed: 0,
+            mint_a: Some(mint_a),
+            mint_b: Some(mint_b),
+            token_program: TOKEN_PROGRAM_ID,
+        };
+
+        let dispute_ix = Dispute {
+            party: taker.pubkey(),
+            maker,
+            seed: 0,
+        }
+        .instruction();
+        send(&mut program, &[dispute_ix], &[&taker]).unwrap();
+
+        // A disputed escrow can no longer be approved, only resolved by the arbiter
+        assert!(send(&mut program, &[settle(maker).approve()], &[&payer]).is_err());
+
+        assert!(send(&mut program, &[settle(maker).resolve(2500)], &[&payer]).is_err());
+
+        let resolve_tx =
+            send(&mut program, &[settle(arbiter.pubkey()).resolve(2500)], &[&arbiter]).unwrap();
+        msg!("\n\nResolve transaction sucessfull");
+        msg!("Cu consumed , {}", resolve_tx.compute_units_consumed);
+
+        // The taker gets a quarter of each leg and the maker the rest
+        let amount = |program: &LiteSVM, ata: &Pubkey| {
+            let ata_data = program.get_account(ata).unwrap();
+            spl_token::state::Account::unpack(&ata_data.data)
+                .unwrap()
+                .amount
+        };
+        assert_eq!(amount(&program, &taker_ata_a), 2500000);
+        assert_eq!(amount(&program, &maker_ata_a), 7500000);
+        assert_eq!(amount(&program, &taker_ata_b), 5000000);
+        assert_eq!(amount(&program, &maker_ata_b), 15000000);
+
+        let escrow_account = program.get_account(&escrow).unwrap();
+        assert!(escrow_account.data.is_empty(), "Escrow should be closed");
+    }
+
+    #[test]
+    fn test_take_partial_allowlist() {
+        let Fixture { mut program, payer, taker, mint_a, mint_b, taker_ata_a, .. } = fixture();
+        let maker = payer.pubkey();
+        let outsider = Keypair::new();
+        program
+            .airdrop(&outsider.pubkey(), 10 * LAMPORTS_PER_SOL)
+            .expect("failed airdrop at outsider ");
+
+        let escrow = escrow_address(&maker, 0);
+
+        // Two allowlisted buyers: the taker may pay up to 8 B, someone else up to 20 B
+        let taker_leaf = crate::merkle::leaf(&taker.pubkey(), 8 * 1000000);
+        let other_leaf = crate::merkle::leaf(&Pubkey::new_unique(), 20 * 1000000);
+        let root = crate::merkle::hash_pair(&taker_leaf, &other_leaf);
+
+        let make_ix = Make {
+            receive: 20 * 1000000,
+            allowlist_root: Some(root),
+            ..make(maker, 0, mint_a, mint_b)
+        }
+        .instruction();
+        send(&mut program, &[make_ix], &[&payer]).unwrap();
+
+        let outsider_ata_b = CreateAssociatedTokenAccount::new(&mut program, &outsider, &mint_b)
+            .owner(&outsider.pubkey())
+            .send()
+            .unwrap();
+        MintTo::new(&mut program, &payer, &mint_b, &outsider_ata_b, 20 * 1000000)
+            .send()
+            .unwrap();
+        let taker_fill = allowlist_fill_address(&escrow, &taker.pubkey());
+
+        // Both buyers present the taker's proof, which only matches the taker's own leaf
+        let take_partial_ix = |buyer: Pubkey, amount_b: u64| {
+            TakePartial {
+                taker: buyer,
+                maker,
+                seed: 0,
+                mint_a: Some(mint_a),
+                mint_b: Some(mint_b),
+                treasury: TREASURY,
+                amount_b,
+                expected_version: 0,
+                allowlist: Some(AllowlistProof {
+                    max_fill: 8 * 1000000,
+                    proof: vec![other_leaf],
+                }),
+                receipt_holder: None,
+                proceeds_to: None,
+                token_program: TOKEN_PROGRAM_ID,
+            }
+            .instruction()
+        };
+
+        let outsider_ix = take_partial_ix(outsider.pubkey(), 5 * 1000000);
+        assert!(send(&mut program, &[outsider_ix], &[&outsider]).is_err());
+
+        let taker_ix = take_partial_ix(taker.pubkey(), 5 * 1000000);
+        let take_tx = send(&mut program, &[taker_ix], &[&taker]).unwrap();
+        msg!("\n\nAllowlisted partial take transaction sucessfull");
+        msg!("Cu consumed , {}", take_tx.compute_units_consumed);
+
+        // The cap holds across fills: another 5 B would bring the taker to 10 B
+        program.expire_blockhash();
+        let over_cap_ix = take_partial_ix(taker.pubkey(), 5 * 1000000);
+        assert!(send(&mut program, &[over_cap_ix], &[&taker]).is_err());
+
+        send(&mut program, &[take_partial_ix(taker.pubkey(), 3 * 1000000)], &[&taker]).unwrap();
+
+        let fill_account = program.get_account(&taker_fill).unwrap();
+        let fill_data =
+            crate::state::AllowlistFill::try_deserialize(&mut fill_account.data.as_ref()).unwrap();
+        assert_eq!(fill_data.filled, 8 * 1000000);
+
+        let taker_ata_a_data = program.get_account(&taker_ata_a).unwrap();
+        let taker_ata_a_metadata =
+            spl_token::state::Account::unpack(&taker_ata_a_data.data).unwrap();
+        assert_eq!(taker_ata_a_metadata.amount, 4 * 1000000);
+    }
+
+    #[test]
+    fn test_take_many() {
+        let Fixture { mut program, payer, taker, mint_a, mint_b, taker_ata_a, taker_ata_b, .. } =
+            fixture();
+        let second_maker = Keypair::new();
+        program
+            .airdrop(&second_maker.pubkey(), 10 * LAMPORTS_PER_SOL)
+            .expect("failed airdrop at second maker ");
+        MintTo::new(&mut program, &payer, &mint_b, &taker_ata_b, 40 * 1000000)
+            .send()
+            .unwrap();
+        let second_maker_ata_a =
+            CreateAssociatedTokenAccount::new(&mut program, &second_maker, &mint_a)
+                .owner(&second_maker.pubkey())
+                .send()
+                .unwrap();
+        MintTo::new(&mut program, &payer, &mint_a, &second_maker_ata_a, 10 * 1000000)
+            .send()
+            .unwrap();
+
+        // Two makers each offer 10 A, for 20 B and 25 B
+        for (maker, receive) in [(&payer, 20 * 1000000), (&second_maker, 25 * 1000000)] {
+            let make_ix = Make {
+                receive,
+                ..make(maker.pubkey(), 0, mint_a, mint_b)
+            }
+            .instruction();
+            send(&mut program, &[make_ix], &[maker]).unwrap();
+        }
+        let escrows = vec![(payer.pubkey(), 0), (second_maker.pubkey(), 0)];
+
+        let take_many_ix = |max_total_payment: u64| {
+            TakeMany {
+                taker: taker.pubkey(),
+                mint_a,
+                mint_b,
+                treasury: TREASURY,
+                escrows: escrows.clone(),
+                max_total_payment,
+                token_program: TOKEN_PROGRAM_ID,
+            }
+            .instruction()
+        };
+
+        // The aggregate cap covers the whole batch, not each escrow
+        assert!(send(&mut program, &[take_many_ix(44 * 1000000)], &[&taker]).is_err());
+
+        let take_ix = take_many_ix(45 * 1000000);
+        let keys = account_keys(&[take_ix.clone()], &taker);
+        let take_tx = send(&mut program, &[take_ix], &[&taker]).unwrap();
+        msg!("\n\nBatch take transaction sucessfull");
+        msg!("Cu consumed , {}", take_tx.compute_units_consumed);
+
+        let taker_ata_a_data = program.get_account(&taker_ata_a).unwrap();
+        let taker_ata_a_metadata =
+            spl_token::state::Account::unpack(&taker_ata_a_data.data).unwrap();
+        assert_eq!(taker_ata_a_metadata.amount, 20 * 1000000);
+
+        let events = crate::events::decode_events(&keys, &take_tx.inner_instructions);
+        assert_eq!(events.len(), 2);
+
+        let makers = [(payer.pubkey(), 20 * 1000000), (second_maker.pubkey(), 25 * 1000000)];
+        for (maker, receive) in makers {
+            let maker_ata_b = associated_token::get_associated_token_address(&maker, &mint_b);
+            let maker_ata_b_data = program.get_account(&maker_ata_b).unwrap();
+            let maker_ata_b_metadata =
+                spl_token::state::Account::unpack(&maker_ata_b_data.data).unwrap();
+            assert_eq!(maker_ata_b_metadata.amount, receive);
+        }
+        for (maker, seed) in &escrows {
+            let escrow_account = program.get_account(&escrow_address(maker, *seed)).unwrap();
+            assert!(escrow_account.data.is_empty(), "Escrow should be closed");
+        }
+    }
+
+    #[test]
+    fn test_receipt_transfer_redirects_proceeds() {
+        let Fixture {
+            mut program,
+            payer,
+            taker,
+            mint_a,
+            mint_b,
+            maker_ata_b,
+            taker_ata_a,
+            taker_ata_b,
+            ..
+        } = fixture();
+        let maker = payer.pubkey();
+        let holder = Keypair::new();
+        program
+            .airdrop(&holder.pubkey(), 10 * LAMPORTS_PER_SOL)
+            .expect("failed airdrop at holder ");
+        MintTo::new(&mut program, &payer, &mint_b, &taker_ata_b, 10 * 1000000)
+            .send()
+            .unwrap();
+
+        let escrow = escrow_address(&maker, 0);
+        let receipt_mint = receipt_mint_address(&escrow);
+        let receipt_ata = |owner: &Pubkey| {
+            associated_token::get_associated_token_address_with_program_id(
+                owner,
+                &receipt_mint,
+                &spl_token_2022::ID,
+            )
+        };
+
+        let make_ix = Make {
+            receive: 20 * 1000000,
+            receipt: true,
+            ..make(maker, 0, mint_a, mint_b)
+        }
+        .instruction();
+        send(&mut program, &[make_ix], &[&payer]).unwrap();
+        assert_eq!(token_2022_amount(&program, &receipt_ata(&maker)), 1);
+
+        // The maker sells the position by handing the receipt over
+        let create_holder_receipt_ix =
+            spl_associated_token_account::instruction::create_associated_token_account(
+                &maker,
+                &holder.pubkey(),
+                &receipt_mint,
+                &spl_token_2022::ID,
+            );
+        let transfer_receipt_ix = spl_token_2022::instruction::transfer_checked(
+            &spl_token_2022::ID,
+            &receipt_ata(&maker),
+            &receipt_mint,
+            &receipt_ata(&holder.pubkey()),
+            &maker,
+            &[],
+            1,
+            0,
+        )
+        .unwrap();
+        send(&mut program, &[create_holder_receipt_ix, transfer_receipt_ix], &[&payer]).unwrap();
+
+        let take_ix = |receipt_holder: Pubkey| {
+            Take {
+                receipt_holder: Some(receipt_holder),
+                ..take(taker.pubkey(), maker, 0, mint_a, mint_b)
+            }
+            .instruction()
+        };
+
+        // The original maker no longer holds the receipt, so cannot be paid
+        assert!(send(&mut program, &[take_ix(maker)], &[&taker]).is_err());
+
+        let take_tx = send(&mut program, &[take_ix(holder.pubkey())], &[&taker]).unwrap();
+        msg!("\n\nReceipt holder take transaction sucessfull");
+        msg!("Cu consumed , {}", take_tx.compute_units_consumed);
+
+        let holder_ata_b =
+            associated_token::get_associated_token_address(&holder.pubkey(), &mint_b);
+        let holder_ata_b_data = program.get_account(&holder_ata_b).unwrap();
+        let holder_ata_b_metadata =
+            spl_token::state::Account::unpack(&holder_ata_b_data.data).unwrap();
+        assert_eq!(holder_ata_b_metadata.amount, 20 * 1000000);
+
+        let maker_ata_b_data = program.get_account(&maker_ata_b).unwrap();
+        let maker_ata_b_metadata =
+            spl_token::state::Account::unpack(&maker_ata_b_data.data).unwrap();
+        assert_eq!(maker_ata_b_metadata.amount, 0);
+
+        let taker_ata_a_data = program.get_account(&taker_ata_a).unwrap();
+        let taker_ata_a_metadata =
+            spl_token::state::Account::unpack(&taker_ata_a_data.data).unwrap();
+        assert_eq!(taker_ata_a_metadata.amount, 10 * 1000000);
+    }
+
+    #[test]
+    fn test_counter_offer_accept_and_reject() {
+        let Fixture {
+            mut program,
+            payer,
+            taker: first_taker,
+            mint_a,
+            mint_b,
+            maker_ata_a,
+            maker_ata_b,
+            taker_ata_b: first_taker_ata_b,
+            ..
+        } = fixture();
+        let maker = payer.pubkey();
+        let second_taker = Keypair::new();
+        program
+            .airdrop(&second_taker.pubkey(), 10 * LAMPORTS_PER_SOL)
+            .expect("failed airdrop at taker ");
+        let second_taker_ata_b =
+            CreateAssociatedTokenAccount::new(&mut program, &second_taker, &mint_b)
+                .owner(&second_taker.pubkey())
+                .send()
+                .unwrap();
+        MintTo::new(&mut program, &payer, &mint_b, &first_taker_ata_b, 10 * 1000000)
+            .send()
+            .unwrap();
+        MintTo::new(&mut program, &payer, &mint_b, &second_taker_ata_b, 20 * 1000000)
+            .send()
+            .unwrap();
+
+        let escrow = escrow_address(&maker, 0);
+        let counter = |taker: &Pubkey| counter_address(&escrow, taker);
+
+        let make_ix = Make {
+            receive: 20 * 1000000,
+            ..make(maker, 0, mint_a, mint_b)
+        }
+        .instruction();
+        send(&mut program, &[make_ix], &[&payer]).unwrap();
+
+        // The first taker escrows 15 B for the whole deposit, the second only
+        // delegates 18 B for 8 A
+        let counters = [
+            (&first_taker, 10 * 1000000, 15 * 1000000, true),
+            (&second_taker, 8 * 1000000, 18 * 1000000, false),
+        ];
+        for (taker, amount_a, amount_b, funded) in counters {
+            let post_counter_ix = PostCounter {
+                taker: taker.pubkey(),
+                maker,
+                seed: 0,
+                mint_b: Some(mint_b),
+                amount_a,
+                amount_b,
+                funded,
+                token_program: TOKEN_PROGRAM_ID,
+            }
+            .instruction();
+            send(&mut program, &[post_counter_ix], &[taker]).unwrap();
+        }
+
+        let first_counter = counter(&first_taker.pubkey());
+        let first_counter_vault =
+            associated_token::get_associated_token_address(&first_counter, &mint_b);
+        let first_counter_vault_data = program.get_account(&first_counter_vault).unwrap();
+        let first_counter_vault_metadata =
+            spl_token::state::Account::unpack(&first_counter_vault_data.data).unwrap();
+        assert_eq!(first_counter_vault_metadata.amount, 15 * 1000000);
+
+        // Rejecting hands the escrowed 15 B back to the first taker
+        let reject_counter_ix = RejectCounter {
+            authority: maker,
+            maker,
+            taker: first_taker.pubkey(),
+            seed: 0,
+            mint_b: Some(mint_b),
+            funded: true,
+            receipt_holder: None,
+            escrow_closed: false,
+            token_program: TOKEN_PROGRAM_ID,
+        }
+        .instruction();
+        send(&mut program, &[reject_counter_ix], &[&payer]).unwrap();
+
+        let first_taker_ata_b_data = program.get_account(&first_taker_ata_b).unwrap();
+        let first_taker_ata_b_metadata =
+            spl_token::state::Account::unpack(&first_taker_ata_b_data.data).unwrap();
+        assert_eq!(first_taker_ata_b_metadata.amount, 20 * 1000000);
+        let first_counter_account = program.get_account(&first_counter).unwrap();
+        assert!(first_counter_account.data.is_empty(), "Counter should be closed");
+
+        // Accepting the second counter pulls 18 B through the delegation and
+        // returns the 2 A it did not ask for
+        let second_taker_ata_a =
+            associated_token::get_associated_token_address(&second_taker.pubkey(), &mint_a);
+        let accept_counter_ix = AcceptCounter {
+            maker,
+            taker: second_taker.pubkey(),
+            seed: 0,
+            mint_a: Some(mint_a),
+            mint_b: Some(mint_b),
+            treasury: TREASURY,
+            funded: false,
+            receipt_holder: None,
+            token_program: TOKEN_PROGRAM_ID,
+        }
+        .instruction();
+
+        // Once the taker revokes the delegation the counter can no longer pay
+        let revoke_ix = spl_token::instruction::revoke(
+            &TOKEN_PROGRAM_ID,
+            &second_taker_ata_b,
+            &second_taker.pubkey(),
+            &[],
+        )
+        .unwrap();
+        send(&mut program, &[revoke_ix], &[&second_taker]).unwrap();
+
+        assert_program_error(
+            send(&mut program, &[accept_counter_ix.clone()], &[&payer]),
+            crate::error::ErrorCode::CounterNotDelegated,
+        );
+
+        // Approving the counter again for its full amount makes it acceptable
+        let approve_ix = spl_token::instruction::approve(
+            &TOKEN_PROGRAM_ID,
+            &second_taker_ata_b,
+            &counter(&second_taker.pubkey()),
+            &second_taker.pubkey(),
+            &[],
+            18 * 1000000,
+        )
+        .unwrap();
+        send(&mut program, &[approve_ix], &[&second_taker]).unwrap();
+        program.expire_blockhash();
+
+        let accept_tx = send(&mut program, &[accept_counter_ix], &[&payer]).unwrap();
+        msg!("\n\nAccept counter transaction sucessfull");
+        msg!("Cu consumed , {}", accept_tx.compute_units_consumed);
+
+        let expected = [
+            (maker_ata_a, 2 * 1000000),
+            (maker_ata_b, 18 * 1000000),
+            (second_taker_ata_a, 8 * 1000000),
+            (second_taker_ata_b, 2 * 1000000),
+        ];
+        for (token_account, amount) in expected {
+            let token_account_data = program.get_account(&token_account).unwrap();
+            let token_account_metadata =
+                spl_token::state::Account::unpack(&token_account_data.data).unwrap();
+            assert_eq!(token_account_metadata.amount, amount);
+        }
+        let escrow_account = program.get_account(&escrow).unwrap();
+        assert!(escrow_account.data.is_empty(), "Escrow should be closed");
+    }
+
+    #[test]
+    fn test_match_escrows() {
+        // X is the fixture's maker with its 10 A, Y is the taker topped up to 25 B
+        let Fixture {
+            mut program,
+            payer: maker_x,
+            taker: maker_y,
+            mint_a,
+            mint_b,
+            maker_ata_b: maker_x_ata_b,
+            taker_ata_a: maker_y_ata_a,
+            taker_ata_b: maker_y_ata_b,
+            ..
+        } = fixture();
+        let matcher = Keypair::new();
+        program
+            .airdrop(&matcher.pubkey(), 10 * LAMPORTS_PER_SOL)
+            .expect("failed airdrop ");
+        MintTo::new(&mut program, &maker_x, &mint_b, &maker_y_ata_b, 15 * 1000000)
+            .send()
+            .unwrap();
+
+        // X offers 10 A for 20 B, Y offers 25 B for 8 A
+        let make_x_ix = Make {
+            receive: 20 * 1000000,
+            ..make(maker_x.pubkey(), 0, mint_a, mint_b)
+        }
+        .instruction();
+        send(&mut program, &[make_x_ix], &[&maker_x]).unwrap();
+        let make_y_ix = Make {
+            deposit: 25 * 1000000,
+            receive: 8 * 1000000,
+            ..make(maker_y.pubkey(), 0, mint_b, mint_a)
+        }
+        .instruction();
+        send(&mut program, &[make_y_ix], &[&maker_y]).unwrap();
+        let escrow_x = escrow_address(&maker_x.pubkey(), 0);
+        let escrow_y = escrow_address(&maker_y.pubkey(), 0);
+
+        let ata = associated_token::get_associated_token_address;
+        let matcher_ata_a = ata(&matcher.pubkey(), &mint_a);
+        let matcher_ata_b = ata(&matcher.pubkey(), &mint_b);
+
+        let match_ix = MatchEscrows {
+            matcher: matcher.pubkey(),
+            maker_x: maker_x.pubkey(),
+            seed_x: 0,
+            maker_y: maker_y.pubkey(),
+            seed_y: 0,
+            mint_a,
+            mint_b,
+            treasury: TREASURY,
+            token_program: TOKEN_PROGRAM_ID,
+        }
+        .instruction();
+        let match_tx = send(&mut program, &[match_ix], &[&matcher]).unwrap();
+        msg!("\n\nMatch transaction sucessfull");
+        msg!("Cu consumed , {}", match_tx.compute_units_consumed);
+
+        // Each maker gets exactly its price, the matcher keeps 2 A and 5 B
+        let expected = [
+            (maker_x_ata_b, 20 * 1000000),
+            (maker_y_ata_a, 8 * 1000000),
+            (matcher_ata_a, 2 * 1000000),
+            (matcher_ata_b, 5 * 1000000),
+        ];
+        for (token_account, amount) in expected {
+            let token_account_data = program.get_account(&token_account).unwrap();
+            let token_account_metadata =
+                spl_token::state::Account::unpack(&token_account_data.data).unwrap();
+            assert_eq!(token_account_metadata.amount, amount);
+        }
+        for escrow in [escrow_x, escrow_y] {
+            let escrow_account = program.get_account(&escrow).unwrap();
+            assert!(escrow_account.data.is_empty(), "Escrow should be closed");
+        }
+    }
+
+    #[test]
+    fn test_make_for_pda_maker() {
+        let Fixture { mut program, payer, taker, mint_a, mint_b, taker_ata_a, taker_ata_b, .. } =
+            fixture();
+        MintTo::new(&mut program, &payer, &mint_b, &taker_ata_b, 10 * 1000000)
+            .send()
+            .unwrap();
+
+        let maker_data = std::fs::read(
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../target/deploy/test_pda_maker.so"),
+        )
+        .expect("Failed to read PDA maker SO file");
+        program.add_program(test_pda_maker::ID, &maker_data);
+
+        let authority = Pubkey::find_program_address(&[b"authority"], &test_pda_maker::ID).0;
+
+        // The PDA keeps its tokens in plain token accounts, not ATAs
+        let authority_token_a = litesvm_token::CreateAccount::new(&mut program, &payer, &mint_a)
+            .owner(&authority)
+            .send()
+            .unwrap();
+        let proceeds_to = litesvm_token::CreateAccount::new(&mut program, &payer, &mint_b)
+            .owner(&authority)
+            .send()
+            .unwrap();
+        MintTo::new(&mut program, &payer, &mint_a, &authority_token_a, 20 * 1000000)
+            .send()
+            .unwrap();
+
+        let escrow_accounts = |seed: u64| {
+            let escrow = escrow_address(&authority, seed);
+            (escrow, vault_address(&escrow, &mint_a, &TOKEN_PROGRAM_ID))
+        };
+        // The PDA maker's own instructions, which CPI into `make_for` and `refund`
+        let make_ix = |escrow: Pubkey, vault: Pubkey| Instruction {
+            program_id: test_pda_maker::ID,
+            accounts: test_pda_maker::accounts::MakeEscrow {
+                payer: payer.pubkey(),
+                authority,
+                mint_a,
+                mint_b,
+                authority_token_a,
+                proceeds_to,
+                config: config_address(),
+                maker_state: maker_state_address(&authority),
+                escrow,
+                vault,
+                event_authority: event_authority_address(),
+                escrow_program: PROGRAM_ID,
+                associated_token_program: spl_associated_token_account::ID,
+                token_program: TOKEN_PROGRAM_ID,
+                system_program: SYSTEM_PROGRAM_ID,
+            }
+            .to_account_metas(None),
+            data: test_pda_maker::instruction::MakeEscrow {
+                deposit: 10 * 1000000,
+                receive: 20 * 1000000,
+            }
+            .data(),
+        };
+
+        // The PDA makes an escrow through CPI and refunds it to its own token account
+        let (escrow, vault) = escrow_accounts(0);
+        let make_tx = send(&mut program, &[make_ix(escrow, vault)], &[&payer]).unwrap();
+        msg!("\n\nMake for PDA transaction sucessfull");
+        msg!("Cu consumed , {}", make_tx.compute_units_consumed);
+
+        let escrow_account = program.get_account(&escrow).unwrap();
+        let escrow_data =
+            crate::state::Escrow::try_deserialize(&mut escrow_account.data.as_ref()).unwrap();
+        assert_eq!(escrow_data.maker, authority);
+        assert_eq!(escrow_data.proceeds_to, Some(proceeds_to));
+        assert_eq!(escrow_data.refund_to, Some(authority_token_a));
+
+        let refund_ix = Instruction {
+            program_id: test_pda_maker::ID,
+            accounts: test_pda_maker::accounts::RefundEscrow {
+                authority,
+                mint_a,
+                refund_to: authority_token_a,
+                escrow,
+                maker_state: maker_state_address(&authority),
+                vault,
+                event_authority: event_authority_address(),
+                escrow_program: PROGRAM_ID,
+                token_program: TOKEN_PROGRAM_ID,
+                system_program: SYSTEM_PROGRAM_ID,
+            }
+            .to_account_metas(None),
+            data: test_pda_maker::instruction::RefundEscrow {}.data(),
+        };
+        send(&mut program, &[refund_ix], &[&payer]).unwrap();
+
+        let token_account_data = program.get_account(&authority_token_a).unwrap();
+        let token_account_metadata =
+            spl_token::state::Account::unpack(&token_account_data.data).unwrap();
+        assert_eq!(token_account_metadata.amount, 20 * 1000000);
+
+        // A second escrow is taken, its payment goes to the PDA's proceeds account
+        let (escrow, vault) = escrow_accounts(1);
+        send(&mut program, &[make_ix(escrow, vault)], &[&payer]).unwrap();
+
+        let take_ix = Take {
+            proceeds_to: Some(proceeds_to),
+            ..take(taker.pubkey(), authority, 1, mint_a, mint_b)
+        }
+        .instruction();
+        send(&mut program, &[take_ix], &[&taker]).unwrap();
+
+        let expected = [(proceeds_to, 20 * 1000000), (taker_ata_a, 10 * 1000000)];
+        for (token_account, amount) in expected {
+            let token_account_data = program.get_account(&token_account).unwrap();
+            let token_account_metadata =
+                spl_token::state::Account::unpack(&token_account_data.data).unwrap();
+            assert_eq!(token_account_metadata.amount, amount);
+        }
+    }
+
+    #[test]
+    fn test_take_signed_offer() {
+        let Fixture {
+            mut program,
+            payer: maker,
+            taker,
+            mint_a,
+            mint_b,
+            maker_ata_a,
+            maker_ata_b,
+            taker_ata_a,
+            taker_ata_b,
+        } = fixture();
+        MintTo::new(&mut program, &maker, &mint_a, &maker_ata_a, 20 * 1000000)
+            .send()
+            .unwrap();
+        MintTo::new(&mut program, &maker, &mint_b, &taker_ata_b, 30 * 1000000)
+            .send()
+            .unwrap();
+
+        // The maker backs its quotes with a delegate approval instead of a deposit
+        let approve_ix = spl_token::instruction::approve(
+            &TOKEN_PROGRAM_ID,
+            &maker_ata_a,
+            &offer_delegate_address(&maker.pubkey()),
+            &maker.pubkey(),
+            &[],
+            20 * 1000000,
+        )
+        .unwrap();
+        send(&mut program, &[approve_ix], &[&maker]).unwrap();
+
+        let offer = |nonce: u64| SignedOffer {
+            mint_a,
+            mint_b,
+            amount_a: 10 * 1000000,
+            amount_b: 20 * 1000000,
+            expiry_time: i64::MAX,
+            nonce,
+        };
+        // Ed25519 precompile check of `signer`'s signature over `offer`
+        let signature_ix = |signer: &Keypair, offer: &SignedOffer| {
+            let signature = signer.sign_message(&offer.message().unwrap());
+            offer_signature_instruction(
+                &signer.pubkey(),
+                signature.as_ref().try_into().unwrap(),
+                offer,
+            )
+        };
+        let take_ix = |offer: SignedOffer| {
+            TakeOffer {
+                taker: taker.pubkey(),
+                maker: maker.pubkey(),
+                offer,
+                treasury: TREASURY,
+                token_program: TOKEN_PROGRAM_ID,
+            }
+            .instruction()
+        };
+        let mut send_fresh = |instructions: &[Instruction], signer: &Keypair| {
+            program.expire_blockhash();
+            send(&mut program, instructions, &[signer])
+        };
+
+        let signed = signature_ix(&maker, &offer(5));
+        let take_tx = send_fresh(&[signed.clone(), take_ix(offer(5))], &taker).unwrap();
+        msg!("\n\nTake signed offer transaction sucessfull");
+        msg!("Cu consumed , {}", take_tx.compute_units_consumed);
+
+        // The same signature cannot be used twice, nor for different terms
+        assert!(send_fresh(&[signed.clone(), take_ix(offer(5))], &taker).is_err());
+        let mut richer = offer(6);
+        richer.amount_a = 20 * 1000000;
+        let signed = signature_ix(&maker, &offer(6));
+        assert!(send_fresh(&[signed, take_ix(richer)], &taker).is_err());
+        // A signature by anyone but the maker, or none at all, is rejected
+        let signed = signature_ix(&taker, &offer(6));
+        assert!(send_fresh(&[signed, take_ix(offer(6))], &taker).is_err());
+        assert!(send_fresh(&[take_ix(offer(6))], &taker).is_err());
+
+        // A cancelled nonce can no longer be taken
+        let cancel_ix = CancelOffer {
+            maker: maker.pubkey(),
+            nonce: 6,
+        }
+        .instruction();
+        send_fresh(&[cancel_ix], &maker).unwrap();
+        let signed = signature_ix(&maker, &offer(6));
+        assert!(send_fresh(&[signed, take_ix(offer(6))], &taker).is_err());
+
+        let expected = [
+            (maker_ata_a, 20 * 1000000),
+            (maker_ata_b, 20 * 1000000),
+            (taker_ata_a, 10 * 1000000),
+            (taker_ata_b, 20 * 1000000),
+        ];
+        for (token_account, amount) in expected {
+            let token_account_data = program.get_account(&token_account).unwrap();
+            let token_account_metadata =
+                spl_token::state::Account::unpack(&token_account_data.data).unwrap();
+            assert_eq!(token_account_metadata.amount, amount);
+        }
+    }
+
+    #[test]
+    fn test_reserve_with_bond() {
+        let Fixture {
+            mut program,
+            payer: maker,
+            taker,
+            mint_a,
+            mint_b,
+            maker_ata_a,
+            taker_ata_b,
+            ..
+        } = fixture();
+        let reserver = Keypair::new();
+        program
+            .airdrop(&reserver.pubkey(), 10 * LAMPORTS_PER_SOL)
+            .expect("failed airdrop ");
+        MintTo::new(&mut program, &maker, &mint_a, &maker_ata_a, 10 * 1000000)
+            .send()
+            .unwrap();
+        MintTo::new(&mut program, &maker, &mint_b, &taker_ata_b, 10 * 1000000)
+            .send()
+            .unwrap();
+
+        let bond = LAMPORTS_PER_SOL;
+
+        let send = |program: &mut LiteSVM, instructions: &[Instruction], signer: &Keypair| {
+            program.expire_blockhash();
+            send(program, instructions, &[signer])
+        };
+        let escrow_accounts = |seed: u64| {
+            let escrow = escrow_address(&maker.pubkey(), seed);
+            (escrow, vault_address(&escrow, &mint_a, &TOKEN_PROGRAM_ID))
+        };
+        // Offers 10 A for 20 B and lets takers reserve it for up to an hour
+        let make_ixs = |seed: u64| {
+            let make_ix = Make {
+                receive: 20 * 1000000,
+                ..make(maker.pubkey(), seed, mint_a, mint_b)
+            }
+            .instruction();
+            let terms_ix = SetReservationTerms {
+                maker: maker.pubkey(),
+                seed,
+                terms: Some(ReservationTerms {
+                    bond,
+                    max_duration: 60 * 60,
+                }),
+            }
+            .instruction();
+            [make_ix, terms_ix]
+        };
+        let reserve_ix = |seed: u64, duration: i64| {
+            Reserve {
+                taker: reserver.pubkey(),
+                maker: maker.pubkey(),
+                seed,
+                duration,
+            }
+            .instruction()
+        };
+        let take_ix =
+            |seed: u64| take(taker.pubkey(), maker.pubkey(), seed, mint_a, mint_b).instruction();
+        let top_up_ix = |seed: u64| {
+            TopUp {
+                maker: maker.pubkey(),
+                seed,
+                mint_a: Some(mint_a),
+                amount: 5 * 1000000,
+                receipt_holder: None,
+                token_program: TOKEN_PROGRAM_ID,
+            }
+            .instruction()
+        };
+        let refund_ix = |seed: u64, reserver: Option<Pubkey>| {
+            Refund {
+                maker: maker.pubkey(),
+                seed,
+                mint_a: Some(mint_a),
+                receipt_holder: None,
+                refund_to: None,
+                reserver,
+                token_program: TOKEN_PROGRAM_ID,
+            }
+            .instruction()
+        };
+
+        // Reservations are capped by the terms, and while one is held nobody else can take
+        send(&mut program, &make_ixs(0), &maker).unwrap();
+        assert!(send(&mut program, &[reserve_ix(0, 2 * 60 * 60)], &reserver).is_err());
+        send(&mut program, &[reserve_ix(0, 10 * 60)], &reserver).unwrap();
+        let reserver_lamports = program.get_account(&reserver.pubkey()).unwrap().lamports;
+        assert!(send(&mut program, &[take_ix(0)], &taker).is_err());
+
+        // Nor can the maker change the terms under the reserver, topping up included
+        assert_program_error(
+            send(&mut program, &[top_up_ix(0)], &maker),
+            crate::error::ErrorCode::Reserved,
+        );
+
+        // The maker can only back out by paying the reserver their bond plus a penalty
+        assert!(send(&mut program, &[refund_ix(0, None)], &maker).is_err());
+        send(&mut program, &[refund_ix(0, Some(reserver.pubkey()))], &maker).unwrap();
+        assert_eq!(
+            program.get_account(&reserver.pubkey()).unwrap().lamports,
+            reserver_lamports + 2 * bond
+        );
+        let token_account_data = program.get_account(&maker_ata_a).unwrap();
+        let token_account_metadata =
+            spl_token::state::Account::unpack(&token_account_data.data).unwrap();
+        assert_eq!(token_account_metadata.amount, 20 * 1000000);
+
+        // Once a reservation lapses anyone can take, and the untaken bond is the maker's
+        let (escrow, vault) = escrow_accounts(1);
+        send(&mut program, &make_ixs(1), &maker).unwrap();
+        send(&mut program, &[reserve_ix(1, 10 * 60)], &reserver).unwrap();
+        let reserver_lamports = program.get_account(&reserver.pubkey()).unwrap().lamports;
+        let escrow_lamports = program.get_account(&escrow).unwrap().lamports;
+        let vault_lamports = program.get_account(&vault).unwrap().lamports;
+
+        let mut clock = program.get_sysvar::<Clock>();
+        clock.unix_timestamp += 10 * 60;
+        program.set_sysvar::<Clock>(&clock);
+
+        let maker_lamports = program.get_account(&maker.pubkey()).unwrap().lamports;
+        send(&mut program, &[take_ix(1)], &taker).unwrap();
+        assert_eq!(
+            program.get_account(&reserver.pubkey()).unwrap().lamports,
+            reserver_lamports
+        );
+        // Closing the escrow hands the maker its rent and the bond along with it
+        assert_eq!(
+            program.get_account(&maker.pubkey()).unwrap().lamports,
+            maker_lamports + escrow_lamports + vault_lamports
+        );
+        assert!(escrow_lamports > bond);
+    }
+
+    #[test]
+    fn test_make_basket_rejects_fee_mint() {
+        let (mut program, payer) = setup();
+        let maker = payer.pubkey();
+        let token_program = spl_token_2022::ID;
+
+        // A transfer-fee leg would leave the taker short, so the basket is refused up front
+        let mint = create_transfer_fee_mint(&mut program, &payer, 100);
+        let maker_ata = CreateAssociatedTokenAccount::new(&mut program, &payer, &mint)
+            .owner(&maker)
+            .token_program_id(&token_program)
+            .send()
+            .unwrap();
+        MintTo::new(&mut program, &payer, &mint, &maker_ata, 10 * 1000000)
+            .token_program_id(&token_program)
+            .send()
+            .unwrap();
+
+        let make_ix = MakeBasket {
+            maker,
+            seed: 0,
+            offered: vec![(mint, 10 * 1000000)],
+            requested: vec![BasketLeg {
+                mint: Pubkey::new_unique(),
+                amount: 5 * 1000000,
+            }],
+            token_program,
+        }
+        .instruction();
+        assert_program_error(
+            send(&mut program, &[make_ix], &[&payer]),
+            crate::error::ErrorCode::UnsupportedBasketMint,
+        );
+    }
+
+    #[test]
+    fn test_initialize_config_requires_upgrade_authority() {
+        let mut program = LiteSVM::new();
+        let deployer = Keypair::new();
+        let front_runner = Keypair::new();
+        program.airdrop(&front_runner.pubkey(), LAMPORTS_PER_SOL).unwrap();
+
+        let so_path =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../target/deploy/anchor_escrow.so");
+        let program_data = std::fs::read(so_path).expect("Failed to read program SO file");
+        deploy_upgradeable(&mut program, &program_data, deployer.pubkey());
+
+        // Someone other than the upgrade authority cannot claim the admin role
+        let initialize_config_ix = InitializeConfig {
+            admin: front_runner.pubkey(),
+            fee_bps: 0,
+            treasury: front_runner.pubkey(),
+        }
+        .instruction();
+        assert_program_error(
+            send(&mut program, &[initialize_config_ix], &[&front_runner]),
+            crate::error::ErrorCode::NotUpgradeAuthority,
+        );
+        assert!(program.get_account(&config_address()).is_none());
+    }
+
+    #[test]
+    fn test_decode_events_ignores_spoofed_program() {
+        use anchor_lang::{Event, event::EVENT_IX_TAG_LE};
+        use anchor_lang::solana_program::message::{
+            compiled_instruction::CompiledInstruction, inner_instruction::InnerInstruction,
+        };
+
+        let refunded = crate::events::EscrowRefunded {
+            escrow: Pubkey::new_unique(),
+            maker: Pubkey::new_unique(),
+            seed: 1,
+            mint_a: Pubkey::new_unique(),
+            amount: 10 * 1000000,
+            expired: false,
+            timestamp: 0,
+        };
+        let data = [EVENT_IX_TAG_LE, &refunded.data()].concat();
+
+        // An impostor emits byte-identical event data from its own program and event authority
+        let impostor = Pubkey::new_unique();
+        let impostor_authority =
+            Pubkey::find_program_address(&[b"__event_authority"], &impostor).0;
+        let account_keys = [impostor, impostor_authority, PROGRAM_ID, event_authority_address()];
+        let self_cpi = |program_id_index: u8, authority_index: u8| InnerInstruction {
+            instruction: CompiledInstruction {
+                program_id_index,
+                accounts: vec![authority_index],
+                data: data.clone(),
+            },
+            stack_height: 2,
+        };
+
+        let spoofed = vec![vec![self_cpi(0, 1)]];
+        assert!(crate::events::decode_events(&account_keys, &spoofed).is_empty());
+
+        // Targeting this program without its event authority is not an event either
+        let unsigned = vec![vec![self_cpi(2, 1)]];
+        assert!(crate::events::decode_events(&account_keys, &unsigned).is_empty());
+
+        let genuine = vec![vec![self_cpi(0, 1), self_cpi(2, 3)]];
+        assert_eq!(
+            crate::events::decode_events(&account_keys, &genuine),
+            vec![crate::events::EscrowEvent::Refunded(refunded)],
+        );
+    }
+
+    #[test]
+    fn test_take_counts_partial_fills_against_allowlist_cap() {
+        let Fixture { mut program, payer, taker, mint_a, mint_b, .. } = fixture();
+        let maker = payer.pubkey();
+
+        let escrow = escrow_address(&maker, 0);
+        let taker_fill = allowlist_fill_address(&escrow, &taker.pubkey());
+
+        // The taker may pay up to 8 B towards an escrow asking 10 B
+        let taker_leaf = crate::merkle::leaf(&taker.pubkey(), 8 * 1000000);
+        let other_leaf = crate::merkle::leaf(&Pubkey::new_unique(), 20 * 1000000);
+        let root = crate::merkle::hash_pair(&taker_leaf, &other_leaf);
+        let proof = AllowlistProof {
+            max_fill: 8 * 1000000,
+            proof: vec![other_leaf],
+        };
+
+        let make_ix = Make {
+            allowlist_root: Some(root),
+            ..make(maker, 0, mint_a, mint_b)
+        }
+        .instruction();
+        send(&mut program, &[make_ix], &[&payer]).unwrap();
+
+        let take_partial_ix = TakePartial {
+            taker: taker.pubkey(),
+            maker,
+            seed: 0,
+            mint_a: Some(mint_a),
+            mint_b: Some(mint_b),
+            treasury: TREASURY,
+            amount_b: 8 * 1000000,
+            expected_version: 0,
+            allowlist: Some(proof.clone()),
+            receipt_holder: None,
+            proceeds_to: None,
+            token_program: TOKEN_PROGRAM_ID,
+        }
+        .instruction();
+        send(&mut program, &[take_partial_ix], &[&taker]).unwrap();
+
+        // The remaining 2 B fit the cap on their own, but not on top of the 8 B already filled
+        let take_ix = Take {
+            allowlist: Some(proof),
+            ..take(taker.pubkey(), maker, 0, mint_a, mint_b)
+        }
+        .instruction();
+        assert_program_error(
+            send(&mut program, &[take_ix], &[&taker]),
+            crate::error::ErrorCode::AllowlistFillExceeded,
+        );
+
+        let fill_account = program.get_account(&taker_fill).unwrap();
+        let fill_data =
+            crate::state::AllowlistFill::try_deserialize(&mut fill_account.data.as_ref()).unwrap();
+        assert_eq!(fill_data.filled, 8 * 1000000);
+    }
+
+    #[test]
+    fn test_take_many_full_batch_fits_default_budget() {
+        let Fixture { mut program, payer, taker, mint_a, mint_b, taker_ata_a, taker_ata_b, .. } =
+            fixture();
+        MintTo::new(&mut program, &payer, &mint_b, &taker_ata_b, 90 * 1000000)
+            .send()
+            .unwrap();
+        // The treasury already holds B, as it would after its first fee
+        CreateAssociatedTokenAccount::new(&mut program, &payer, &mint_b)
+            .owner(&TREASURY)
+            .send()
+            .unwrap();
+
+        // A separate maker per escrow, so every one brings its own accounts and maker ATA
+        let mut escrows = Vec::new();
+        for _ in 0..crate::instructions::take_many::MAX_TAKE_MANY {
+            let maker = Keypair::new();
+            program
+                .airdrop(&maker.pubkey(), 10 * LAMPORTS_PER_SOL)
+                .expect("failed airdrop at maker ");
+            let maker_ata_a = CreateAssociatedTokenAccount::new(&mut program, &maker, &mint_a)
+                .owner(&maker.pubkey())
+                .send()
+                .unwrap();
+            MintTo::new(&mut program, &payer, &mint_a, &maker_ata_a, 10 * 1000000)
+                .send()
+                .unwrap();
+
+            let make_ix = Make {
+                receive: 20 * 1000000,
+                ..make(maker.pubkey(), 0, mint_a, mint_b)
+            }
+            .instruction();
+            send(&mut program, &[make_ix], &[&maker]).unwrap();
+            escrows.push((maker.pubkey(), 0));
+        }
+
+        let take_many_ix = TakeMany {
+            taker: taker.pubkey(),
+            mint_a,
+            mint_b,
+            treasury: TREASURY,
+            escrows,
+            max_total_payment: u64::MAX,
+            token_program: TOKEN_PROGRAM_ID,
+        }
+        .instruction();
+
+        // No compute budget instruction, so the batch runs on the default limit
+        let take_tx = send(&mut program, &[take_many_ix], &[&taker]).unwrap();
+        msg!("\n\nFull batch take transaction sucessfull");
+        msg!("Cu consumed , {}", take_tx.compute_units_consumed);
+        assert!(take_tx.compute_units_consumed <= 200_000);
+
+        let taker_ata_a_data = program.get_account(&taker_ata_a).unwrap();
+        let taker_ata_a_metadata =
+            spl_token::state::Account::unpack(&taker_ata_a_data.data).unwrap();
+        assert_eq!(
+            taker_ata_a_metadata.amount,
+            crate::instructions::take_many::MAX_TAKE_MANY as u64 * 10 * 1000000
+        );
+    }
+
+    #[test]
+    fn test_refund_and_expire_transfer_fee_mint() {
+        let (mut program, payer) = setup();
+        let maker = payer.pubkey();
+        let cranker = Keypair::new();
+        program
+            .airdrop(&cranker.pubkey(), LAMPORTS_PER_SOL)
+            .expect("failed airdrop at cranker ");
+
+        // The offered mint charges 1%, so every vault ends up holding withheld fees
+        let mint_a = create_transfer_fee_mint(&mut program, &payer, 100);
+        let token_program = spl_token_2022::ID;
+        let mint_b = CreateMint::new(&mut program, &payer)
+            .authority(&maker)
+            .decimals(6)
+            .token_program_id(&token_program)
+            .send()
+            .unwrap();
+        let maker_ata_a = CreateAssociatedTokenAccount::new(&mut program, &payer, &mint_a)
+            .owner(&maker)
+            .token_program_id(&token_program)
+            .send()
+            .unwrap();
+        MintTo::new(&mut program, &payer, &mint_a, &maker_ata_a, 20 * 1000000)
+            .token_program_id(&token_program)
+            .send()
+            .unwrap();
+
+        let expiry_time = program.get_sysvar::<Clock>().unix_timestamp + 60 * 60;
+        let vault =
+            |seed: u64| vault_address(&escrow_address(&maker, seed), &mint_a, &token_program);
+
+        for seed in 0..2u64 {
+            let make_ix = Make {
+                receive: 5 * 1000000,
+                windows: TimeWindows {
+                    take_open_time: 0,
+                    expiry_time,
+                    refund_lock_time: 0,
+                },
+                token_program,
+                ..make(maker, seed, mint_a, mint_b)
+            }
+            .instruction();
+            send(&mut program, &[make_ix], &[&payer]).unwrap();
+        }
+
+        // The maker refunds the first escrow
+        let refund_ix = Refund {
+            maker,
+            seed: 0,
+            mint_a: Some(mint_a),
+            receipt_holder: None,
+            refund_to: None,
+            reserver: None,
+            token_program,
+        }
+        .instruction();
+        send(&mut program, &[refund_ix], &[&payer]).unwrap();
+        assert!(program.get_account(&vault(0)).is_none_or(|account| account.lamports == 0));
+        assert_eq!(token_2022_amount(&program, &maker_ata_a), 9_801_000);
+
+        // A cranker expires the second one
+        let mut clock = program.get_sysvar::<Clock>();
+        clock.unix_timestamp = expiry_time;
+        program.set_sysvar::<Clock>(&clock);
+
+        let expire_ix = Expire {
+            maker,
+            seed: 1,
+            mint_a: Some(mint_a),
+            receipt_holder: None,
+            refund_to: None,
+            token_program,
+        }
+        .instruction();
+        send(&mut program, &[expire_ix], &[&cranker]).unwrap();
+        assert!(program.get_account(&vault(1)).is_none_or(|account| account.lamports == 0));
+        assert_eq!(token_2022_amount(&program, &maker_ata_a), 2 * 9_801_000);
+
+        // The fees withheld in both vaults were harvested to the mint before closing
+        let mint_account = program.get_account(&mint_a).unwrap();
+        let mint_state = spl_token_2022::extension::StateWithExtensions::<
+            spl_token_2022::state::Mint,
+        >::unpack(&mint_account.data)
+        .unwrap();
+        let fee_config = spl_token_2022::extension::BaseStateWithExtensions::get_extension::<
+            spl_token_2022::extension::transfer_fee::TransferFeeConfig,
+        >(&mint_state)
+        .unwrap();
+        assert_eq!(u64::from(fee_config.withheld_amount), 2 * 100_000);
+    }
 }
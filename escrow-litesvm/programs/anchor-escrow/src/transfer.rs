@@ -0,0 +1,66 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token_2022::spl_token_2022::{
+        self,
+        extension::{transfer_fee::TransferFeeAmount, BaseStateWithExtensions, StateWithExtensions},
+        onchain,
+    },
+    token_2022_extensions::{harvest_withheld_tokens_to_mint, HarvestWithheldTokensToMint},
+    token_interface::{self, CloseAccount, TransferChecked},
+};
+
+// Drop-in for `token_interface::transfer_checked` that also forwards the context's remaining
+// accounts, so Token-2022 mints with a transfer hook get the extra accounts their
+// `ExtraAccountMetaList` asks for. Mints without a hook just ignore them.
+pub fn transfer_checked<'info>(
+    ctx: CpiContext<'_, '_, '_, 'info, TransferChecked<'info>>,
+    amount: u64,
+    decimals: u8,
+) -> Result<()> {
+    onchain::invoke_transfer_checked(
+        ctx.program.key,
+        ctx.accounts.from,
+        ctx.accounts.mint,
+        ctx.accounts.to,
+        ctx.accounts.authority,
+        &ctx.remaining_accounts,
+        amount,
+        decimals,
+        ctx.signer_seeds,
+    )
+    .map_err(Into::into)
+}
+
+// Drop-in for `token_interface::close_account` that first sweeps the Token-2022 transfer fees
+// withheld in the account into `mint`, since Token-2022 refuses to close an account still
+// holding them. Every deposit of a transfer-fee mint leaves some in the receiving vault, which
+// is why callers mark the mint writable.
+pub fn close_account<'info>(
+    ctx: CpiContext<'_, '_, '_, 'info, CloseAccount<'info>>,
+    mint: AccountInfo<'info>,
+) -> Result<()> {
+    if withheld_fees(&ctx.accounts.account)? > 0 {
+        let cpi_accounts = HarvestWithheldTokensToMint {
+            token_program_id: ctx.program.clone(),
+            mint,
+        };
+        harvest_withheld_tokens_to_mint(
+            CpiContext::new(ctx.program.clone(), cpi_accounts),
+            vec![ctx.accounts.account.clone()],
+        )?;
+    }
+
+    token_interface::close_account(ctx)
+}
+
+fn withheld_fees(account: &AccountInfo) -> Result<u64> {
+    if *account.owner != spl_token_2022::ID {
+        return Ok(0);
+    }
+
+    let data = account.try_borrow_data()?;
+    let state = StateWithExtensions::<spl_token_2022::state::Account>::unpack(&data)?;
+    Ok(state
+        .get_extension::<TransferFeeAmount>()
+        .map_or(0, |fee_amount| fee_amount.withheld_amount.into()))
+}
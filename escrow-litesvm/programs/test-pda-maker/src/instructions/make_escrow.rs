@@ -0,0 +1,87 @@
+use anchor_escrow::{
+    cpi::{accounts::MakeFor, make_for},
+    program::AnchorEscrow,
+    state::{Pricing, TimeWindows},
+};
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+#[derive(Accounts)]
+pub struct MakeEscrow<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: Signs as the escrow maker, holds no data
+    #[account(seeds = [b"authority"], bump)]
+    pub authority: UncheckedAccount<'info>,
+    pub mint_a: InterfaceAccount<'info, Mint>,
+    pub mint_b: InterfaceAccount<'info, Mint>,
+    // Any `mint_a` account of the authority, not necessarily its ATA
+    #[account(mut, token::mint = mint_a, token::authority = authority)]
+    pub authority_token_a: InterfaceAccount<'info, TokenAccount>,
+    pub proceeds_to: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: Checked by the escrow program
+    pub config: UncheckedAccount<'info>,
+    /// CHECK: Checked by the escrow program
+    #[account(mut)]
+    pub maker_state: UncheckedAccount<'info>,
+    /// CHECK: Checked by the escrow program
+    #[account(mut)]
+    pub escrow: UncheckedAccount<'info>,
+    /// CHECK: Checked by the escrow program
+    #[account(mut)]
+    pub vault: UncheckedAccount<'info>,
+    /// CHECK: Checked by the escrow program
+    pub event_authority: UncheckedAccount<'info>,
+    pub escrow_program: Program<'info, AnchorEscrow>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+// Offers `deposit` of `mint_a` for `receive` of `mint_b`, with refunds going back
+// to the account the deposit came from
+impl<'info> MakeEscrow<'info> {
+    pub fn make_escrow(&self, deposit: u64, receive: u64, bumps: &MakeEscrowBumps) -> Result<()> {
+        let signer_seeds: [&[&[u8]]; 1] = [&[b"authority", &[bumps.authority]]];
+
+        let cpi_program = self.escrow_program.to_account_info();
+
+        let cpi_accounts = MakeFor {
+            payer: self.payer.to_account_info(),
+            maker: self.authority.to_account_info(),
+            mint_a: self.mint_a.to_account_info(),
+            mint_b: self.mint_b.to_account_info(),
+            maker_token_a: self.authority_token_a.to_account_info(),
+            proceeds_to: self.proceeds_to.to_account_info(),
+            refund_to: self.authority_token_a.to_account_info(),
+            config: self.config.to_account_info(),
+            maker_state: self.maker_state.to_account_info(),
+            escrow: self.escrow.to_account_info(),
+            vault: self.vault.to_account_info(),
+            associated_token_program: self.associated_token_program.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+            system_program: self.system_program.to_account_info(),
+            event_authority: self.event_authority.to_account_info(),
+            program: self.escrow_program.to_account_info(),
+        };
+
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, &signer_seeds);
+
+        make_for(
+            cpi_ctx,
+            deposit,
+            receive,
+            TimeWindows {
+                take_open_time: 0,
+                expiry_time: i64::MAX,
+                refund_lock_time: 0,
+            },
+            None,
+            false,
+            Pricing::Fixed,
+        )
+    }
+}
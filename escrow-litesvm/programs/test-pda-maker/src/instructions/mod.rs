@@ -0,0 +1,5 @@
+pub mod make_escrow;
+pub mod refund_escrow;
+
+pub use make_escrow::*;
+pub use refund_escrow::*;
@@ -0,0 +1,60 @@
+use anchor_escrow::{
+    cpi::{accounts::Refund, refund},
+    program::AnchorEscrow,
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenInterface};
+
+#[derive(Accounts)]
+pub struct RefundEscrow<'info> {
+    /// CHECK: Signs as the escrow maker and gets the escrow's rent back
+    #[account(mut, seeds = [b"authority"], bump)]
+    pub authority: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub mint_a: InterfaceAccount<'info, Mint>,
+    /// CHECK: Checked by the escrow program against the escrow's refund account
+    #[account(mut)]
+    pub refund_to: UncheckedAccount<'info>,
+    /// CHECK: Checked by the escrow program
+    #[account(mut)]
+    pub escrow: UncheckedAccount<'info>,
+    /// CHECK: Checked by the escrow program
+    #[account(mut)]
+    pub maker_state: UncheckedAccount<'info>,
+    /// CHECK: Checked by the escrow program
+    #[account(mut)]
+    pub vault: UncheckedAccount<'info>,
+    /// CHECK: Checked by the escrow program
+    pub event_authority: UncheckedAccount<'info>,
+    pub escrow_program: Program<'info, AnchorEscrow>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> RefundEscrow<'info> {
+    pub fn refund_escrow(&self, bumps: &RefundEscrowBumps) -> Result<()> {
+        let signer_seeds: [&[&[u8]]; 1] = [&[b"authority", &[bumps.authority]]];
+
+        let cpi_program = self.escrow_program.to_account_info();
+
+        let cpi_accounts = Refund {
+            maker: self.authority.to_account_info(),
+            mint_a: Some(self.mint_a.to_account_info()),
+            maker_ata_a: None,
+            refund_to: Some(self.refund_to.to_account_info()),
+            escrow: self.escrow.to_account_info(),
+            receipt: None,
+            reserver: None,
+            maker_state: self.maker_state.to_account_info(),
+            vault: Some(self.vault.to_account_info()),
+            token_program: self.token_program.to_account_info(),
+            system_program: self.system_program.to_account_info(),
+            event_authority: self.event_authority.to_account_info(),
+            program: self.escrow_program.to_account_info(),
+        };
+
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, &signer_seeds);
+
+        refund(cpi_ctx)
+    }
+}
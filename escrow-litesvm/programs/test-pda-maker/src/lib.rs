@@ -0,0 +1,24 @@
+#![allow(unexpected_cfgs)]
+#![allow(deprecated)]
+
+use anchor_lang::prelude::*;
+
+mod instructions;
+use instructions::*;
+
+declare_id!("6TQz3HNFqEfERfzyrKfgnSdkNNFSQwWUQk1gkLfMW5Qy");
+
+// Stands in for a DAO or vault program: its `authority` PDA owns the tokens
+// and is the maker of the escrows, signing for them through `invoke_signed`
+#[program]
+pub mod test_pda_maker {
+    use super::*;
+
+    pub fn make_escrow(ctx: Context<MakeEscrow>, deposit: u64, receive: u64) -> Result<()> {
+        ctx.accounts.make_escrow(deposit, receive, &ctx.bumps)
+    }
+
+    pub fn refund_escrow(ctx: Context<RefundEscrow>) -> Result<()> {
+        ctx.accounts.refund_escrow(&ctx.bumps)
+    }
+}
@@ -0,0 +1,5 @@
+pub mod init_extra_account_meta;
+pub mod transfer_hook;
+
+pub use init_extra_account_meta::*;
+pub use transfer_hook::*;
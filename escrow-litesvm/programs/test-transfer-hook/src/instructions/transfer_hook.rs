@@ -0,0 +1,36 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount};
+
+use crate::state::Counter;
+
+#[derive(Accounts)]
+pub struct TransferHook<'info> {
+    #[account(token::mint = mint)]
+    pub source_token: InterfaceAccount<'info, TokenAccount>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(token::mint = mint)]
+    pub destination_token: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: source token account owner, can be SystemAccount or PDA owned by another program
+    pub owner: UncheckedAccount<'info>,
+    /// CHECK: ExtraAccountMetaList Account
+    #[account(
+        seeds = [b"extra-account-metas", mint.key().as_ref()],
+        bump,
+    )]
+    pub extra_account_meta_list: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"counter", mint.key().as_ref()],
+        bump = counter.bump,
+    )]
+    pub counter: Account<'info, Counter>,
+}
+
+impl<'info> TransferHook<'info> {
+    pub fn transfer_hook(&mut self, amount: u64) -> Result<()> {
+        msg!("Hooked transfer of {} from {}", amount, self.owner.key());
+        self.counter.transfers += 1;
+
+        Ok(())
+    }
+}
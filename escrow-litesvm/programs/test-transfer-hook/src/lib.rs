@@ -0,0 +1,31 @@
+#![allow(unexpected_cfgs)]
+#![allow(deprecated)]
+
+use anchor_lang::prelude::*;
+
+mod instructions;
+mod state;
+use instructions::*;
+
+use spl_discriminator::SplDiscriminate;
+use spl_transfer_hook_interface::instruction::ExecuteInstruction;
+
+pub use state::Counter;
+
+declare_id!("2zD3uDKNUwPzxvTkDMSP4XM582Gh6DHjxSnzpCiFJx8w");
+
+#[program]
+pub mod test_transfer_hook {
+    use super::*;
+
+    pub fn initialize_extra_account_meta_list(
+        ctx: Context<InitializeExtraAccountMetaList>,
+    ) -> Result<()> {
+        ctx.accounts.initialize_extra_account_meta_list(&ctx.bumps)
+    }
+
+    #[instruction(discriminator = ExecuteInstruction::SPL_DISCRIMINATOR_SLICE)]
+    pub fn transfer_hook(ctx: Context<TransferHook>, amount: u64) -> Result<()> {
+        ctx.accounts.transfer_hook(amount)
+    }
+}
@@ -0,0 +1,9 @@
+use anchor_lang::prelude::*;
+
+// Counts hooked transfers of a mint, so tests can tell the hook actually ran
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct Counter {
+    pub transfers: u64,
+    pub bump: u8,
+}